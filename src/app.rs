@@ -1,7 +1,12 @@
 pub mod components;
+mod discovery;
+mod highlight;
 mod utils;
+mod vcs;
 pub mod views;
+mod watch;
 mod window;
+mod workspace;
 
 pub fn start() -> std::result::Result<(), iced::Error> {
     iced::application(
@@ -9,5 +14,6 @@ pub fn start() -> std::result::Result<(), iced::Error> {
         window::Window::update,
         window::Window::view,
     )
+    .subscription(window::Window::subscription)
     .run()
 }
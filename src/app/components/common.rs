@@ -1,2 +1,5 @@
+pub mod badge;
+pub mod dialog;
+pub mod diff_view;
 pub mod drawer;
 pub mod select;
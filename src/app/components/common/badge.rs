@@ -0,0 +1,16 @@
+use iced::widget::{container, text};
+use iced::{Element, Theme, border};
+
+/// 件数などを小さく表示するバッジ。style には container のスタイル関数を渡す。
+pub fn badge<'a, Message: 'a>(
+    label: impl Into<String>,
+    style: impl Fn(&Theme) -> container::Style + 'a,
+) -> Element<'a, Message> {
+    container(text(label.into()).size(12))
+        .padding([2, 6])
+        .style(move |theme| container::Style {
+            border: border::rounded(8),
+            ..style(theme)
+        })
+        .into()
+}
@@ -0,0 +1,84 @@
+use iced::font::Weight;
+use iced::widget::{Row, button, center, column, container, opaque, text};
+use iced::{Element, Length};
+
+type ButtonStyle = fn(&iced::Theme, button::Status) -> button::Style;
+
+/// 汎用的な確認ダイアログ。画面全体を覆い、背後の操作を受け付けない。
+pub struct Dialog<'a, Message> {
+    title: String,
+    content: Element<'a, Message>, // 表示する中身（特定の型に依存しない）
+    actions: Vec<(String, Message, ButtonStyle)>,
+}
+
+impl<'a, Message> Dialog<'a, Message>
+where
+    Message: Clone + 'a,
+{
+    /// 新しい Dialog を作成。content には任意のウィジェットを渡せる。
+    pub fn new(title: impl Into<String>, content: impl Into<Element<'a, Message>>) -> Self {
+        Self {
+            title: title.into(),
+            content: content.into(),
+            actions: vec![],
+        }
+    }
+
+    /// ボタンを追加（追加した順に左から並ぶ）
+    pub fn action(self, label: impl Into<String>, message: Message) -> Self {
+        self.action_with_style(label, message, button::primary)
+    }
+
+    /// 削除など、取り消せない操作のボタンを追加
+    pub fn danger_action(self, label: impl Into<String>, message: Message) -> Self {
+        self.action_with_style(label, message, button::danger)
+    }
+
+    /// キャンセルなど、何もしないボタンを追加
+    pub fn secondary_action(self, label: impl Into<String>, message: Message) -> Self {
+        self.action_with_style(label, message, button::secondary)
+    }
+
+    fn action_with_style(
+        mut self,
+        label: impl Into<String>,
+        message: Message,
+        style: ButtonStyle,
+    ) -> Self {
+        self.actions.push((label.into(), message, style));
+        self
+    }
+
+    /// 表示用メソッド
+    pub fn view(self) -> Element<'a, Message> {
+        let actions = self.actions.into_iter().map(|(label, message, style)| {
+            button(text(label)).on_press(message).style(style).into()
+        });
+
+        let body = container(
+            column![
+                text(self.title).size(20).font(iced::Font {
+                    weight: Weight::Bold,
+                    ..Default::default()
+                }),
+                self.content,
+                Row::with_children(actions).spacing(10),
+            ]
+            .spacing(20),
+        )
+        .padding(25)
+        .max_width(480)
+        .style(container::rounded_box);
+
+        // 背景を暗くし、クリックが背後のカードに届かないようにする
+        opaque(
+            center(opaque(body))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .style(|_| {
+                    container::Style::default()
+                        .background(iced::Color::from_rgba(0.0, 0.0, 0.0, 0.4))
+                }),
+        )
+    }
+}
@@ -0,0 +1,653 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use iced::widget::{Column, Row, button, checkbox, container, mouse_area, pick_list, row, text};
+use iced::{Color, Element, Font, Length, Theme};
+
+use crate::app::highlight::{Highlighter, Token, TokenKind, language_for};
+use crate::app::vcs::diff::{DiffLine, DiffOptions, FileDiff, LineKind};
+use crate::app::vcs::stage::LineSelection;
+
+/// 行番号欄の幅
+const LINENO_WIDTH: f32 = 48.0;
+const TEXT_SIZE: f32 = 13.0;
+/// 行を選ぶときの印の幅
+const SELECTION_MARK_WIDTH: f32 = 16.0;
+/// 選べる前後の行数
+const CONTEXT_CHOICES: [usize; 6] = [0, 1, 3, 5, 10, 25];
+/// 単語単位の比較を行う上限（トークン数の積）。これを超える行は行全体を変更として扱う。
+const WORD_DIFF_LIMIT: usize = 40_000;
+/// 色分けを保持する行数の上限。超えたら捨てて作り直す。
+const HIGHLIGHT_CACHE_LIMIT: usize = 200_000;
+/// 単語単位の比較結果を保持する行の組の上限。超えたら捨てて作り直す。
+const WORD_DIFF_CACHE_LIMIT: usize = 100_000;
+
+/// 差分の表示方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffMode {
+    #[default]
+    Unified,
+    Split,
+}
+
+/// 表示方法と取得方法を持つ差分ビュー。
+/// 差分そのものは持たないので、コミットの差分にも作業ツリーの差分にも使える。
+#[derive(Debug, Clone, Default)]
+pub struct DiffView {
+    mode: DiffMode,
+    options: DiffOptions,
+    highlights: Highlights,
+    word_diffs: WordDiffs,
+}
+
+/// blob ID と行番号から引く色分けの結果。
+/// 同じ blob は別のコミットや表示設定でも使い回し、view のたびに字句解析しない。
+#[derive(Debug, Clone, Default)]
+struct Highlights {
+    blobs: HashMap<String, HashMap<usize, Vec<Token>>>,
+    lines: usize,
+}
+
+/// 対応する削除行と追加行の内容から引く、単語単位で変わった範囲。
+/// 差分を受け取ったときに一度だけ比べ、view のたびに比べ直さない。
+#[derive(Debug, Clone, Default)]
+struct WordDiffs {
+    ranges: HashMap<String, HashMap<String, Vec<Range<usize>>>>,
+    pairs: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ModeSelected(DiffMode),
+    IgnoreWhitespaceToggled(bool),
+    ContextLinesSelected(usize),
+}
+
+impl DiffView {
+    pub fn options(&self) -> &DiffOptions {
+        &self.options
+    }
+
+    /// 差分を取り直す必要があれば true を返す
+    pub fn update(&mut self, message: Message) -> bool {
+        match message {
+            Message::ModeSelected(mode) => {
+                self.mode = mode;
+                false
+            }
+            Message::IgnoreWhitespaceToggled(value) => {
+                self.options.ignore_whitespace = value;
+                true
+            }
+            Message::ContextLinesSelected(value) => {
+                let changed = self.options.context_lines != value;
+                self.options.context_lines = value;
+                changed
+            }
+        }
+    }
+
+    /// 差分を受け取ったときに呼び、まだ色分けしていない blob の行の色分けと
+    /// 対応する行の単語単位の比較を済ませておく
+    pub fn prepare(&mut self, files: &[FileDiff]) {
+        for file in files {
+            self.highlights.prepare(file);
+            self.word_diffs.prepare(file);
+        }
+    }
+
+    /// 表示方法・空白の無視・前後の行数を切り替える
+    pub fn view_controls(&self) -> Element<'_, Message> {
+        let mode_button = |label, mode| {
+            button(text(label).size(12))
+                .on_press(Message::ModeSelected(mode))
+                .style(if self.mode == mode {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+        };
+
+        row![
+            mode_button("統合", DiffMode::Unified),
+            mode_button("左右分割", DiffMode::Split),
+            checkbox(self.options.ignore_whitespace)
+                .label("空白を無視")
+                .on_toggle(Message::IgnoreWhitespaceToggled),
+            text("前後の行数").size(12),
+            pick_list(
+                CONTEXT_CHOICES,
+                Some(self.options.context_lines),
+                Message::ContextLinesSelected,
+            )
+            .text_size(12),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into()
+    }
+
+    /// 選択中の表示方法で一ファイル分の差分を表示する
+    pub fn view_file<'a, M: 'a>(&'a self, file: &'a FileDiff) -> Element<'a, M> {
+        match self.mode {
+            DiffMode::Unified => unified(file, &self.highlights, &self.word_diffs),
+            DiffMode::Split => split(file, &self.highlights, &self.word_diffs),
+        }
+    }
+
+    /// 行を選べる unified 表示。選んだ行は左端に印を付ける。
+    /// hunk_actions はハンクの見出しの右に並べるボタン（ラベル, メッセージ）。
+    pub fn view_selectable<'a, M: Clone + 'a>(
+        &'a self,
+        file: &'a FileDiff,
+        selection: &'a LineSelection,
+        on_toggle: impl Fn(usize, usize) -> M + 'a,
+        hunk_actions: impl Fn(usize) -> Vec<(&'static str, M)> + 'a,
+    ) -> Element<'a, M> {
+        if let Some(placeholder) = placeholder(file) {
+            return placeholder;
+        }
+
+        let mut c = Column::new();
+        for (h, hunk) in file.hunks.iter().enumerate() {
+            let actions = hunk_actions(h).into_iter().map(|(label, message)| {
+                button(text(label).size(12))
+                    .on_press(message)
+                    .style(button::secondary)
+                    .into()
+            });
+            c = c.push(
+                row![hunk_header(&hunk.header)]
+                    .extend(actions)
+                    .spacing(4)
+                    .align_y(iced::Alignment::Center),
+            );
+
+            for (l, (line, counterpart)) in
+                hunk.lines.iter().zip(counterparts(&hunk.lines)).enumerate()
+            {
+                let view = unified_line(
+                    line,
+                    self.highlights.get(file, line),
+                    counterpart.and_then(|x| self.word_diffs.get(line, x)),
+                );
+                if line.kind == LineKind::Context {
+                    c = c.push(row![text("").width(SELECTION_MARK_WIDTH), view]);
+                    continue;
+                }
+                let mark = if selection.contains(&(h, l)) {
+                    "●"
+                } else {
+                    "○"
+                };
+                c = c.push(
+                    mouse_area(row![
+                        text(mark).size(TEXT_SIZE).width(SELECTION_MARK_WIDTH),
+                        view
+                    ])
+                    .on_press(on_toggle(h, l)),
+                );
+            }
+        }
+        c.width(Length::Fill).into()
+    }
+}
+
+impl Highlights {
+    fn prepare(&mut self, file: &FileDiff) {
+        let Some(language) = file
+            .new_path
+            .as_deref()
+            .or(file.old_path.as_deref())
+            .and_then(language_for)
+        else {
+            return;
+        };
+        let (Some(old_blob), Some(new_blob)) = (&file.old_blob, &file.new_blob) else {
+            return;
+        };
+        if self.lines > HIGHLIGHT_CACHE_LIMIT {
+            self.blobs.clear();
+            self.lines = 0;
+        }
+
+        for hunk in &file.hunks {
+            if hunk.lines.iter().all(|x| self.get(file, x).is_some()) {
+                continue;
+            }
+
+            // 変更前と変更後で、ブロックコメントの状態を別々に追う
+            let mut old = Highlighter::new(language);
+            let mut new = Highlighter::new(language);
+            for line in &hunk.lines {
+                let (blob, lineno, tokens) = match line.kind {
+                    LineKind::Removed => {
+                        (old_blob, line.old_lineno, old.highlight_line(&line.content))
+                    }
+                    LineKind::Added => {
+                        (new_blob, line.new_lineno, new.highlight_line(&line.content))
+                    }
+                    LineKind::Context => {
+                        old.highlight_line(&line.content);
+                        (new_blob, line.new_lineno, new.highlight_line(&line.content))
+                    }
+                };
+                let Some(lineno) = lineno else {
+                    continue;
+                };
+                let previous = self
+                    .blobs
+                    .entry(blob.to_owned())
+                    .or_default()
+                    .insert(lineno, tokens);
+                if previous.is_none() {
+                    self.lines += 1;
+                }
+            }
+        }
+    }
+
+    fn get(&self, file: &FileDiff, line: &DiffLine) -> Option<&[Token]> {
+        let (blob, lineno) = match line.kind {
+            LineKind::Removed => (file.old_blob.as_ref()?, line.old_lineno?),
+            _ => (file.new_blob.as_ref()?, line.new_lineno?),
+        };
+        self.blobs.get(blob)?.get(&lineno).map(|x| x.as_slice())
+    }
+}
+
+impl WordDiffs {
+    fn prepare(&mut self, file: &FileDiff) {
+        if self.pairs > WORD_DIFF_CACHE_LIMIT {
+            self.ranges.clear();
+            self.pairs = 0;
+        }
+
+        for hunk in &file.hunks {
+            for (line, counterpart) in hunk.lines.iter().zip(counterparts(&hunk.lines)) {
+                let Some(counterpart) = counterpart else {
+                    continue;
+                };
+                if self.get(line, counterpart).is_some() {
+                    continue;
+                }
+                let ranges = changed_ranges(&line.content, &counterpart.content);
+                self.ranges
+                    .entry(line.content.clone())
+                    .or_default()
+                    .insert(counterpart.content.clone(), ranges);
+                self.pairs += 1;
+            }
+        }
+    }
+
+    fn get(&self, line: &DiffLine, counterpart: &DiffLine) -> Option<&[Range<usize>]> {
+        self.ranges
+            .get(&line.content)?
+            .get(&counterpart.content)
+            .map(|x| x.as_slice())
+    }
+}
+
+/// unified 形式で一ファイル分の差分を表示する
+fn unified<'a, Message: 'a>(
+    file: &'a FileDiff,
+    highlights: &'a Highlights,
+    word_diffs: &'a WordDiffs,
+) -> Element<'a, Message> {
+    if let Some(placeholder) = placeholder(file) {
+        return placeholder;
+    }
+
+    let mut c = Column::new();
+    for hunk in &file.hunks {
+        // 対応する削除行と追加行を互いに引けるようにする
+        c = c.push(hunk_header(&hunk.header));
+        c = c.extend(hunk.lines.iter().zip(counterparts(&hunk.lines)).map(
+            |(line, counterpart)| {
+                unified_line(
+                    line,
+                    highlights.get(file, line),
+                    counterpart.and_then(|x| word_diffs.get(line, x)),
+                )
+            },
+        ));
+    }
+    c.width(Length::Fill).into()
+}
+
+/// 対応する削除行と追加行を互いに引けるようにする
+fn counterparts(lines: &[DiffLine]) -> Vec<Option<&DiffLine>> {
+    let mut counterparts = vec![None; lines.len()];
+    for (left, right) in pair_lines(lines) {
+        if let (Some(left), Some(right)) = (left, right)
+            && left != right
+        {
+            counterparts[left] = Some(&lines[right]);
+            counterparts[right] = Some(&lines[left]);
+        }
+    }
+    counterparts
+}
+
+/// 左に変更前、右に変更後を並べて表示する。
+/// 左右を一つの行に並べるので、スクロールは常に揃う。
+fn split<'a, Message: 'a>(
+    file: &'a FileDiff,
+    highlights: &'a Highlights,
+    word_diffs: &'a WordDiffs,
+) -> Element<'a, Message> {
+    if let Some(placeholder) = placeholder(file) {
+        return placeholder;
+    }
+
+    let mut c = Column::new();
+    for hunk in &file.hunks {
+        c = c.push(hunk_header(&hunk.header));
+        c = c.extend(pair_lines(&hunk.lines).into_iter().map(|(left, right)| {
+            let left = left.map(|i| &hunk.lines[i]);
+            let right = right.map(|i| &hunk.lines[i]);
+            let tokens = |line: Option<&'a DiffLine>| line.and_then(|x| highlights.get(file, x));
+            // 文脈行は左右が同じ行なので比べない
+            let changed = |line: Option<&'a DiffLine>, counterpart: Option<&'a DiffLine>| {
+                line.zip(counterpart)
+                    .filter(|(x, _)| x.kind != LineKind::Context)
+                    .and_then(|(x, y)| word_diffs.get(x, y))
+            };
+            row![
+                split_cell(left, tokens(left), changed(left, right), |x| x.old_lineno),
+                split_cell(right, tokens(right), changed(right, left), |x| x.new_lineno),
+            ]
+            .spacing(2)
+            .into()
+        }));
+    }
+    c.width(Length::Fill).into()
+}
+
+fn placeholder<'a, Message: 'a>(file: &FileDiff) -> Option<Element<'a, Message>> {
+    if file.binary {
+        Some(text("バイナリファイルの差分は表示できません").into())
+    } else if file.hunks.is_empty() {
+        Some(text("内容の変更はありません").into())
+    } else {
+        None
+    }
+}
+
+fn hunk_header<'a, Message: 'a>(header: &'a str) -> Element<'a, Message> {
+    container(text(header).size(TEXT_SIZE).font(Font::MONOSPACE))
+        .width(Length::Fill)
+        .padding([2, 6])
+        .style(|theme: &Theme| {
+            let palette = theme.extended_palette();
+            container::Style::default()
+                .background(palette.background.weak.color)
+                .color(palette.background.weak.text)
+        })
+        .into()
+}
+
+/// changed は対応する削除行／追加行と比べて変わった範囲（単語単位の強調に使う）
+fn unified_line<'a, Message: 'a>(
+    line: &'a DiffLine,
+    tokens: Option<&'a [Token]>,
+    changed: Option<&'a [Range<usize>]>,
+) -> Element<'a, Message> {
+    let prefix = match line.kind {
+        LineKind::Added => "+",
+        LineKind::Removed => "-",
+        LineKind::Context => " ",
+    };
+    let kind = line.kind;
+
+    container(
+        row![
+            lineno(line.old_lineno),
+            lineno(line.new_lineno),
+            text(prefix).size(TEXT_SIZE).font(Font::MONOSPACE),
+            content(line, tokens, changed),
+        ]
+        .spacing(6),
+    )
+    .width(Length::Fill)
+    .style(move |theme: &Theme| line_style(theme, Some(kind)))
+    .into()
+}
+
+/// 分割表示の片側。line が無い側は空欄にする。
+fn split_cell<'a, Message: 'a>(
+    line: Option<&'a DiffLine>,
+    tokens: Option<&'a [Token]>,
+    changed: Option<&'a [Range<usize>]>,
+    lineno_of: fn(&DiffLine) -> Option<usize>,
+) -> Element<'a, Message> {
+    let kind = line.map(|x| x.kind);
+    let inner: Element<'a, Message> = match line {
+        Some(line) => row![lineno(lineno_of(line)), content(line, tokens, changed),]
+            .spacing(6)
+            .into(),
+        None => text("").size(TEXT_SIZE).into(),
+    };
+
+    container(inner)
+        .width(Length::FillPortion(1))
+        .style(move |theme: &Theme| line_style(theme, kind))
+        .into()
+}
+
+/// 行の内容。構文で色分けし、対応する行と比べて変わった単語を濃い色で強調する。
+fn content<'a, Message: 'a>(
+    line: &'a DiffLine,
+    tokens: Option<&'a [Token]>,
+    changed: Option<&'a [Range<usize>]>,
+) -> Element<'a, Message> {
+    let changed = changed.unwrap_or_default();
+    if tokens.is_none() && changed.is_empty() {
+        return text(line.content.as_str())
+            .size(TEXT_SIZE)
+            .font(Font::MONOSPACE)
+            .into();
+    }
+
+    let highlight = match line.kind {
+        LineKind::Added => Color::from_rgba8(0x3f, 0xb9, 0x50, 0.45),
+        _ => Color::from_rgba8(0xf8, 0x51, 0x49, 0.45),
+    };
+    let pieces = pieces(line.content.len(), tokens.unwrap_or_default(), changed);
+    Row::with_children(pieces.into_iter().map(|(range, kind, changed)| {
+        let piece = text(&line.content[range])
+            .size(TEXT_SIZE)
+            .font(Font::MONOSPACE)
+            .wrapping(text::Wrapping::None)
+            .style(move |theme: &Theme| text::Style {
+                color: token_color(theme, kind),
+            });
+        if changed {
+            container(piece)
+                .style(move |_| container::Style::default().background(highlight))
+                .into()
+        } else {
+            piece.into()
+        }
+    }))
+    .into()
+}
+
+/// 色分けの範囲と単語の変更範囲を重ね、（範囲, 種類, 変更されたか）に切り分ける
+fn pieces(
+    len: usize,
+    tokens: &[Token],
+    changed: &[Range<usize>],
+) -> Vec<(Range<usize>, TokenKind, bool)> {
+    let mut bounds = vec![0, len];
+    bounds.extend(tokens.iter().flat_map(|x| [x.range.start, x.range.end]));
+    bounds.extend(changed.iter().flat_map(|x| [x.start, x.end]));
+    bounds.retain(|x| *x <= len);
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    bounds
+        .windows(2)
+        .map(|x| {
+            let kind = tokens
+                .iter()
+                .find(|t| t.range.contains(&x[0]))
+                .map(|t| t.kind)
+                .unwrap_or(TokenKind::Plain);
+            let is_changed = changed.iter().any(|r| r.contains(&x[0]));
+            (x[0]..x[1], kind, is_changed)
+        })
+        .collect()
+}
+
+/// 構文の色はアプリのテーマの配色から選ぶ
+fn token_color(theme: &Theme, kind: TokenKind) -> Option<Color> {
+    let palette = theme.palette();
+    match kind {
+        TokenKind::Plain => None,
+        TokenKind::Keyword => Some(palette.primary),
+        TokenKind::Type => Some(palette.warning),
+        TokenKind::String => Some(palette.success),
+        TokenKind::Number => Some(palette.danger),
+        TokenKind::Comment => Some(Color {
+            a: 0.6,
+            ..palette.text
+        }),
+    }
+}
+
+fn lineno<'a, Message: 'a>(lineno: Option<usize>) -> Element<'a, Message> {
+    text(lineno.map(|x| x.to_string()).unwrap_or_default())
+        .size(TEXT_SIZE)
+        .font(Font::MONOSPACE)
+        .width(LINENO_WIDTH)
+        .align_x(iced::alignment::Horizontal::Right)
+        .style(text::secondary)
+        .into()
+}
+
+/// 追加行は緑、削除行は赤の背景にする
+fn line_style(theme: &Theme, kind: Option<LineKind>) -> container::Style {
+    let palette = theme.extended_palette();
+    match kind {
+        Some(LineKind::Added) => container::Style::default().background(palette.success.weak.color),
+        Some(LineKind::Removed) => {
+            container::Style::default().background(palette.danger.weak.color)
+        }
+        Some(LineKind::Context) => container::Style::default(),
+        // 分割表示で相手側にしか行が無い場合
+        None => container::Style::default().background(palette.background.weak.color),
+    }
+}
+
+/// ハンクの行を（変更前, 変更後）の番号の組にする。
+/// 連続する削除行と追加行は先頭から順に対応させ、余った行は片側だけになる。
+fn pair_lines(lines: &[DiffLine]) -> Vec<(Option<usize>, Option<usize>)> {
+    fn flush(
+        pairs: &mut Vec<(Option<usize>, Option<usize>)>,
+        removed: &mut Vec<usize>,
+        added: &mut Vec<usize>,
+    ) {
+        for i in 0..removed.len().max(added.len()) {
+            pairs.push((removed.get(i).copied(), added.get(i).copied()));
+        }
+        removed.clear();
+        added.clear();
+    }
+
+    let mut pairs = vec![];
+    let mut removed = vec![];
+    let mut added = vec![];
+    for (index, line) in lines.iter().enumerate() {
+        match line.kind {
+            LineKind::Removed => {
+                // 追加行の後に来た削除行は別のまとまり
+                if !added.is_empty() {
+                    flush(&mut pairs, &mut removed, &mut added);
+                }
+                removed.push(index);
+            }
+            LineKind::Added => added.push(index),
+            LineKind::Context => {
+                flush(&mut pairs, &mut removed, &mut added);
+                pairs.push((Some(index), Some(index)));
+            }
+        }
+    }
+    flush(&mut pairs, &mut removed, &mut added);
+
+    pairs
+}
+
+/// line を other と単語単位で比べ、line 側で変わった範囲（バイト単位）を返す
+fn changed_ranges(line: &str, other: &str) -> Vec<Range<usize>> {
+    let a = tokenize(line);
+    let b = tokenize(other);
+    if a.len() * b.len() > WORD_DIFF_LIMIT {
+        return std::iter::once(0..line.len()).collect();
+    }
+
+    // 最長共通部分列で共通のトークンを求める
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ranges: Vec<Range<usize>> = vec![];
+    let (mut i, mut j) = (0, 0);
+    let mut offset = 0;
+    while i < a.len() {
+        if j < b.len() && a[i] == b[j] {
+            j += 1;
+        } else if j < b.len() && lcs[i][j + 1] > lcs[i + 1][j] {
+            // other 側だけのトークンを読み飛ばす
+            j += 1;
+            continue;
+        } else {
+            let range = offset..offset + a[i].len();
+            match ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ranges.push(range),
+            }
+        }
+        offset += a[i].len();
+        i += 1;
+    }
+    ranges
+}
+
+/// 英数字の連続・空白の連続・それ以外の一文字ずつに分ける
+fn tokenize(line: &str) -> Vec<&str> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            0
+        } else if c.is_whitespace() {
+            1
+        } else {
+            2
+        }
+    };
+
+    let mut tokens = vec![];
+    let mut start = 0;
+    let mut previous = None;
+    for (index, c) in line.char_indices() {
+        let current = class(c);
+        if index > start && (previous != Some(current) || current == 2) {
+            tokens.push(&line[start..index]);
+            start = index;
+        }
+        previous = Some(current);
+    }
+    if start < line.len() {
+        tokens.push(&line[start..]);
+    }
+    tokens
+}
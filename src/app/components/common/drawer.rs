@@ -7,6 +7,7 @@ pub struct Drawer<'a, Message> {
     title: String,
     content: Element<'a, Message>, // 表示する中身（特定の型に依存しない）
     on_close: Option<Message>,
+    scrollable: bool,
 }
 
 impl<'a, Message> Drawer<'a, Message>
@@ -19,6 +20,7 @@ where
             title: title.into(),
             content: content.into(),
             on_close: None,
+            scrollable: true,
         }
     }
 
@@ -28,10 +30,28 @@ where
         self
     }
 
+    /// 中身を Drawer 側でスクロールさせるか（既定は true）。
+    /// 中身が自前でスクロールや仮想化をする場合は false にする。
+    pub fn scrollable(mut self, scrollable: bool) -> Self {
+        self.scrollable = scrollable;
+        self
+    }
+
     /// 表示用メソッド
     pub fn view(self) -> Element<'a, Message> {
         // --- Drawer 本体のレイアウト ---
         let title = self.title.to_owned();
+        let content: Element<'a, Message> = if self.scrollable {
+            scrollable(self.content)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into()
+        } else {
+            container(self.content)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into()
+        };
         let drawer_body = container(
             column![
                 // ヘッダー部分
@@ -50,9 +70,7 @@ where
                 ]
                 .align_y(Alignment::Center),
                 // メインコンテンツ部分
-                scrollable(self.content)
-                    .width(Length::Fill)
-                    .height(Length::Fill)
+                content
             ]
             .spacing(20),
         )
@@ -76,6 +76,12 @@ impl Select {
         column![label, content].spacing(10).into()
     }
 
+    // 選択を取り消す（確認ダイアログでキャンセルされたときなど）
+    pub fn clear(&mut self) {
+        self.selected_item = None;
+        self.input_value = String::new();
+    }
+
     // 内部の状態を更新するロジック
     pub fn update(&mut self, message: Message) {
         match message {
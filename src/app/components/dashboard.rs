@@ -1,2 +1,15 @@
+pub mod branches;
+pub mod bulk;
 pub mod card;
-// pub mod card_detail;
+pub mod card_detail;
+pub mod changes;
+pub mod cleanup;
+pub mod commit_detail;
+pub mod commit_editor;
+pub mod commit_graph;
+pub mod grid;
+pub mod history;
+pub mod remote;
+pub mod stashes;
+pub mod table;
+pub mod tags;
@@ -0,0 +1,426 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use iced::font::Weight;
+use iced::widget::{
+    Column, button, column, container, pick_list, row, scrollable, space, text, text_input,
+};
+use iced::{Alignment, Element, Font, Length, Task};
+
+use crate::app::components::common::badge::badge;
+use crate::app::components::common::dialog::Dialog;
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::branch::{
+    BranchInfo, create_branch, delete_branch, list_branches, rename_branch, set_upstream,
+};
+
+/// ブランチ（jj ではブックマーク）の一覧と、作成・名前変更・削除・upstream の設定
+#[derive(Debug, Clone)]
+pub struct Branches {
+    path: PathBuf,
+    kind: RepositoryKind,
+    /// 読み込み中は None
+    branches: Option<Result<Vec<BranchInfo>, String>>,
+    new_name_input: String,
+    /// 空なら今いる位置（HEAD / @）から作る
+    start_input: String,
+    /// 名前を変えているブランチと入力中の新しい名前
+    renaming: Option<(String, String)>,
+    /// 取り込まれていないので強制削除の確認を待っているブランチと、-d で消せなかったときの git のエラー
+    pending_delete: Option<(String, Option<String>)>,
+    running: bool,
+    /// 直前の操作が失敗したときのメッセージ
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Refresh,
+    Loaded(Result<Vec<BranchInfo>, String>),
+    NewNameChanged(String),
+    StartChanged(String),
+    Create,
+    RenameStarted(String),
+    RenameInputChanged(String),
+    RenameSubmitted,
+    RenameCancelled,
+    Delete(String),
+    /// 取り込み済みに見えても upstream や HEAD に取り込まれていないと -d は断るので、確認に回す
+    DeleteRejected(String, String),
+    DeleteConfirmed,
+    DeleteCancelled,
+    /// ブランチ名と upstream
+    UpstreamSelected(String, String),
+    UpstreamUnset(String),
+    /// 成功・失敗にかかわらず、親はここでカードを読み込み直す
+    Finished(Result<(), String>),
+}
+
+impl Branches {
+    pub fn new(path: PathBuf, kind: RepositoryKind) -> Self {
+        Self {
+            path,
+            kind,
+            branches: None,
+            new_name_input: String::new(),
+            start_input: String::new(),
+            renaming: None,
+            pending_delete: None,
+            running: false,
+            error: None,
+        }
+    }
+
+    /// 前の一覧は読み込み終わるまで残す
+    pub fn load(&self) -> Task<Message> {
+        let path = self.path.clone();
+        let kind = self.kind;
+        Task::perform(async move { list_branches(&path, kind) }, Message::Loaded)
+    }
+
+    /// selected_commit は履歴で選んでいるコミット。作成の起点にできる。
+    pub fn view<'a>(&'a self, selected_commit: Option<&'a str>) -> Element<'a, Message> {
+        let header = row![
+            text("ブランチ").font(Font {
+                weight: Weight::Bold,
+                ..Default::default()
+            }),
+            space().width(Length::Fill),
+            button(text("更新"))
+                .on_press_maybe((!self.running).then_some(Message::Refresh))
+                .style(button::secondary),
+        ]
+        .align_y(Alignment::Center);
+
+        let mut c = column![header, self.view_create(selected_commit)].spacing(10);
+        if let Some(error) = &self.error {
+            c = c.push(text(error).size(12).style(text::danger));
+        }
+
+        let branches = match &self.branches {
+            None => return c.push(text("読み込み中...")).into(),
+            Some(Err(err)) => {
+                return c
+                    .push(text(format!("ブランチを取得できません: {}", err)).style(text::danger))
+                    .into();
+            }
+            Some(Ok(branches)) => branches,
+        };
+
+        // upstream に選べるリモート追跡ブランチ
+        let remotes = branches
+            .iter()
+            .filter(|x| x.remote.is_some())
+            .map(|x| x.full_name(self.kind))
+            .collect::<Vec<_>>();
+        let locals = branches
+            .iter()
+            .filter(|x| x.remote.is_none())
+            .map(|x| self.view_local(x, &remotes));
+        let remote_rows = branches
+            .iter()
+            .filter(|x| x.remote.is_some())
+            .map(|x| self.view_remote(x));
+
+        let list = column![
+            text("ローカル").size(14).style(text::secondary),
+            Column::with_children(locals).spacing(8),
+            text("リモート").size(14).style(text::secondary),
+            Column::with_children(remote_rows).spacing(8),
+        ]
+        .spacing(10);
+
+        c.push(scrollable(list).width(Length::Fill).height(Length::Fill))
+            .into()
+    }
+
+    /// 取り込まれていないブランチの強制削除の確認
+    pub fn view_dialog(&self) -> Option<Element<'_, Message>> {
+        let (branch, rejected) = self.pending_delete.as_ref()?;
+        let body = match rejected {
+            Some(err) => format!("{}\n強制的に削除しますか?", err),
+            None if self.kind.is_jj() => {
+                "既定のブランチ (trunk) に取り込まれていません。ブックマークを削除しますか?"
+                    .to_owned()
+            }
+            None => "既定のブランチに取り込まれていないコミットがあります。強制的に削除しますか?"
+                .to_owned(),
+        };
+
+        Some(
+            Dialog::new(format!("{} を削除", branch), text(body))
+                .danger_action("削除", Message::DeleteConfirmed)
+                .secondary_action("中止", Message::DeleteCancelled)
+                .view(),
+        )
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Refresh => return self.load(),
+            Message::Loaded(result) => self.branches = Some(result),
+            Message::NewNameChanged(value) => self.new_name_input = value,
+            Message::StartChanged(value) => self.start_input = value,
+            Message::Create => {
+                let branch = self.new_name_input.trim().to_owned();
+                let start = match self.start_input.trim() {
+                    "" if self.kind.is_jj() => "@".to_owned(),
+                    "" => "HEAD".to_owned(),
+                    x => x.to_owned(),
+                };
+                self.new_name_input.clear();
+                return self.run(move |path, kind| create_branch(path, kind, &branch, &start));
+            }
+            Message::RenameStarted(branch) => self.renaming = Some((branch.clone(), branch)),
+            Message::RenameInputChanged(value) => {
+                if let Some((_, input)) = &mut self.renaming {
+                    *input = value;
+                }
+            }
+            Message::RenameSubmitted => {
+                if let Some((from, to)) = self.renaming.take() {
+                    let to = to.trim().to_owned();
+                    if !to.is_empty() && to != from {
+                        return self.run(move |path, kind| rename_branch(path, kind, &from, &to));
+                    }
+                }
+            }
+            Message::RenameCancelled => self.renaming = None,
+            Message::Delete(branch) => {
+                // 取り込み済みなら確認せずに -d で消す
+                if self.find(&branch).is_some_and(|x| x.merged) {
+                    if self.running {
+                        return Task::none();
+                    }
+                    self.running = true;
+                    self.error = None;
+
+                    let path = self.path.clone();
+                    let kind = self.kind;
+                    return Task::perform(
+                        {
+                            let branch = branch.clone();
+                            async move { delete_branch(&path, kind, &branch, false) }
+                        },
+                        move |result| match result {
+                            // メッセージは翻訳されていることがあるので、git の失敗はすべて確認に回す
+                            Err(err) if !kind.is_jj() => {
+                                Message::DeleteRejected(branch.clone(), err)
+                            }
+                            result => Message::Finished(result),
+                        },
+                    );
+                }
+                self.pending_delete = Some((branch, None));
+            }
+            Message::DeleteRejected(branch, err) => {
+                self.running = false;
+                self.pending_delete = Some((branch, Some(err)));
+            }
+            Message::DeleteConfirmed => {
+                if let Some((branch, _)) = self.pending_delete.take() {
+                    return self.run(move |path, kind| delete_branch(path, kind, &branch, true));
+                }
+            }
+            Message::DeleteCancelled => self.pending_delete = None,
+            Message::UpstreamSelected(branch, upstream) => {
+                if let Some(branch) = self.find(&branch).cloned() {
+                    return self
+                        .run(move |path, kind| set_upstream(path, kind, &branch, Some(&upstream)));
+                }
+            }
+            Message::UpstreamUnset(branch) => {
+                if let Some(branch) = self.find(&branch).cloned() {
+                    return self.run(move |path, kind| set_upstream(path, kind, &branch, None));
+                }
+            }
+            Message::Finished(result) => {
+                self.running = false;
+                self.error = result.err();
+                return self.load();
+            }
+        }
+
+        Task::none()
+    }
+
+    /// ローカルのブランチを名前で探す
+    fn find(&self, branch: &str) -> Option<&BranchInfo> {
+        self.branches
+            .as_ref()
+            .and_then(|x| x.as_ref().ok())
+            .and_then(|x| x.iter().find(|x| x.remote.is_none() && x.name == branch))
+    }
+
+    fn run(
+        &mut self,
+        operation: impl FnOnce(&Path, RepositoryKind) -> Result<(), String> + Send + 'static,
+    ) -> Task<Message> {
+        if self.running {
+            return Task::none();
+        }
+        self.running = true;
+        self.error = None;
+
+        let path = self.path.clone();
+        let kind = self.kind;
+        Task::perform(async move { operation(&path, kind) }, Message::Finished)
+    }
+
+    fn view_create<'a>(&'a self, selected_commit: Option<&'a str>) -> Element<'a, Message> {
+        let can_create = !self.running && !self.new_name_input.trim().is_empty();
+        let mut r = row![
+            text_input("新しいブランチ名", &self.new_name_input)
+                .on_input(Message::NewNameChanged)
+                .on_submit_maybe(can_create.then_some(Message::Create))
+                .width(180),
+            text_input(
+                if self.kind.is_jj() {
+                    "起点 (既定: @)"
+                } else {
+                    "起点 (既定: HEAD)"
+                },
+                &self.start_input
+            )
+            .on_input(Message::StartChanged)
+            .width(Length::Fill),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+        if let Some(commit) = selected_commit {
+            r = r.push(
+                button(text("履歴で選んだコミット").size(13))
+                    .on_press(Message::StartChanged(commit.to_owned()))
+                    .style(button::secondary),
+            );
+        }
+        r.push(
+            button(text("作成"))
+                .on_press_maybe(can_create.then_some(Message::Create))
+                .style(button::primary),
+        )
+        .into()
+    }
+
+    fn view_local<'a>(
+        &'a self,
+        branch: &'a BranchInfo,
+        remotes: &[String],
+    ) -> Element<'a, Message> {
+        let name: Element<'a, Message> = match &self.renaming {
+            Some((from, input)) if *from == branch.name => row![
+                text_input("新しい名前", input)
+                    .on_input(Message::RenameInputChanged)
+                    .on_submit(Message::RenameSubmitted)
+                    .width(180),
+                button(text("変更").size(13)).on_press_maybe(
+                    (!self.running && !input.trim().is_empty()).then_some(Message::RenameSubmitted)
+                ),
+                button(text("中止").size(13))
+                    .on_press(Message::RenameCancelled)
+                    .style(button::text),
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center)
+            .into(),
+            _ => text(format!(
+                "{}{}",
+                if branch.current { "● " } else { "" },
+                branch.name
+            ))
+            .into(),
+        };
+
+        let mut badges = row![].spacing(4);
+        if branch.ahead > 0 {
+            badges = badges.push(badge(format!("↑{}", branch.ahead), container::warning));
+        }
+        if branch.behind > 0 {
+            badges = badges.push(badge(format!("↓{}", branch.behind), container::primary));
+        }
+        if branch.upstream_gone {
+            badges = badges.push(badge("upstream 消失", container::danger));
+        }
+        if branch.merged {
+            badges = badges.push(badge("取り込み済み", container::success));
+        }
+
+        let enabled = !self.running;
+        let upstream = row![
+            pick_list(remotes.to_vec(), branch.upstream.clone(), |upstream| {
+                Message::UpstreamSelected(branch.name.clone(), upstream)
+            })
+            .placeholder("upstream なし")
+            .text_size(13),
+            button(text("解除").size(13))
+                .on_press_maybe(
+                    (enabled && branch.upstream.is_some())
+                        .then(|| Message::UpstreamUnset(branch.name.clone()))
+                )
+                .style(button::text),
+            space().width(Length::Fill),
+            button(text("名前を変更").size(13))
+                .on_press_maybe(enabled.then(|| Message::RenameStarted(branch.name.clone())))
+                .style(button::secondary),
+            button(text("削除").size(13))
+                .on_press_maybe(
+                    // 今いるブランチは消せない
+                    (enabled && !branch.current).then(|| Message::Delete(branch.name.clone()))
+                )
+                .style(button::danger),
+        ]
+        .spacing(6)
+        .align_y(Alignment::Center);
+
+        column![
+            row![name, badges].spacing(10).align_y(Alignment::Center),
+            view_last_commit(branch, self.kind),
+            upstream,
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    fn view_remote<'a>(&'a self, branch: &'a BranchInfo) -> Element<'a, Message> {
+        let full_name = branch.full_name(self.kind);
+        let mut r = row![text(full_name.clone()).style(text::secondary)]
+            .spacing(10)
+            .align_y(Alignment::Center);
+        if branch.merged {
+            r = r.push(badge("取り込み済み", container::success));
+        }
+        r = r.push(space().width(Length::Fill));
+        r = r.push(
+            button(text("起点にする").size(13))
+                .on_press(Message::StartChanged(full_name))
+                .style(button::text),
+        );
+
+        column![r, view_last_commit(branch, self.kind)]
+            .spacing(4)
+            .into()
+    }
+}
+
+fn view_last_commit(branch: &BranchInfo, kind: RepositoryKind) -> Element<'_, Message> {
+    let Some(commit) = &branch.last_commit else {
+        // jj では複数のコミットを指す（衝突した）ブックマークだけが読めない
+        let label = if kind.is_jj() {
+            "(衝突しています)"
+        } else {
+            "(コミットを読み込めません)"
+        };
+        return text(label).size(12).style(text::danger).into();
+    };
+    let datetime: DateTime<Local> = commit.timestamp.into();
+    text(format!(
+        "{}  {}  {}  {}",
+        commit.commit_id.chars().take(10).collect::<String>(),
+        datetime.format("%Y-%m-%d %H:%M"),
+        commit.author,
+        commit.summary
+    ))
+    .size(12)
+    .wrapping(text::Wrapping::None)
+    .into()
+}
@@ -0,0 +1,268 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use iced::futures::{Stream, channel::mpsc};
+use iced::widget::{Column, button, column, container, row, scrollable, space, text, text_input};
+use iced::{Alignment, Element, Length, Task};
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::bulk::{BulkOperation, BulkOutcome, run_bulk_operation};
+
+/// 結果表の最大の高さ
+const RESULTS_HEIGHT: f32 = 240.0;
+/// 結果表の一行のおおよその高さ
+const ROW_HEIGHT: f32 = 28.0;
+
+/// 一括操作の対象
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub id: usize,
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: RepositoryKind,
+}
+
+/// 選択したカードへの一括操作のバーと結果表
+#[derive(Debug, Default)]
+pub struct BulkActions {
+    branch_input: String,
+    new_branch_input: String,
+    command_input: String,
+    run: Option<BulkRun>,
+    /// 前の実行の結果が後から届いても無視する
+    generation: usize,
+}
+
+#[derive(Debug)]
+struct BulkRun {
+    operation: BulkOperation,
+    rows: Vec<ResultRow>,
+}
+
+#[derive(Debug)]
+struct ResultRow {
+    target: Target,
+    /// 実行中は None
+    outcome: Option<BulkOutcome>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    BranchInputChanged(String),
+    NewBranchInputChanged(String),
+    CommandInputChanged(String),
+    /// 親が選択中のカードを対象にして start を呼ぶ
+    Run(BulkOperation),
+    /// 一件終わった。成功したら親がそのカードを読み込み直す。
+    Finished(usize, usize, BulkOutcome),
+    RerunFailed,
+    Close,
+}
+
+impl BulkActions {
+    /// 結果表を出しているか
+    pub fn has_results(&self) -> bool {
+        self.run.is_some()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.run
+            .as_ref()
+            .is_some_and(|x| x.rows.iter().any(|x| x.outcome.is_none()))
+    }
+
+    /// 一括操作のバー。selected は選択中のカードの数。
+    pub fn view(&self, selected: usize) -> Element<'_, Message> {
+        let enabled = selected > 0 && !self.is_running();
+        let action = |label: &'static str, operation: Option<BulkOperation>| {
+            button(text(label))
+                .on_press_maybe(operation.filter(|_| enabled).map(Message::Run))
+                .style(button::secondary)
+        };
+        let input = |value: &str| (!value.trim().is_empty()).then(|| value.trim().to_owned());
+
+        let bar = row![
+            text(format!("{} 件を選択中", selected)),
+            action("fetch", Some(BulkOperation::Fetch)),
+            action("pull (ff)", Some(BulkOperation::Pull)),
+            text_input("ブランチ名", &self.branch_input)
+                .on_input(Message::BranchInputChanged)
+                .width(140),
+            action(
+                "切り替え",
+                input(&self.branch_input).map(BulkOperation::Switch)
+            ),
+            text_input("新しいブランチ名", &self.new_branch_input)
+                .on_input(Message::NewBranchInputChanged)
+                .width(140),
+            action(
+                "作成",
+                input(&self.new_branch_input).map(BulkOperation::CreateBranch)
+            ),
+            text_input("シェルコマンド", &self.command_input)
+                .on_input(Message::CommandInputChanged)
+                .width(200),
+            action(
+                "実行",
+                input(&self.command_input).map(BulkOperation::Command)
+            ),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center)
+        .wrap();
+
+        let mut c = column![bar].spacing(10);
+        if let Some(run) = &self.run {
+            c = c.push(self.view_results(run));
+        }
+        c.into()
+    }
+
+    /// targets に operation を並列に実行する
+    pub fn start(&mut self, operation: BulkOperation, targets: Vec<Target>) -> Task<Message> {
+        self.generation += 1;
+        let generation = self.generation;
+        self.run = Some(BulkRun {
+            operation: operation.clone(),
+            rows: targets
+                .iter()
+                .map(|x| ResultRow {
+                    target: x.clone(),
+                    outcome: None,
+                })
+                .collect(),
+        });
+
+        Task::run(run_all(targets, operation), move |(id, outcome)| {
+            Message::Finished(generation, id, outcome)
+        })
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::BranchInputChanged(value) => self.branch_input = value,
+            Message::NewBranchInputChanged(value) => self.new_branch_input = value,
+            Message::CommandInputChanged(value) => self.command_input = value,
+            Message::Run(_) => (),
+            Message::Finished(generation, id, outcome) => {
+                if generation != self.generation {
+                    return Task::none();
+                }
+                if let Some(row) = self
+                    .run
+                    .as_mut()
+                    .and_then(|x| x.rows.iter_mut().find(|x| x.target.id == id))
+                {
+                    row.outcome = Some(outcome);
+                }
+            }
+            Message::RerunFailed => {
+                if let Some(run) = self.run.take() {
+                    let targets = run
+                        .rows
+                        .into_iter()
+                        .filter(|x| x.outcome.as_ref().is_some_and(|x| x.is_failed()))
+                        .map(|x| x.target)
+                        .collect();
+                    return self.start(run.operation, targets);
+                }
+            }
+            Message::Close => self.run = None,
+        }
+
+        Task::none()
+    }
+
+    fn view_results<'a>(&'a self, run: &'a BulkRun) -> Element<'a, Message> {
+        let done = run.rows.iter().filter(|x| x.outcome.is_some()).count();
+        let failed = run
+            .rows
+            .iter()
+            .filter(|x| x.outcome.as_ref().is_some_and(|x| x.is_failed()))
+            .count();
+
+        let header = row![
+            text(format!(
+                "{}: {} / {} 件完了、失敗 {} 件",
+                run.operation,
+                done,
+                run.rows.len(),
+                failed
+            )),
+            space().width(Length::Fill),
+            button("失敗したものを再実行")
+                .on_press_maybe((failed > 0 && !self.is_running()).then_some(Message::RerunFailed))
+                .style(button::secondary),
+            button("閉じる")
+                .on_press_maybe((!self.is_running()).then_some(Message::Close))
+                .style(button::text),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let rows = run.rows.iter().map(|row| {
+            let (status, detail, style): (_, _, fn(&iced::Theme) -> text::Style) =
+                match &row.outcome {
+                    None => ("実行中", "", text::default),
+                    Some(BulkOutcome::Succeeded(x)) => ("成功", x.as_str(), text::success),
+                    Some(BulkOutcome::Skipped(x)) => ("スキップ", x.as_str(), text::secondary),
+                    Some(BulkOutcome::Failed(x)) => ("失敗", x.as_str(), text::danger),
+                };
+            Element::from(
+                row![
+                    text(&row.target.name).width(Length::FillPortion(2)),
+                    text(status).style(style).width(Length::FillPortion(1)),
+                    text(detail)
+                        .size(12)
+                        .font(iced::Font::MONOSPACE)
+                        .width(Length::FillPortion(5)),
+                ]
+                .spacing(10),
+            )
+        });
+        let table = column![
+            row![
+                text("リポジトリ").width(Length::FillPortion(2)),
+                text("結果").width(Length::FillPortion(1)),
+                text("出力").width(Length::FillPortion(5)),
+            ]
+            .spacing(10),
+            scrollable(Column::with_children(rows).spacing(4))
+                .height((run.rows.len() as f32 * ROW_HEIGHT).min(RESULTS_HEIGHT)),
+        ]
+        .spacing(6);
+
+        container(column![header, table].spacing(10))
+            .padding(10)
+            .style(container::rounded_box)
+            .into()
+    }
+}
+
+/// 対象をスレッドで並列に処理し、終わった順に (id, 結果) を流す
+fn run_all(
+    targets: Vec<Target>,
+    operation: BulkOperation,
+) -> impl Stream<Item = (usize, BulkOutcome)> {
+    let (sender, receiver) = mpsc::unbounded();
+
+    thread::spawn(move || {
+        let next = AtomicUsize::new(0);
+        let workers = thread::available_parallelism().map_or(4, |x| x.get());
+        thread::scope(|scope| {
+            for _ in 0..workers.min(targets.len()) {
+                scope.spawn(|| {
+                    while let Some(target) = targets.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let outcome = run_bulk_operation(&target.path, target.kind, &operation);
+                        if sender.unbounded_send((target.id, outcome)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+    });
+
+    receiver
+}
@@ -2,41 +2,110 @@ use std::path::PathBuf;
 
 use endringer::repository::Repository;
 use endringer::types::StatusDigest;
-use iced::widget::{Column, button, column, container, text};
-use iced::{Alignment, Element, Length};
+use iced::widget::{Column, Row, button, column, container, row, space, text};
+use iced::{Alignment, Element, Length, Task, Theme};
 
+use crate::app::components::common::badge::badge;
+use crate::app::components::common::dialog::Dialog;
 use crate::app::components::common::select::{self, Select};
+use crate::app::components::dashboard::remote::{self, Remote};
+use crate::app::components::dashboard::table;
+use crate::app::discovery::RepositoryKind;
 use crate::app::utils::system_time_to_string;
+use crate::app::vcs::branch::{SwitchMode, switch_branch};
+use crate::app::vcs::status::WorkingTreeStatus;
+use crate::app::vcs::tag::LatestTag;
+use crate::app::vcs::upstream::UpstreamStatus;
+use crate::app::workspace::ColumnWidths;
 
 #[derive(Debug, Clone)]
 pub struct Card {
     pub id: usize,
     pub path: PathBuf,
-    pub repository: Repository,
+    pub kind: RepositoryKind,
+    /// 開けなかった場合は None（理由は error に入る）
+    pub repository: Option<Repository>,
     pub status_digest: Option<StatusDigest>,
     pub branch_selector: Select,
+    /// 読み込んだときのローカルブランチの名前
+    pub local_branches: Vec<String>,
+    /// bare リポジトリや取得に失敗した場合は None
+    pub working_tree_status: Option<WorkingTreeStatus>,
+    pub upstream_status: Option<UpstreamStatus>,
+    /// 最後のコミットの作者（StatusDigest には含まれない）
+    pub last_commit_author: Option<String>,
+    /// 最も近いタグと、それより後のコミット数
+    pub latest_tag: Option<LatestTag>,
+    /// 忘れている stash に気づけるようにバッジで出す
+    pub stash_count: usize,
+    pub error: Option<String>,
+    /// 直前の操作（ブランチ切り替えなど）が失敗したときのメッセージ
+    pub operation_error: Option<String>,
+    /// 変更を監視できないときのメッセージ（自動では読み直されない）
+    pub watch_error: Option<String>,
+    /// 確認待ちの切り替え先ブランチ
+    pub pending_switch: Option<String>,
+    /// fetch / pull / push の途中経過と結果
+    pub remote: Remote,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    SelectMessage(select::Message),
+    Select(select::Message),
+    /// 親が詳細を Drawer に開く
     ShowDetail,
+    Retry,
+    SwitchConfirmed(SwitchMode),
+    SwitchCancelled,
+    SwitchFinished(Result<(), String>),
+    Remote(remote::Message),
 }
 
 impl Card {
     pub fn new(
         id: usize,
         path: PathBuf,
+        kind: RepositoryKind,
         repository: Repository,
         status_digest: Option<StatusDigest>,
         branch_selector: Select,
     ) -> Self {
         Self {
-            id,
-            path,
-            repository,
+            repository: Some(repository),
             status_digest,
             branch_selector,
+            ..Self::blank(id, path, kind)
+        }
+    }
+
+    /// リポジトリを読み込めなかったカードを作成
+    pub fn failed(id: usize, path: PathBuf, kind: RepositoryKind, error: String) -> Self {
+        Self {
+            error: Some(error),
+            ..Self::blank(id, path, kind)
+        }
+    }
+
+    /// 何も読み込んでいない状態のカード
+    fn blank(id: usize, path: PathBuf, kind: RepositoryKind) -> Self {
+        Self {
+            id,
+            remote: Remote::new(path.clone(), kind),
+            path,
+            kind,
+            repository: None,
+            status_digest: None,
+            branch_selector: Select::new(vec![], ""),
+            local_branches: vec![],
+            working_tree_status: None,
+            upstream_status: None,
+            last_commit_author: None,
+            latest_tag: None,
+            stash_count: 0,
+            operation_error: None,
+            watch_error: None,
+            pending_switch: None,
+            error: None,
         }
     }
 
@@ -46,7 +115,7 @@ impl Card {
         let dir_name = self
             .path
             .file_name()
-            .unwrap()
+            .unwrap_or(self.path.as_os_str())
             .to_string_lossy()
             .into_owned();
         if let Some(status_digest) = self.status_digest.clone() {
@@ -67,34 +136,355 @@ impl Card {
             c = c.push(text(dir_name).size(20));
         };
 
-        c = c.push(button("詳細").on_press(Message::ShowDetail));
+        c = c.push(text(self.kind.to_string()).size(12));
+        if let Some(tag) = &self.latest_tag {
+            c = c.push(text(tag.to_string()).size(12));
+        }
+
+        if let Some(status) = self.working_tree_status {
+            c = c.push(view_status_badges(status));
+        }
+        if let Some(status) = &self.upstream_status {
+            c = c.push(view_upstream_badges(status));
+        }
+        if self.stash_count > 0 {
+            c = c.push(view_stash_badge(self.stash_count));
+        }
+        if let Some(error) = &self.watch_error {
+            c = c.push(text(error).size(12).style(text::warning));
+        }
 
-        c = c.push(
-            self.branch_selector
-                .view()
-                .map(move |msg| Message::SelectMessage(msg)),
-        );
+        if let Some(error) = &self.error {
+            c = c.push(text(error).style(text::danger));
+            c = c.push(button("再試行").on_press(Message::Retry));
+        } else {
+            c = c.push(button("詳細").on_press(Message::ShowDetail));
+
+            c = c.push(self.branch_selector.view().map(Message::Select));
+            c = c.push(self.remote.view().map(Message::Remote));
+
+            if let Some(error) = &self.operation_error {
+                c = c.push(text(error).size(12).style(text::danger));
+            }
+        }
 
         container(c.spacing(10).align_x(Alignment::Center))
             .padding(20)
             .width(Length::Fixed(150.0))
-            .style(container::rounded_box) // 0.14のスタイル指定
+            .style(self.container_style())
             .into()
     }
 
-    pub fn update(&mut self, message: Message) {
+    /// 一行に収めた表示
+    pub fn view_compact(&self) -> Element<'_, Message> {
+        let mut r = row![
+            text(self.name()).size(16).width(200),
+            text(self.current_branch().unwrap_or_default())
+                .size(13)
+                .width(160),
+            text(self.kind.to_string()).size(12).width(100),
+            text(
+                self.latest_tag
+                    .as_ref()
+                    .map(|x| x.to_string())
+                    .unwrap_or_default()
+            )
+            .size(12)
+            .width(100),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        if let Some(status) = self.working_tree_status {
+            r = r.push(view_status_badges(status));
+        }
+        if let Some(status) = &self.upstream_status {
+            r = r.push(view_upstream_badges(status));
+        }
+        if self.stash_count > 0 {
+            r = r.push(view_stash_badge(self.stash_count));
+        }
+        r = r.push(match &self.error {
+            Some(error) => text(error).size(13).style(text::danger),
+            None => text(
+                self.status_digest
+                    .as_ref()
+                    .map(|x| x.last_commit_summary.clone())
+                    .unwrap_or_default(),
+            )
+            .size(13),
+        });
+        r = r.push(space().width(Length::Fill));
+        r = r.push(if self.error.is_some() {
+            button(text("再試行").size(13)).on_press(Message::Retry)
+        } else {
+            button(text("詳細").size(13)).on_press(Message::ShowDetail)
+        });
+
+        container(r)
+            .padding([6, 12])
+            .width(Length::Fill)
+            .style(self.container_style())
+            .into()
+    }
+
+    /// 表の一行。列の並びは table::Column::ALL と同じ。
+    pub fn view_table_row(&self, widths: &ColumnWidths) -> Element<'_, Message> {
+        let cells = table::Column::ALL.into_iter().map(|column| {
+            let content: Element<'_, Message> = match column {
+                table::Column::Repository => button(text(self.name()).size(13))
+                    .on_press(Message::ShowDetail)
+                    .padding(0)
+                    .style(button::text)
+                    .into(),
+                table::Column::Kind => text(self.kind.to_string()).size(13).into(),
+                table::Column::Branch => text(self.current_branch().unwrap_or_default())
+                    .size(13)
+                    .into(),
+                table::Column::Status => match (&self.error, self.working_tree_status) {
+                    (Some(error), _) => text(error).size(13).style(text::danger).into(),
+                    (None, Some(status)) => view_status_badges(status),
+                    (None, None) => text("-").size(13).into(),
+                },
+                table::Column::Upstream => match &self.upstream_status {
+                    Some(status) => view_upstream_badges(status),
+                    None => text("-").size(13).into(),
+                },
+                table::Column::LastCommit => text(
+                    self.status_digest
+                        .as_ref()
+                        .map(|x| {
+                            format!(
+                                "{} {}",
+                                system_time_to_string(x.last_commit_timestamp),
+                                x.last_commit_summary
+                            )
+                        })
+                        .unwrap_or_default(),
+                )
+                .size(13)
+                .wrapping(text::Wrapping::None)
+                .into(),
+                table::Column::Author => text(self.last_commit_author.clone().unwrap_or_default())
+                    .size(13)
+                    .into(),
+            };
+            table::cell(content, column.width(widths))
+        });
+
+        Row::with_children(cells)
+            .align_y(Alignment::Center)
+            .height(28)
+            .into()
+    }
+
+    /// ディレクトリ名
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or(self.path.as_os_str())
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn current_branch(&self) -> Option<String> {
+        self.status_digest
+            .as_ref()
+            .map(|x| x.current_branch.clone())
+    }
+
+    fn container_style(&self) -> impl Fn(&Theme) -> container::Style + use<> {
+        let is_dirty = self.working_tree_status.is_some_and(|x| x.is_dirty());
+        move |theme| {
+            let style = container::rounded_box(theme); // 0.14のスタイル指定
+            if is_dirty {
+                // 未コミットの変更があるカードは枠で目立たせる
+                container::Style {
+                    border: style.border.color(theme.palette().warning).width(2),
+                    ..style
+                }
+            } else {
+                style
+            }
+        }
+    }
+
+    /// 監視で変更を検知したときや操作の後に、読み込み直したカードから状態だけを取り込む。
+    /// 開いている詳細や確認待ちの操作、リモートの結果はそのまま残す。
+    pub fn refresh(&mut self, loaded: Card) {
+        // 読み込みに失敗した・失敗していたカードは丸ごと差し替える
+        if self.error.is_some() || loaded.error.is_some() {
+            // 監視とリモートの操作は続いているので、その状態は引き継ぐ
+            *self = Card {
+                watch_error: self.watch_error.take(),
+                remote: self.remote.clone(),
+                ..loaded
+            };
+            return;
+        }
+
+        self.repository = loaded.repository;
+        self.status_digest = loaded.status_digest;
+        self.working_tree_status = loaded.working_tree_status;
+        self.upstream_status = loaded.upstream_status;
+        self.last_commit_author = loaded.last_commit_author;
+        self.local_branches = loaded.local_branches;
+        self.latest_tag = loaded.latest_tag;
+        self.stash_count = loaded.stash_count;
+        if self.pending_switch.is_none() {
+            self.branch_selector = loaded.branch_selector;
+        }
+    }
+
+    /// ブランチ切り替えや認証の確認ダイアログ。確認待ちでなければ None。
+    pub fn view_dialog(&self) -> Option<Element<'_, Message>> {
+        if let Some(dialog) = self.remote.view_dialog() {
+            return Some(dialog.map(Message::Remote));
+        }
+        let branch = self.pending_switch.as_ref()?;
+
+        let dialog = if self.kind.is_jj() {
+            Dialog::new(
+                format!("{} に移動", branch),
+                text("作業中の変更は今の change に残ります。"),
+            )
+            .action("jj new", Message::SwitchConfirmed(SwitchMode::JjNew))
+            .action("jj edit", Message::SwitchConfirmed(SwitchMode::JjEdit))
+        } else {
+            Dialog::new(
+                format!("{} に切り替え", branch),
+                text("コミットされていない変更があります。どうしますか?"),
+            )
+            .action(
+                "stash して切り替え",
+                Message::SwitchConfirmed(SwitchMode::Stash),
+            )
+            .action(
+                "変更を持ったまま切り替え",
+                Message::SwitchConfirmed(SwitchMode::Carry),
+            )
+        };
+
+        Some(
+            dialog
+                .secondary_action("中止", Message::SwitchCancelled)
+                .view(),
+        )
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::SelectMessage(select_message) => {
+            Message::Remote(remote_message) => {
+                return self.remote.update(remote_message).map(Message::Remote);
+            }
+            Message::Select(select_message) => {
                 // 1. まず子に処理させて、子の状態を更新する
                 self.branch_selector.update(select_message.clone());
 
                 // 2. その上で、もし「選択」イベントだったら親としての追加処理をする
                 if let select::Message::OptionSelected(selected_value) = select_message {
-                    println!("親が選択を検知しました: {}", selected_value);
-                    // ここで親にしかできない処理（API呼び出しなど）を書く
+                    return self.request_switch(selected_value.label);
                 }
             }
+            Message::SwitchConfirmed(mode) => {
+                if let Some(branch) = self.pending_switch.take() {
+                    return self.switch(branch, mode);
+                }
+            }
+            Message::SwitchCancelled => {
+                self.pending_switch = None;
+                self.branch_selector.clear();
+            }
+            // 成功時は親がカードを読み込み直す
+            Message::SwitchFinished(Err(err)) => {
+                self.operation_error = Some(err);
+                self.branch_selector.clear();
+            }
             _ => {}
         }
+
+        Task::none()
+    }
+
+    /// 作業ツリーの状態を見て、必要なら確認を挟んでから切り替える
+    fn request_switch(&mut self, branch: String) -> Task<Message> {
+        if self
+            .status_digest
+            .as_ref()
+            .is_some_and(|x| x.current_branch == branch)
+        {
+            return Task::none();
+        }
+
+        // jj は new / edit のどちらにするかを選んでもらう
+        if self.kind.is_jj() || self.working_tree_status.is_some_and(|x| x.is_dirty()) {
+            self.pending_switch = Some(branch);
+            return Task::none();
+        }
+        self.switch(branch, SwitchMode::Carry)
+    }
+
+    fn switch(&mut self, branch: String, mode: SwitchMode) -> Task<Message> {
+        self.operation_error = None;
+
+        let path = self.path.clone();
+        let kind = self.kind;
+        Task::perform(
+            async move { switch_branch(&path, kind, &branch, mode) },
+            Message::SwitchFinished,
+        )
+    }
+}
+
+/// バッジの色
+type BadgeStyle = fn(&Theme) -> container::Style;
+
+/// 作業ツリーの変更件数をバッジで並べる
+pub fn view_status_badges<'a, M: 'a>(status: WorkingTreeStatus) -> Element<'a, M> {
+    if !status.is_dirty() {
+        return badge("変更なし", container::success);
+    }
+
+    let counts: [(usize, &str, BadgeStyle); 6] = [
+        (status.staged, "staged", container::success),
+        (status.modified, "modified", container::warning),
+        (status.untracked, "untracked", container::secondary),
+        (status.deleted, "deleted", container::danger),
+        (status.renamed, "renamed", container::primary),
+        (status.conflicted, "conflicted", container::danger),
+    ];
+    let badges = counts
+        .into_iter()
+        .filter(|(count, _, _)| *count > 0)
+        .map(|(count, label, style)| badge(format!("{} {}", label, count), style));
+
+    Row::with_children(badges).spacing(4).wrap().into()
+}
+
+/// 残っている stash の数
+pub fn view_stash_badge<'a, M: 'a>(count: usize) -> Element<'a, M> {
+    badge(format!("stash {}", count), container::warning)
+}
+
+/// upstream との ahead/behind をバッジで並べる
+pub fn view_upstream_badges<'a, M: 'a>(status: &UpstreamStatus) -> Element<'a, M> {
+    match status {
+        UpstreamStatus::Detached => badge("detached", container::danger),
+        UpstreamStatus::NoUpstream { .. } => badge("upstream なし", container::secondary),
+        UpstreamStatus::Tracking {
+            ahead: 0,
+            behind: 0,
+            ..
+        } => badge("push 済み", container::success),
+        UpstreamStatus::Tracking { ahead, behind, .. } => {
+            let mut badges = Row::new().spacing(4);
+            if *ahead > 0 {
+                badges = badges.push(badge(format!("↑{}", ahead), container::warning));
+            }
+            if *behind > 0 {
+                badges = badges.push(badge(format!("↓{}", behind), container::primary));
+            }
+            badges.into()
+        }
     }
 }
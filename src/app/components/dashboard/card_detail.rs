@@ -1,59 +1,405 @@
-use std::{collections::HashMap, path::PathBuf, rc::Rc};
+use std::path::PathBuf;
 
-use endringer::types::{CommitInfo, DagInfo};
+use iced::widget::{Column, button, checkbox, column, row, scrollable, text};
+use iced::{Alignment, Element, Length, Task};
 
-#[derive(Debug, Clone)]
+use crate::app::components::dashboard::branches::{self, Branches};
+use crate::app::components::dashboard::card::{self, Card};
+use crate::app::components::dashboard::changes::{self, Changes};
+use crate::app::components::dashboard::commit_editor::{self, CommitEditor};
+use crate::app::components::dashboard::commit_graph::CommitGraph;
+use crate::app::components::dashboard::history::{self, History};
+use crate::app::components::dashboard::stashes::{self, Stashes};
+use crate::app::components::dashboard::tags::{self, Tags};
+use crate::app::discovery::RepositoryKind;
+use crate::app::utils::system_time_to_string;
+use crate::app::vcs::graph::graph_commits;
+use crate::app::vcs::remote::{RemoteInfo, list_remotes};
+use crate::app::watch::Change;
+
+/// グラフに表示する最大コミット数
+const GRAPH_COMMIT_LIMIT: usize = 500;
+
+/// 読み込み中は None
+type Listing<T> = Option<Result<Vec<T>, String>>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tab {
+    #[default]
+    Overview,
+    History,
+    Branches,
+    Tags,
+    Remotes,
+    Stashes,
+    Changes,
+}
+
+impl Tab {
+    const ALL: [Tab; 7] = [
+        Tab::Overview,
+        Tab::History,
+        Tab::Branches,
+        Tab::Tags,
+        Tab::Remotes,
+        Tab::Stashes,
+        Tab::Changes,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Tab::Overview => "概要",
+            Tab::History => "履歴",
+            Tab::Branches => "ブランチ",
+            Tab::Tags => "タグ",
+            Tab::Remotes => "リモート",
+            Tab::Stashes => "stash",
+            Tab::Changes => "変更",
+        }
+    }
+}
+
+/// Drawer に表示するリポジトリの詳細
+#[derive(Debug)]
 pub struct CardDetail {
     pub id: usize,
-    pub path: PathBuf,
-    pub status_digest: Option<StatusDigest>,
+    path: PathBuf,
+    kind: RepositoryKind,
+    tab: Tab,
+    /// メタデータが変わって履歴を読み直すたびに増やす。古い読み込み結果を捨てるのに使う。
+    generation: usize,
+    /// 履歴の代わりにグラフを表示するか
+    show_graph: bool,
+    /// グラフを初めて表示するときに読み込む
+    commit_graph: Option<Result<CommitGraph, String>>,
+    /// ページ単位で読み込む
+    history: History,
+    changes: Changes,
+    commit_editor: CommitEditor,
+    branches: Branches,
+    tags: Tags,
+    remotes: Listing<RemoteInfo>,
+    stashes: Stashes,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    DemoDelete, // 削除ボタンが押された
+    TabSelected(Tab),
+    GraphToggled(bool),
+    /// generation が今のものと違えば捨てる
+    GraphLoaded(usize, Result<CommitGraph, String>),
+    /// generation が今のものと違えば捨てる
+    History(usize, history::Message),
+    /// Finished を受けたら親がカードの件数を取り直す
+    Changes(changes::Message),
+    /// Finished(Ok) を受けたら親がカードを読み込み直す
+    CommitEditor(commit_editor::Message),
+    /// Finished を受けたら親がカードを読み込み直す
+    Branches(branches::Message),
+    /// Finished を受けたら親がカードを読み込み直す
+    Tags(tags::Message),
+    RemotesLoaded(Result<Vec<RemoteInfo>, String>),
+    /// Finished を受けたら親がカードを読み込み直す
+    Stashes(stashes::Message),
 }
 
 impl CardDetail {
-    pub fn new(id: usize, path: PathBuf, status_digest: Option<StatusDigest>) -> Self {
+    pub fn new(card: &Card) -> Self {
         Self {
-            id,
-            path,
-            status_digest,
-        }
-    }
-
-    pub fn view(&self) -> Element<'_, Message> {
-        let mut c: Column<'_, Message> = column![];
-
-        let dir_name = self
-            .path
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .into_owned();
-        if let Some(status_digest) = self.status_digest.clone() {
-            // todo: currently endringer can't open repo whose name is different from dir name
-            // therefore, always go to else clause
-            let repo_name = if status_digest.repo_name != dir_name {
-                format!("{} (Dir: {})", status_digest.repo_name, dir_name)
-            } else {
-                status_digest.repo_name
-            };
-            c = c.push(text(repo_name).size(20));
-            c = c.push(text(status_digest.current_branch));
-            c = c.push(text(status_digest.last_commit_summary));
-            c = c.push(text(system_time_to_string(status_digest.last_commit_time)));
-        } else {
-            c = c.push(text(dir_name).size(20));
+            id: card.id,
+            path: card.path.clone(),
+            kind: card.kind,
+            tab: Tab::default(),
+            generation: 0,
+            show_graph: false,
+            commit_graph: None,
+            history: History::new(card.path.clone(), card.kind),
+            changes: Changes::new(card.path.clone(), card.kind),
+            commit_editor: CommitEditor::new(card.path.clone(), card.kind),
+            branches: Branches::new(card.path.clone(), card.kind),
+            tags: Tags::new(card.path.clone(), card.kind),
+            remotes: None,
+            stashes: Stashes::new(card.path.clone(), card.kind),
+        }
+    }
+
+    /// 各タブの中身を読み込む
+    pub fn load(&mut self) -> Task<Message> {
+        let mut tasks = vec![
+            {
+                let generation = self.generation;
+                self.history
+                    .load_first_page()
+                    .map(move |x| Message::History(generation, x))
+            },
+            self.changes.load().map(Message::Changes),
+            self.branches.load().map(Message::Branches),
+            self.tags.load().map(Message::Tags),
+            self.load_remotes(),
+            self.stashes.load().map(Message::Stashes),
+        ];
+        if self.show_graph {
+            tasks.push(self.load_graph());
+        }
+        Task::batch(tasks)
+    }
+
+    /// 監視やカードの読み込み直しに合わせて取り直す。
+    /// メタデータが変わったときは履歴やブランチなども取り直す。
+    /// index だけの変更（ステージなど）では履歴のスクロールや開いているコミットを残す。
+    pub fn reload(&mut self, change: Change) -> Task<Message> {
+        match change {
+            Change::WorkingTree | Change::Index => self.changes.load().map(Message::Changes),
+            Change::Metadata => {
+                self.generation += 1;
+                self.history = History::new(self.path.clone(), self.kind);
+                self.commit_graph = None;
+                self.load()
+            }
+        }
+    }
+
+    pub fn view<'a>(&'a self, card: &'a Card) -> Element<'a, Message> {
+        let tabs = row(Tab::ALL.into_iter().map(|tab| {
+            button(tab.label())
+                .on_press(Message::TabSelected(tab))
+                .style(if self.tab == tab {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+                .into()
+        }))
+        .spacing(10)
+        .wrap();
+
+        let content = match self.tab {
+            Tab::Overview => scrollable(view_overview(card)).height(Length::Fill).into(),
+            Tab::History => self.view_history(),
+            Tab::Branches => self
+                .branches
+                .view(self.history.selected_commit())
+                .map(Message::Branches),
+            Tab::Tags => self
+                .tags
+                .view(self.history.selected_commit())
+                .map(Message::Tags),
+            Tab::Remotes => view_listing(&self.remotes, "リモート", view_remote),
+            Tab::Stashes if self.kind.is_jj() => text("jj には stash がありません").into(),
+            Tab::Stashes => self.stashes.view().map(Message::Stashes),
+            Tab::Changes => self.view_changes(),
+        };
+
+        column![tabs, content].spacing(20).into()
+    }
+
+    /// 変更の破棄やブランチ・タグ・stash の削除などの確認ダイアログ
+    pub fn view_dialog(&self) -> Option<Element<'_, Message>> {
+        if let Some(dialog) = self.changes.view_dialog() {
+            return Some(dialog.map(Message::Changes));
+        }
+        if let Some(dialog) = self.stashes.view_dialog() {
+            return Some(dialog.map(Message::Stashes));
+        }
+        if let Some(dialog) = self.tags.view_dialog() {
+            return Some(dialog.map(Message::Tags));
+        }
+        self.branches
+            .view_dialog()
+            .map(|x| x.map(Message::Branches))
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::TabSelected(tab) => self.tab = tab,
+            Message::GraphToggled(value) => {
+                self.show_graph = value;
+                if value && self.commit_graph.is_none() {
+                    return self.load_graph();
+                }
+            }
+            // 読み込み中に読み直した場合は古い結果なので捨てる
+            Message::GraphLoaded(generation, _) | Message::History(generation, _)
+                if generation != self.generation => {}
+            Message::GraphLoaded(_, result) => self.commit_graph = Some(result),
+            Message::History(generation, history_message) => {
+                return self
+                    .history
+                    .update(history_message)
+                    .map(move |x| Message::History(generation, x));
+            }
+            Message::Changes(changes_message) => {
+                return self.changes.update(changes_message).map(Message::Changes);
+            }
+            Message::CommitEditor(editor_message) => {
+                return self
+                    .commit_editor
+                    .update(editor_message)
+                    .map(Message::CommitEditor);
+            }
+            Message::Branches(branches_message) => {
+                return self
+                    .branches
+                    .update(branches_message)
+                    .map(Message::Branches);
+            }
+            Message::Tags(tags_message) => {
+                return self.tags.update(tags_message).map(Message::Tags);
+            }
+            Message::RemotesLoaded(result) => self.remotes = Some(result),
+            Message::Stashes(stashes_message) => {
+                return self.stashes.update(stashes_message).map(Message::Stashes);
+            }
+        }
+
+        Task::none()
+    }
+
+    fn load_graph(&self) -> Task<Message> {
+        let path = self.path.clone();
+        let kind = self.kind;
+        let generation = self.generation;
+        Task::perform(
+            async move { graph_commits(&path, kind, GRAPH_COMMIT_LIMIT).map(CommitGraph::new) },
+            move |x| Message::GraphLoaded(generation, x),
+        )
+    }
+
+    /// リモートの一覧を読み込む（前の結果は読み込み終わるまで残す）
+    fn load_remotes(&self) -> Task<Message> {
+        let path = self.path.clone();
+        let kind = self.kind;
+        Task::perform(
+            async move { list_remotes(&path, kind) },
+            Message::RemotesLoaded,
+        )
+    }
+
+    fn view_history(&self) -> Element<'_, Message> {
+        let toggle = checkbox(self.show_graph)
+            .label("グラフで表示")
+            .on_toggle(Message::GraphToggled);
+        if !self.show_graph {
+            // 履歴は自前でスクロール・仮想化する
+            let generation = self.generation;
+            let history = self
+                .history
+                .view()
+                .map(move |x| Message::History(generation, x));
+            return column![toggle, history].spacing(10).into();
+        }
+
+        let graph: Element<'_, Message> = match &self.commit_graph {
+            Some(Ok(graph)) if graph.is_empty() => text("コミットがありません").into(),
+            Some(Ok(graph)) => scrollable(graph.view())
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into(),
+            Some(Err(err)) => text(format!("グラフを取得できません: {}", err))
+                .style(text::danger)
+                .into(),
+            None => text("読み込み中...").into(),
         };
+        column![toggle, graph].spacing(10).into()
+    }
 
-        c = c.push(button("削除").on_press(Message::DemoDelete));
+    fn view_changes(&self) -> Element<'_, Message> {
+        let mut content = column![self.changes.view().map(Message::Changes)].spacing(20);
+        if self.kind != RepositoryKind::Bare {
+            content = content.push(
+                self.commit_editor
+                    .view(self.changes.working_tree())
+                    .map(Message::CommitEditor),
+            );
+        }
 
-        container(c.spacing(10).align_x(Alignment::Center))
-            .padding(20)
-            .width(Length::Fixed(150.0))
-            .style(container::rounded_box) // 0.14のスタイル指定
+        scrollable(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
             .into()
     }
 }
+
+/// カードの情報をまとめて表示する
+fn view_overview(card: &Card) -> Element<'_, Message> {
+    let field = |label: &'static str, value: String| {
+        Element::from(
+            row![text(label).width(120).style(text::secondary), text(value)]
+                .spacing(10)
+                .align_y(Alignment::Center),
+        )
+    };
+
+    let mut c = column![
+        field("パス", card.path.display().to_string()),
+        field("種類", card.kind.to_string()),
+    ]
+    .spacing(10);
+    if let Some(status_digest) = &card.status_digest {
+        c = c.push(field("ブランチ", status_digest.current_branch.clone()));
+        c = c.push(field(
+            "最終コミット",
+            format!(
+                "{} {}",
+                system_time_to_string(status_digest.last_commit_timestamp),
+                status_digest.last_commit_summary
+            ),
+        ));
+    }
+    if let Some(author) = &card.last_commit_author {
+        c = c.push(field("作者", author.clone()));
+    }
+    if let Some(status) = card.working_tree_status {
+        c = c.push(
+            row![
+                text("変更").width(120).style(text::secondary),
+                card::view_status_badges(status)
+            ]
+            .spacing(10),
+        );
+    }
+    if let Some(status) = &card.upstream_status {
+        c = c.push(
+            row![
+                text("upstream").width(120).style(text::secondary),
+                card::view_upstream_badges(status)
+            ]
+            .spacing(10),
+        );
+    }
+    if let Some(error) = &card.error {
+        c = c.push(text(error).style(text::danger));
+    }
+    c.into()
+}
+
+/// 読み込み中・失敗・空を表示し、あれば一行ずつ並べる
+fn view_listing<'a, T>(
+    listing: &'a Listing<T>,
+    label: &'static str,
+    view_item: impl Fn(&'a T) -> Element<'a, Message>,
+) -> Element<'a, Message> {
+    match listing {
+        None => text("読み込み中...").into(),
+        Some(Err(err)) => text(format!("{}を取得できません: {}", label, err))
+            .style(text::danger)
+            .into(),
+        Some(Ok(items)) if items.is_empty() => text(format!("{}がありません", label)).into(),
+        Some(Ok(items)) => {
+            scrollable(Column::with_children(items.iter().map(view_item)).spacing(6))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into()
+        }
+    }
+}
+
+fn view_remote(remote: &RemoteInfo) -> Element<'_, Message> {
+    row![
+        text(&remote.name).width(120),
+        text(&remote.url).size(13).font(iced::Font::MONOSPACE),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center)
+    .into()
+}
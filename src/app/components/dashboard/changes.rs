@@ -0,0 +1,584 @@
+use std::path::PathBuf;
+
+use iced::font::Weight;
+use iced::widget::{Column, button, column, container, row, space, text};
+use iced::{Element, Font, Length, Task};
+
+use crate::app::components::common::dialog::Dialog;
+use crate::app::components::common::diff_view::{self, DiffView};
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::diff::{DiffOptions, FileDiff, Hunk};
+use crate::app::vcs::stage::{
+    LineSelection, WorkingTreeChanges, discard_file, discard_lines, discard_untracked,
+    hunk_selection, stage_file, stage_lines, stage_untracked, supports_partial, unstage_file,
+    unstage_lines, working_tree_changes,
+};
+
+/// 変更の置き場所
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Area {
+    Staged,
+    Unstaged,
+    Untracked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Stage,
+    Unstage,
+    Discard,
+}
+
+/// 操作の対象。Hunk と Lines は差分を表示しているファイルが対象。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    File(Area, usize),
+    Hunk(usize),
+    Lines,
+}
+
+/// 確認待ちの破棄。確認までに読み込み直して対象の中身が変わっていたら破棄しない。
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PendingDiscard {
+    target: Target,
+    /// 対象のファイルのパス
+    path: String,
+    /// 捨てるハンク（ファイル全体なら全部、未追跡なら空）
+    hunks: Vec<Hunk>,
+    selection: LineSelection,
+}
+
+/// 作業ツリーの変更を一覧し、ファイル・ハンク・行の単位でステージする
+#[derive(Debug, Clone)]
+pub struct Changes {
+    path: PathBuf,
+    kind: RepositoryKind,
+    /// ステージの操作に使う差分。空白を無視する設定でも、常に空白の変更まで含めて取る。
+    changes: Option<Result<WorkingTreeChanges, String>>,
+    /// 空白を無視するときに表示だけに使う差分
+    displayed: Option<WorkingTreeChanges>,
+    /// 差分を表示しているファイル（読み込み直しても保つようにパスで持つ）
+    selected: Option<(Area, String)>,
+    selection: LineSelection,
+    diff_view: DiffView,
+    /// 確認待ちの破棄
+    pending_discard: Option<PendingDiscard>,
+    running: bool,
+    /// 直前の操作が失敗したときのメッセージ
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Refresh,
+    /// (操作に使う差分, 空白を無視するときの表示用の差分)
+    Loaded(Result<(WorkingTreeChanges, Option<WorkingTreeChanges>), String>),
+    FileSelected(Area, usize),
+    LineToggled(usize, usize),
+    Run(Operation, Target),
+    DiscardConfirmed,
+    DiscardCancelled,
+    /// 成功・失敗にかかわらず、親はここで作業ツリーの件数を取り直す
+    Finished(Result<(), String>),
+    Diff(diff_view::Message),
+}
+
+impl Changes {
+    pub fn new(path: PathBuf, kind: RepositoryKind) -> Self {
+        Self {
+            path,
+            kind,
+            changes: None,
+            displayed: None,
+            selected: None,
+            selection: LineSelection::new(),
+            diff_view: DiffView::default(),
+            pending_discard: None,
+            running: false,
+            error: None,
+        }
+    }
+
+    pub fn load(&self) -> Task<Message> {
+        if self.kind == RepositoryKind::Bare {
+            return Task::none();
+        }
+
+        let path = self.path.clone();
+        let kind = self.kind;
+        let options = self.diff_view.options().clone();
+        Task::perform(
+            async move {
+                // ハンクや行を当てる差分は作業ツリーと一致していないといけないので、
+                // 空白の無視は表示用の差分にだけ使う
+                let exact = DiffOptions {
+                    ignore_whitespace: false,
+                    ..options.clone()
+                };
+                let changes = working_tree_changes(&path, kind, &exact)?;
+                let displayed = if options.ignore_whitespace {
+                    Some(working_tree_changes(&path, kind, &options)?)
+                } else {
+                    None
+                };
+                Ok((changes, displayed))
+            },
+            Message::Loaded,
+        )
+    }
+
+    /// 読み込み済みの変更（読み込み中・失敗時は None）
+    pub fn working_tree(&self) -> Option<&WorkingTreeChanges> {
+        self.changes.as_ref().and_then(|x| x.as_ref().ok())
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        if self.kind == RepositoryKind::Bare {
+            return text("bare リポジトリには作業ツリーがありません").into();
+        }
+
+        let header = row![
+            text("作業ツリーの変更").font(Font {
+                weight: Weight::Bold,
+                ..Default::default()
+            }),
+            space().width(Length::Fill),
+            button(text("更新"))
+                .on_press_maybe((!self.running).then_some(Message::Refresh))
+                .style(button::secondary),
+        ]
+        .align_y(iced::Alignment::Center);
+
+        let mut c = column![header].spacing(10);
+        if self.kind.is_jj() {
+            c = c.push(
+                text("jj では @ の直前に作るステージ用の change を index の代わりにします")
+                    .size(12),
+            );
+        }
+        if let Some(error) = &self.error {
+            c = c.push(text(error).size(12).style(text::danger));
+        }
+
+        let changes = match &self.changes {
+            None => return c.push(text("読み込み中...")).into(),
+            Some(Err(err)) => {
+                return c
+                    .push(text(format!("変更を取得できません: {}", err)).style(text::danger))
+                    .into();
+            }
+            Some(Ok(changes)) => changes,
+        };
+        if changes.is_empty() {
+            return c.push(text("変更はありません")).into();
+        }
+
+        let (staged_title, unstaged_title) = if self.kind.is_jj() {
+            ("@-（index の代わり）", "@（作業コピー）")
+        } else {
+            ("ステージ済み", "未ステージ")
+        };
+        c = c.push(self.view_section(staged_title, Area::Staged, &changes.staged));
+        c = c.push(self.view_section(unstaged_title, Area::Unstaged, &changes.unstaged));
+        if !changes.untracked.is_empty() {
+            let rows = changes
+                .untracked
+                .iter()
+                .enumerate()
+                .map(|(index, file)| self.view_untracked(index, file));
+            c = c.push(column![text("未追跡").size(14), Column::with_children(rows)].spacing(4));
+        }
+
+        if let Some((area, file)) = self.selected_file() {
+            c = c.push(self.view_diff(area, file));
+        }
+
+        c.into()
+    }
+
+    /// 破棄の確認ダイアログ。確認待ちでなければ None。
+    pub fn view_dialog(&self) -> Option<Element<'_, Message>> {
+        let target = self.pending_discard.as_ref()?.target;
+        let description = match target {
+            Target::File(Area::Untracked, index) => format!(
+                "{} を削除します。",
+                self.untracked(index).unwrap_or_default()
+            ),
+            Target::File(area, index) => format!(
+                "{} の変更を捨てます。",
+                self.file(area, index)
+                    .map(|x| x.display_path())
+                    .unwrap_or_default()
+            ),
+            Target::Hunk(_) => "このハンクの変更を捨てます。".to_owned(),
+            Target::Lines => format!("選択した {} 行の変更を捨てます。", self.selection.len()),
+        };
+
+        Some(
+            Dialog::new(
+                "変更の破棄",
+                text(format!("{}この操作は元に戻せません。", description)),
+            )
+            .danger_action("破棄", Message::DiscardConfirmed)
+            .secondary_action("中止", Message::DiscardCancelled)
+            .view(),
+        )
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Refresh => return self.load(),
+            Message::Loaded(result) => {
+                let result = result.map(|(changes, displayed)| {
+                    let shown = displayed.as_ref().unwrap_or(&changes);
+                    self.diff_view.prepare(&shown.staged);
+                    self.diff_view.prepare(&shown.unstaged);
+                    self.displayed = displayed;
+                    changes
+                });
+                self.changes = Some(result);
+                // ハンクの区切りが変わりうるので、行の選択はやり直してもらう
+                self.selection.clear();
+                // 表示中のファイルが無くなっていれば閉じる
+                if self.selected_file().is_none() {
+                    self.selected = None;
+                }
+            }
+            Message::FileSelected(area, index) => {
+                let selected = self.file(area, index).map(|x| (area, x.display_path()));
+                // 同じファイルをもう一度押したら閉じる
+                self.selected = if self.selected == selected {
+                    None
+                } else {
+                    selected
+                };
+                self.selection.clear();
+            }
+            Message::LineToggled(hunk, line) => {
+                if !self.selection.remove(&(hunk, line)) {
+                    self.selection.insert((hunk, line));
+                }
+            }
+            Message::Run(Operation::Discard, target) => {
+                self.pending_discard = self.discard_subject(target);
+            }
+            Message::Run(operation, target) => return self.run(operation, target),
+            Message::DiscardConfirmed => {
+                let Some(pending) = self.pending_discard.take() else {
+                    return Task::none();
+                };
+                // 確認中に読み込み直していれば、同じ位置に別のファイルやハンクがありうる
+                if self.discard_subject(pending.target).as_ref() != Some(&pending) {
+                    self.error = Some(
+                        "確認中に変更が更新されたので破棄を取りやめました。もう一度選んでください"
+                            .to_owned(),
+                    );
+                    return Task::none();
+                }
+                return self.run(Operation::Discard, pending.target);
+            }
+            Message::DiscardCancelled => self.pending_discard = None,
+            Message::Finished(result) => {
+                self.running = false;
+                self.error = result.err();
+                self.selection.clear();
+                return self.load();
+            }
+            Message::Diff(msg) => {
+                if self.diff_view.update(msg) {
+                    return self.load();
+                }
+            }
+        }
+
+        Task::none()
+    }
+
+    fn run(&mut self, operation: Operation, target: Target) -> Task<Message> {
+        if self.running {
+            return Task::none();
+        }
+
+        let path = self.path.clone();
+        let kind = self.kind;
+        let task: Box<dyn FnOnce() -> Result<(), String> + Send> = match target {
+            Target::File(Area::Untracked, index) => {
+                let Some(file) = self.untracked(index).map(|x| x.to_owned()) else {
+                    return Task::none();
+                };
+                match operation {
+                    Operation::Stage => Box::new(move || stage_untracked(&path, &file)),
+                    Operation::Discard => Box::new(move || discard_untracked(&path, &file)),
+                    Operation::Unstage => return Task::none(),
+                }
+            }
+            Target::File(area, index) => {
+                let Some(file) = self.file(area, index).cloned() else {
+                    return Task::none();
+                };
+                match operation {
+                    Operation::Stage => Box::new(move || stage_file(&path, kind, &file)),
+                    Operation::Unstage => Box::new(move || unstage_file(&path, kind, &file)),
+                    Operation::Discard => Box::new(move || discard_file(&path, kind, &file)),
+                }
+            }
+            Target::Hunk(_) | Target::Lines => {
+                let Some((_, file)) = self.selected_file() else {
+                    return Task::none();
+                };
+                let file = file.clone();
+                let selection = match target {
+                    Target::Hunk(hunk) => hunk_selection(&file, hunk),
+                    _ => self.selection.clone(),
+                };
+                match operation {
+                    Operation::Stage => {
+                        Box::new(move || stage_lines(&path, kind, &file, &selection))
+                    }
+                    Operation::Unstage => {
+                        Box::new(move || unstage_lines(&path, kind, &file, &selection))
+                    }
+                    Operation::Discard => Box::new(move || discard_lines(&path, &file, &selection)),
+                }
+            }
+        };
+
+        self.running = true;
+        self.error = None;
+        Task::perform(async move { task() }, Message::Finished)
+    }
+
+    fn view_section<'a>(
+        &'a self,
+        title: &'a str,
+        area: Area,
+        files: &'a [FileDiff],
+    ) -> Element<'a, Message> {
+        if files.is_empty() {
+            return column![text(title).size(14), text("なし").size(12)]
+                .spacing(4)
+                .into();
+        }
+
+        let rows = files.iter().enumerate().map(|(index, file)| {
+            let mut actions = row![].spacing(4);
+            match area {
+                Area::Staged => {
+                    actions = actions.push(self.action_button(
+                        self.unstage_label(),
+                        Message::Run(Operation::Unstage, Target::File(area, index)),
+                    ));
+                }
+                _ => {
+                    actions = actions.push(self.action_button(
+                        self.stage_label(),
+                        Message::Run(Operation::Stage, Target::File(area, index)),
+                    ));
+                    actions = actions.push(self.action_button(
+                        "破棄",
+                        Message::Run(Operation::Discard, Target::File(area, index)),
+                    ));
+                }
+            }
+
+            row![
+                self.file_button(area, index, file.display_path()),
+                text(format!("+{}", file.additions()))
+                    .size(12)
+                    .style(text::success),
+                text(format!("-{}", file.deletions()))
+                    .size(12)
+                    .style(text::danger),
+                actions,
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center)
+            .into()
+        });
+
+        column![text(title).size(14), Column::with_children(rows).spacing(2)]
+            .spacing(4)
+            .into()
+    }
+
+    fn view_untracked<'a>(&'a self, index: usize, file: &'a str) -> Element<'a, Message> {
+        let target = Target::File(Area::Untracked, index);
+        row![
+            text(file)
+                .size(13)
+                .wrapping(text::Wrapping::None)
+                .width(Length::Fill),
+            self.action_button(self.stage_label(), Message::Run(Operation::Stage, target)),
+            self.action_button("削除", Message::Run(Operation::Discard, target)),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center)
+        .into()
+    }
+
+    fn view_diff<'a>(&'a self, area: Area, file: &'a FileDiff) -> Element<'a, Message> {
+        let mut c = column![
+            text(file.display_path()).font(Font::MONOSPACE),
+            self.diff_view.view_controls().map(Message::Diff),
+        ]
+        .spacing(8);
+
+        // 空白を無視した差分は作業ツリーと一致しないので、表示だけにする
+        if let Some(displayed) = &self.displayed {
+            let files = match area {
+                Area::Staged => &displayed.staged,
+                _ => &displayed.unstaged,
+            };
+            c = c.push(text("空白を無視している間は、ハンクや行の単位では操作できません").size(12));
+            c = c.push(
+                match files
+                    .iter()
+                    .find(|x| x.display_path() == file.display_path())
+                {
+                    Some(file) => self.diff_view.view_file(file),
+                    None => text("空白以外の変更はありません").into(),
+                },
+            );
+            return container(c).width(Length::Fill).clip(true).into();
+        }
+        if !supports_partial(file) {
+            c = c.push(self.diff_view.view_file(file));
+            return container(c).width(Length::Fill).clip(true).into();
+        }
+
+        // 選択した行への操作
+        let line_button = |label: &'static str, operation: Operation| {
+            button(text(label).size(12))
+                .on_press_maybe(
+                    (!self.selection.is_empty() && !self.running)
+                        .then_some(Message::Run(operation, Target::Lines)),
+                )
+                .style(button::secondary)
+        };
+        c = c.push(if area == Area::Staged {
+            row![line_button("選択した行を戻す", Operation::Unstage)]
+        } else {
+            row![
+                line_button("選択した行をステージ", Operation::Stage),
+                line_button("選択した行を破棄", Operation::Discard),
+            ]
+            .spacing(8)
+        });
+
+        let stage_label = self.stage_label();
+        let unstage_label = self.unstage_label();
+        c = c.push(self.diff_view.view_selectable(
+            file,
+            &self.selection,
+            Message::LineToggled,
+            move |hunk| match area {
+                Area::Staged => vec![(
+                    unstage_label,
+                    Message::Run(Operation::Unstage, Target::Hunk(hunk)),
+                )],
+                _ => vec![
+                    (
+                        stage_label,
+                        Message::Run(Operation::Stage, Target::Hunk(hunk)),
+                    ),
+                    ("破棄", Message::Run(Operation::Discard, Target::Hunk(hunk))),
+                ],
+            },
+        ));
+
+        container(c).width(Length::Fill).clip(true).into()
+    }
+
+    fn file_button<'a>(&self, area: Area, index: usize, label: String) -> Element<'a, Message> {
+        let selected = self
+            .selected
+            .as_ref()
+            .is_some_and(|(a, p)| *a == area && *p == label);
+        button(text(label).size(13).wrapping(text::Wrapping::None))
+            .on_press(Message::FileSelected(area, index))
+            .style(if selected {
+                button::primary
+            } else {
+                button::text
+            })
+            .width(Length::Fill)
+            .into()
+    }
+
+    fn action_button<'a>(&self, label: &'a str, message: Message) -> Element<'a, Message> {
+        button(text(label).size(12))
+            .on_press_maybe((!self.running).then_some(message))
+            .style(button::secondary)
+            .into()
+    }
+
+    fn stage_label(&self) -> &'static str {
+        if self.kind.is_jj() {
+            "@- へ移す"
+        } else {
+            "ステージ"
+        }
+    }
+
+    fn unstage_label(&self) -> &'static str {
+        if self.kind.is_jj() {
+            "@ へ戻す"
+        } else {
+            "解除"
+        }
+    }
+
+    fn file(&self, area: Area, index: usize) -> Option<&FileDiff> {
+        let changes = self.changes.as_ref()?.as_ref().ok()?;
+        match area {
+            Area::Staged => changes.staged.get(index),
+            Area::Unstaged => changes.unstaged.get(index),
+            Area::Untracked => None,
+        }
+    }
+
+    fn untracked(&self, index: usize) -> Option<&str> {
+        let changes = self.changes.as_ref()?.as_ref().ok()?;
+        changes.untracked.get(index).map(|x| x.as_str())
+    }
+
+    /// target が今指しているものを破棄の確認用に控える
+    fn discard_subject(&self, target: Target) -> Option<PendingDiscard> {
+        let (path, hunks) = match target {
+            Target::File(Area::Untracked, index) => (self.untracked(index)?.to_owned(), vec![]),
+            Target::File(area, index) => {
+                let file = self.file(area, index)?;
+                (file.display_path(), file.hunks.clone())
+            }
+            Target::Hunk(hunk) => {
+                let (_, file) = self.selected_file()?;
+                (file.display_path(), vec![file.hunks.get(hunk)?.clone()])
+            }
+            Target::Lines => {
+                let (_, file) = self.selected_file()?;
+                (file.display_path(), file.hunks.clone())
+            }
+        };
+        Some(PendingDiscard {
+            target,
+            path,
+            hunks,
+            selection: self.selection.clone(),
+        })
+    }
+
+    /// 差分を表示しているファイル（未追跡のファイルには差分が無い）
+    fn selected_file(&self) -> Option<(Area, &FileDiff)> {
+        let (area, path) = self.selected.as_ref()?;
+        let changes = self.changes.as_ref()?.as_ref().ok()?;
+        let files = match area {
+            Area::Staged => &changes.staged,
+            Area::Unstaged => &changes.unstaged,
+            Area::Untracked => return None,
+        };
+        files
+            .iter()
+            .find(|x| x.display_path() == *path)
+            .map(|x| (*area, x))
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::SystemTime;
+
+use iced::widget::{Column, button, checkbox, column, container, row, scrollable, space, text};
+use iced::{Alignment, Element, Length, Task};
+
+use crate::app::components::common::badge::badge;
+use crate::app::components::common::dialog::Dialog;
+use crate::app::components::dashboard::bulk::Target;
+use crate::app::vcs::cleanup::{
+    StaleBranch, delete_commands, delete_stale_branches, stale_branches,
+};
+
+/// ワークスペース全体の、取り込み済みや upstream が消えたブランチの一覧と削除
+#[derive(Debug)]
+pub struct Cleanup {
+    reports: Vec<Report>,
+    /// 削除するブランチ（リポジトリの id とブランチ名）
+    selected: BTreeSet<(usize, String)>,
+    /// 実行せずに、実行するコマンドだけを表示する
+    dry_run: bool,
+    /// dry run で表示しているコマンド
+    preview: Option<Vec<String>>,
+    /// 強制削除 (-D) の確認を待っているブランチ（リポジトリ名とブランチ名）
+    pending_force: Option<Vec<String>>,
+    /// 削除中のリポジトリの数
+    running: usize,
+}
+
+#[derive(Debug)]
+struct Report {
+    target: Target,
+    /// 読み込み中は None
+    branches: Option<Result<Vec<StaleBranch>, String>>,
+    /// 削除した結果（ブランチ名ごと）
+    results: BTreeMap<String, Result<(), String>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Loaded(usize, Result<Vec<StaleBranch>, String>),
+    Toggled(usize, String, bool),
+    SelectAll,
+    SelectNone,
+    DryRunToggled(bool),
+    Delete,
+    DeleteConfirmed,
+    DeleteCancelled,
+    /// リポジトリごとの、ブランチ名と結果。一つでも消せたら親がそのカードを読み込み直す。
+    Deleted(usize, Vec<(String, Result<(), String>)>),
+}
+
+impl Cleanup {
+    /// targets と、それぞれのカードで読み込んだローカルブランチから一覧を作る
+    pub fn new(targets: Vec<(Target, Vec<String>)>) -> (Self, Task<Message>) {
+        let tasks = targets
+            .iter()
+            .map(|(target, local_branches)| {
+                let id = target.id;
+                let path = target.path.clone();
+                let kind = target.kind;
+                let local_branches = local_branches.clone();
+                Task::perform(
+                    async move { stale_branches(&path, kind, &local_branches) },
+                    move |result| Message::Loaded(id, result),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let cleanup = Self {
+            reports: targets
+                .into_iter()
+                .map(|(target, _)| Report {
+                    target,
+                    branches: None,
+                    results: BTreeMap::new(),
+                })
+                .collect(),
+            selected: BTreeSet::new(),
+            dry_run: true,
+            preview: None,
+            pending_force: None,
+            running: 0,
+        };
+        (cleanup, Task::batch(tasks))
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let loading = self.reports.iter().filter(|x| x.branches.is_none()).count();
+        let found = self
+            .reports
+            .iter()
+            .filter_map(|x| x.branches.as_ref()?.as_ref().ok())
+            .map(|x| x.len())
+            .sum::<usize>();
+        let enabled = self.running == 0 && !self.selected.is_empty();
+
+        let summary = if loading > 0 {
+            format!(
+                "調べています... ({} / {} リポジトリ)",
+                self.reports.len() - loading,
+                self.reports.len()
+            )
+        } else {
+            format!(
+                "{} リポジトリで {} 件見つかりました",
+                self.reports.len(),
+                found
+            )
+        };
+        let bar = row![
+            text(summary),
+            space().width(Length::Fill),
+            button(text("-D 以外をすべて選択"))
+                .on_press(Message::SelectAll)
+                .style(button::secondary),
+            button(text("選択を解除"))
+                .on_press(Message::SelectNone)
+                .style(button::text),
+            checkbox(self.dry_run)
+                .label("dry run")
+                .on_toggle(Message::DryRunToggled),
+            button(text(if self.dry_run {
+                format!("{} 件の削除をプレビュー", self.selected.len())
+            } else {
+                format!("{} 件を削除", self.selected.len())
+            }))
+            .on_press_maybe(enabled.then_some(Message::Delete))
+            .style(if self.dry_run {
+                button::primary
+            } else {
+                button::danger
+            }),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center)
+        .wrap();
+
+        let mut c = column![bar].spacing(20);
+        if let Some(commands) = &self.preview {
+            c = c.push(
+                container(
+                    Column::with_children(
+                        commands
+                            .iter()
+                            .map(|x| text(x).size(12).font(iced::Font::MONOSPACE).into()),
+                    )
+                    .spacing(2),
+                )
+                .padding(10)
+                .width(Length::Fill)
+                .style(container::rounded_box),
+            );
+        }
+
+        // 候補の無いリポジトリは出さない
+        let reports = self
+            .reports
+            .iter()
+            .filter(|x| !matches!(&x.branches, Some(Ok(branches)) if branches.is_empty()))
+            .map(|x| self.view_report(x));
+        c.push(
+            scrollable(Column::with_children(reports).spacing(20))
+                .width(Length::Fill)
+                .height(Length::Fill),
+        )
+        .into()
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Loaded(id, result) => {
+                if let Some(report) = self.reports.iter_mut().find(|x| x.target.id == id) {
+                    report.branches = Some(result);
+                }
+            }
+            Message::Toggled(id, branch, value) => {
+                if value {
+                    self.selected.insert((id, branch));
+                } else {
+                    self.selected.remove(&(id, branch));
+                }
+                self.preview = None;
+            }
+            // 取り込まれていないブランチは、一つずつ選んだときだけ消す
+            Message::SelectAll => {
+                self.selected = self
+                    .candidates()
+                    .filter(|(report, branch)| !needs_force(report, branch))
+                    .map(|(report, branch)| (report.target.id, branch.name.clone()))
+                    .collect();
+                self.preview = None;
+            }
+            Message::SelectNone => {
+                self.selected.clear();
+                self.preview = None;
+            }
+            Message::DryRunToggled(value) => {
+                self.dry_run = value;
+                self.preview = None;
+            }
+            Message::Delete if self.dry_run => {
+                self.preview = Some(
+                    self.selected_by_report()
+                        .into_iter()
+                        .flat_map(|(report, branches)| {
+                            delete_commands(report.target.kind, &branches)
+                                .into_iter()
+                                .map(|x| format!("cd {} && {}", report.target.path.display(), x))
+                        })
+                        .collect(),
+                );
+            }
+            // 強制削除が含まれていれば、一覧を見せて確認する
+            Message::Delete => {
+                let force = self
+                    .selected_branches()
+                    .filter(|(report, branch)| needs_force(report, branch))
+                    .map(|(report, branch)| format!("{}: {}", report.target.name, branch.name))
+                    .collect::<Vec<_>>();
+                if force.is_empty() {
+                    return self.delete();
+                }
+                self.pending_force = Some(force);
+            }
+            Message::DeleteConfirmed => {
+                if self.pending_force.take().is_some() {
+                    return self.delete();
+                }
+            }
+            Message::DeleteCancelled => self.pending_force = None,
+            Message::Deleted(id, results) => {
+                self.running = self.running.saturating_sub(1);
+                if let Some(report) = self.reports.iter_mut().find(|x| x.target.id == id) {
+                    report.results.extend(results);
+                }
+            }
+        }
+
+        Task::none()
+    }
+
+    /// 強制削除の確認
+    pub fn view_dialog(&self) -> Option<Element<'_, Message>> {
+        let force = self.pending_force.as_ref()?;
+        let body = column![
+            text("次のブランチは既定のブランチに取り込まれていません。コミットが失われることがあります。"),
+            Column::with_children(
+                force
+                    .iter()
+                    .map(|x| text(x).size(12).font(iced::Font::MONOSPACE).into()),
+            )
+            .spacing(2),
+        ]
+        .spacing(10);
+        Some(
+            Dialog::new(format!("{} 件を強制的に削除", force.len()), body)
+                .danger_action("削除", Message::DeleteConfirmed)
+                .secondary_action("中止", Message::DeleteCancelled)
+                .view(),
+        )
+    }
+
+    /// 選んだブランチをリポジトリごとにまとめて削除する。
+    /// 同じリポジトリの ref を並行して書き換えないように、リポジトリごとに一つのタスクにする。
+    fn delete(&mut self) -> Task<Message> {
+        let tasks = self
+            .selected_by_report()
+            .into_iter()
+            .map(|(report, branches)| {
+                let id = report.target.id;
+                let path = report.target.path.clone();
+                let kind = report.target.kind;
+                let branches = branches.into_iter().cloned().collect::<Vec<_>>();
+                Task::perform(
+                    async move {
+                        delete_stale_branches(&path, kind, &branches.iter().collect::<Vec<_>>())
+                    },
+                    move |results| Message::Deleted(id, results),
+                )
+            })
+            .collect::<Vec<_>>();
+        self.running = tasks.len();
+        self.selected.clear();
+        self.preview = None;
+        Task::batch(tasks)
+    }
+
+    /// まだ削除していない候補
+    fn candidates(&self) -> impl Iterator<Item = (&Report, &StaleBranch)> {
+        self.reports.iter().flat_map(|report| {
+            report
+                .branches
+                .iter()
+                .flatten()
+                .flatten()
+                .filter(|x| !report.results.get(&x.name).is_some_and(|x| x.is_ok()))
+                .map(move |branch| (report, branch))
+        })
+    }
+
+    fn selected_branches(&self) -> impl Iterator<Item = (&Report, &StaleBranch)> {
+        self.candidates().filter(|(report, branch)| {
+            self.selected
+                .contains(&(report.target.id, branch.name.clone()))
+        })
+    }
+
+    /// 選んだブランチをリポジトリごとにまとめる
+    fn selected_by_report(&self) -> Vec<(&Report, Vec<&StaleBranch>)> {
+        let mut groups = Vec::<(&Report, Vec<&StaleBranch>)>::new();
+        for (report, branch) in self.selected_branches() {
+            match groups.last_mut() {
+                Some((x, branches)) if x.target.id == report.target.id => branches.push(branch),
+                _ => groups.push((report, vec![branch])),
+            }
+        }
+        groups
+    }
+
+    fn view_report<'a>(&'a self, report: &'a Report) -> Element<'a, Message> {
+        let title = text(&report.target.name).size(18);
+        let branches = match &report.branches {
+            None => return column![title, text("読み込み中...")].spacing(6).into(),
+            Some(Err(err)) => {
+                return column![title, text(err).size(12).style(text::danger)]
+                    .spacing(6)
+                    .into();
+            }
+            Some(Ok(branches)) => branches,
+        };
+
+        let now = SystemTime::now();
+        let rows = branches.iter().map(|branch| {
+            let id = report.target.id;
+            let name = branch.name.clone();
+            let result = report.results.get(&branch.name);
+            let check: Element<'a, Message> = match result {
+                Some(Ok(())) => text("削除済み").size(12).style(text::success).into(),
+                _ => checkbox(self.selected.contains(&(id, branch.name.clone())))
+                    .label(&branch.name)
+                    .on_toggle_maybe(
+                        (self.running == 0)
+                            .then_some(move |value| Message::Toggled(id, name.clone(), value)),
+                    )
+                    .into(),
+            };
+
+            let mut r = row![container(check).width(220)]
+                .spacing(10)
+                .align_y(Alignment::Center);
+            for reason in &branch.reasons {
+                r = r.push(badge(reason.to_string(), container::secondary));
+            }
+            if let Some(commit) = &branch.last_commit {
+                let days = now
+                    .duration_since(commit.timestamp)
+                    .map_or(0, |x| x.as_secs() / 86400);
+                r = r.push(text(format!("{} 日前", days)).size(12).width(70));
+                r = r.push(text(&commit.author).size(12).width(120));
+                r = r.push(
+                    text(&commit.summary)
+                        .size(12)
+                        .wrapping(text::Wrapping::None),
+                );
+            }
+            let mut c = column![r].spacing(2);
+            if let Some(Err(err)) = result {
+                c = c.push(text(err).size(12).style(text::danger));
+            }
+            Element::from(c)
+        });
+
+        column![title, Column::with_children(rows).spacing(6)]
+            .spacing(6)
+            .into()
+    }
+}
+
+/// git で -D でないと消せないか（jj のブックマークは取り込まれていなくても消せる）
+fn needs_force(report: &Report, branch: &StaleBranch) -> bool {
+    !report.target.kind.is_jj() && branch.needs_force()
+}
@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use iced::font::Weight;
+use iced::widget::{Column, button, column, container, row, space, text};
+use iced::{Element, Font, Length, Task};
+
+use crate::app::components::common::diff_view::{self, DiffView};
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::commit::{CommitDetail, Signature, commit_detail};
+use crate::app::vcs::diff::FileDiff;
+
+/// 履歴で選んだコミットの詳細と差分
+#[derive(Debug, Clone)]
+pub struct CommitDetailPane {
+    path: PathBuf,
+    kind: RepositoryKind,
+    commit_id: String,
+    detail: Option<Result<CommitDetail, String>>,
+    /// 差分を展開しているファイルの番号
+    expanded: HashSet<usize>,
+    diff_view: DiffView,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Loaded(Result<Box<CommitDetail>, String>),
+    FileToggled(usize),
+    Diff(diff_view::Message),
+    Close,
+}
+
+impl CommitDetailPane {
+    /// diff_view は直前に開いていたコミットの表示設定を引き継ぐために受け取る
+    pub fn new(
+        path: PathBuf,
+        kind: RepositoryKind,
+        commit_id: String,
+        diff_view: DiffView,
+    ) -> Self {
+        Self {
+            path,
+            kind,
+            commit_id,
+            detail: None,
+            expanded: HashSet::new(),
+            diff_view,
+        }
+    }
+
+    pub fn commit_id(&self) -> &str {
+        &self.commit_id
+    }
+
+    /// 表示設定と色分けの結果を次に開くコミットへ引き継ぐ
+    pub fn into_diff_view(self) -> DiffView {
+        self.diff_view
+    }
+
+    pub fn load(&self) -> Task<Message> {
+        let path = self.path.clone();
+        let kind = self.kind;
+        let commit_id = self.commit_id.clone();
+        let options = self.diff_view.options().clone();
+        Task::perform(
+            async move { commit_detail(&path, kind, &commit_id, &options).map(Box::new) },
+            Message::Loaded,
+        )
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let short_id = self.commit_id.chars().take(10).collect::<String>();
+        let header = row![
+            text(format!("コミット {}", short_id)).font(Font {
+                weight: Weight::Bold,
+                ..Default::default()
+            }),
+            space().width(Length::Fill),
+            button(text("閉じる"))
+                .on_press(Message::Close)
+                .style(button::secondary),
+        ]
+        .align_y(iced::Alignment::Center);
+
+        let body: Element<'_, Message> = match &self.detail {
+            None => text("読み込み中...").into(),
+            Some(Err(err)) => text(format!("コミットを取得できません: {}", err))
+                .style(text::danger)
+                .into(),
+            Some(Ok(detail)) => self.view_detail(detail),
+        };
+
+        column![
+            header,
+            self.diff_view.view_controls().map(Message::Diff),
+            body
+        ]
+        .spacing(10)
+        .padding(10)
+        .into()
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Loaded(result) => {
+                if let Ok(detail) = &result {
+                    self.diff_view.prepare(&detail.files);
+                }
+                self.detail = Some(result.map(|x| *x));
+            }
+            Message::FileToggled(index) => {
+                if !self.expanded.remove(&index) {
+                    self.expanded.insert(index);
+                }
+            }
+            Message::Diff(msg) => {
+                if self.diff_view.update(msg) {
+                    return self.load();
+                }
+            }
+            // 閉じるのは親（History）が処理する
+            Message::Close => (),
+        }
+        Task::none()
+    }
+
+    fn view_detail<'a>(&'a self, detail: &'a CommitDetail) -> Element<'a, Message> {
+        let parents = if detail.parents.is_empty() {
+            "なし（ルートコミット）".to_owned()
+        } else {
+            detail
+                .parents
+                .iter()
+                .map(|x| x.chars().take(10).collect::<String>())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let files = Column::with_children(
+            detail
+                .files
+                .iter()
+                .enumerate()
+                .map(|(index, file)| self.view_file(index, file)),
+        )
+        .spacing(4);
+
+        column![
+            text(detail.message.to_owned()).font(Font::MONOSPACE),
+            column![
+                text(format!("Author:    {}", signature(&detail.author))).size(12),
+                text(format!("Committer: {}", signature(&detail.committer))).size(12),
+                text(format!("Parents:   {}", parents)).size(12),
+            ]
+            .spacing(2),
+            text(format!("{} 個のファイルを変更", detail.files.len())).size(12),
+            files,
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn view_file<'a>(&'a self, index: usize, file: &'a FileDiff) -> Element<'a, Message> {
+        let expanded = self.expanded.contains(&index);
+        let toggle = button(
+            row![
+                text(if expanded { "▼" } else { "▶" }).size(12),
+                text(file.display_path()).wrapping(text::Wrapping::None),
+                space().width(Length::Fill),
+                text(format!("+{}", file.additions())).style(text::success),
+                text(format!("-{}", file.deletions())).style(text::danger),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+        )
+        .width(Length::Fill)
+        .on_press(Message::FileToggled(index))
+        .style(button::text);
+
+        let mut c = column![toggle];
+        if expanded {
+            c = c.push(
+                container(self.diff_view.view_file(file))
+                    .width(Length::Fill)
+                    .clip(true),
+            );
+        }
+        c.into()
+    }
+}
+
+fn signature(signature: &Signature) -> String {
+    format!(
+        "{} <{}>  {}",
+        signature.name,
+        signature.email,
+        format_time(signature.time)
+    )
+}
+
+fn format_time(time: SystemTime) -> String {
+    let datetime: DateTime<Utc> = time.into();
+    datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+}
@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+
+use iced::font::Weight;
+use iced::widget::{button, checkbox, column, row, space, text, text_editor, text_input};
+use iced::{Element, Font, Length, Task, Theme};
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::commit::{CommitOptions, create_commit, last_commit_message};
+use crate::app::vcs::stage::WorkingTreeChanges;
+
+/// 件名の推奨文字数
+const SUBJECT_SOFT_LIMIT: usize = 50;
+/// これを超える件名は多くのツールで切り詰められる
+const SUBJECT_HARD_LIMIT: usize = 72;
+
+/// コミットメッセージを書いてコミットする。
+/// text_editor::Content は Clone できないので、カードではなく CardDetail が持つ。
+#[derive(Debug)]
+pub struct CommitEditor {
+    path: PathBuf,
+    kind: RepositoryKind,
+    message: text_editor::Content,
+    amend: bool,
+    sign_off: bool,
+    /// 空なら設定どおりの作者
+    author: String,
+    running: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Edited(text_editor::Action),
+    AmendToggled(bool),
+    LastMessageLoaded(Result<String, String>),
+    SignOffToggled(bool),
+    AuthorChanged(String),
+    Commit,
+    /// 成功したら親がカードと履歴を読み込み直す
+    Finished(Result<(), String>),
+}
+
+impl CommitEditor {
+    pub fn new(path: PathBuf, kind: RepositoryKind) -> Self {
+        Self {
+            path,
+            kind,
+            message: text_editor::Content::new(),
+            amend: false,
+            sign_off: false,
+            author: String::new(),
+            running: false,
+            error: None,
+        }
+    }
+
+    /// changes はコミットするものがあるかの判定に使う（未読み込みなら None）
+    pub fn view<'a>(&'a self, changes: Option<&WorkingTreeChanges>) -> Element<'a, Message> {
+        let message = self.message.text();
+        let subject = message.lines().next().unwrap_or_default();
+        let subject_length = subject.chars().count();
+
+        let mut c = column![
+            text("コミット").font(Font {
+                weight: Weight::Bold,
+                ..Default::default()
+            }),
+            text_editor(&self.message)
+                .placeholder("件名\n\n本文")
+                .font(Font::MONOSPACE)
+                .height(160)
+                .on_action(Message::Edited),
+            text(format!(
+                "件名 {}/{} 文字",
+                subject_length, SUBJECT_SOFT_LIMIT
+            ))
+            .size(12)
+            .style(move |theme: &Theme| {
+                let palette = theme.palette();
+                text::Style {
+                    color: match subject_length {
+                        x if x > SUBJECT_HARD_LIMIT => Some(palette.danger),
+                        x if x > SUBJECT_SOFT_LIMIT => Some(palette.warning),
+                        _ => None,
+                    },
+                }
+            }),
+        ]
+        .spacing(8);
+
+        // 件名と本文の間は空行にする
+        if message.lines().nth(1).is_some_and(|x| !x.trim().is_empty()) {
+            c = c.push(
+                text("2 行目は空行にしてください")
+                    .size(12)
+                    .style(text::warning),
+            );
+        }
+
+        // jj でもコミットするのはステージ用の change なので、同じ差分を見る。
+        // ステージ用の change が無ければ staged は空になる。
+        let nothing_to_commit = !self.amend && changes.is_some_and(|x| x.staged.is_empty());
+        if nothing_to_commit {
+            c = c.push(
+                text(if self.kind.is_jj() {
+                    "ステージ用の change (@-) に変更がありません。先に @ から変更を移してください"
+                } else {
+                    "ステージされた変更がありません"
+                })
+                .size(12)
+                .style(text::warning),
+            );
+        }
+
+        let amend_label = if self.kind.is_jj() {
+            "ステージした変更を直前のコミットに移して修正"
+        } else {
+            "直前のコミットを修正 (amend)"
+        };
+        c = c.push(
+            row![
+                checkbox(self.amend)
+                    .label(amend_label)
+                    .on_toggle(Message::AmendToggled),
+                checkbox(self.sign_off)
+                    .label("Signed-off-by を付ける")
+                    .on_toggle(Message::SignOffToggled),
+            ]
+            .spacing(20),
+        );
+        c = c.push(
+            text_input("作者を変更する場合は Name <email>", &self.author)
+                .on_input(Message::AuthorChanged)
+                .size(13),
+        );
+
+        let author_valid = self.author.trim().is_empty() || is_author(self.author.trim());
+        if !author_valid {
+            c = c.push(
+                text("作者は Name <email> の形式で入力してください")
+                    .size(12)
+                    .style(text::danger),
+            );
+        }
+        if let Some(error) = &self.error {
+            c = c.push(text(error).size(12).style(text::danger));
+        }
+
+        let can_commit =
+            !self.running && !message.trim().is_empty() && !nothing_to_commit && author_valid;
+        let label = match (self.kind.is_jj(), self.amend) {
+            (_, true) => "修正",
+            (true, false) => "コミット (ステージ用の change に説明を付ける)",
+            (false, false) => "コミット",
+        };
+        c = c.push(row![
+            space().width(Length::Fill),
+            button(text(label))
+                .on_press_maybe(can_commit.then_some(Message::Commit))
+                .style(button::primary),
+        ]);
+
+        c.into()
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Edited(action) => self.message.perform(action),
+            Message::AmendToggled(value) => {
+                self.amend = value;
+                // 書きかけでなければ、修正するコミットのメッセージを入れておく
+                if value && self.message.text().trim().is_empty() {
+                    let path = self.path.clone();
+                    let kind = self.kind;
+                    return Task::perform(
+                        async move { last_commit_message(&path, kind) },
+                        Message::LastMessageLoaded,
+                    );
+                }
+            }
+            Message::LastMessageLoaded(result) => match result {
+                Ok(message) => {
+                    if self.amend && self.message.text().trim().is_empty() {
+                        self.message = text_editor::Content::with_text(&message);
+                    }
+                }
+                Err(err) => self.error = Some(err),
+            },
+            Message::SignOffToggled(value) => self.sign_off = value,
+            Message::AuthorChanged(value) => self.author = value,
+            Message::Commit => {
+                self.running = true;
+                self.error = None;
+
+                let path = self.path.clone();
+                let kind = self.kind;
+                let message = self.message.text();
+                let author = self.author.trim();
+                let options = CommitOptions {
+                    amend: self.amend,
+                    sign_off: self.sign_off,
+                    author: (!author.is_empty()).then(|| author.to_owned()),
+                };
+                return Task::perform(
+                    async move { create_commit(&path, kind, &message, &options) },
+                    Message::Finished,
+                );
+            }
+            Message::Finished(result) => {
+                self.running = false;
+                match result {
+                    Ok(()) => {
+                        self.message = text_editor::Content::new();
+                        self.amend = false;
+                    }
+                    Err(err) => self.error = Some(err),
+                }
+            }
+        }
+
+        Task::none()
+    }
+}
+
+/// `Name <email>` の形か
+fn is_author(value: &str) -> bool {
+    match value.split_once('<') {
+        Some((name, rest)) => !name.trim().is_empty() && rest.ends_with('>') && rest.len() > 1,
+        None => false,
+    }
+}
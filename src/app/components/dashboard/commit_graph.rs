@@ -0,0 +1,233 @@
+use iced::widget::canvas::{self, Frame, Geometry, Path, Stroke};
+use iced::{Color, Element, Length, Point, Rectangle, Renderer, Theme, alignment, mouse};
+
+use crate::app::vcs::graph::{GraphCommit, RefLabel};
+
+const ROW_HEIGHT: f32 = 24.0;
+const LANE_WIDTH: f32 = 14.0;
+const NODE_RADIUS: f32 = 4.0;
+const TEXT_SIZE: f32 = 13.0;
+
+/// レーンごとの色（レーン番号で循環させる）
+const LANE_COLORS: [Color; 6] = [
+    Color::from_rgb8(0x4e, 0x9a, 0xf1),
+    Color::from_rgb8(0x3f, 0xb9, 0x50),
+    Color::from_rgb8(0xf0, 0x88, 0x3e),
+    Color::from_rgb8(0xbc, 0x8c, 0xff),
+    Color::from_rgb8(0xf7, 0x5f, 0x8f),
+    Color::from_rgb8(0x2d, 0xc6, 0xc6),
+];
+
+/// 一行分のレーン配置
+#[derive(Debug, Clone)]
+struct GraphRow {
+    /// コミットを置くレーン
+    lane: usize,
+    /// 行の上端から中央までの線（開始レーン, 終了レーン）
+    top: Vec<(usize, usize)>,
+    /// 行の中央から下端までの線（開始レーン, 終了レーン）
+    bottom: Vec<(usize, usize)>,
+}
+
+/// レーン式のコミットグラフ
+#[derive(Debug, Clone)]
+pub struct CommitGraph {
+    commits: Vec<GraphCommit>,
+    rows: Vec<GraphRow>,
+    lane_count: usize,
+}
+
+impl CommitGraph {
+    /// commits は子が親より先に来る順で渡す
+    pub fn new(commits: Vec<GraphCommit>) -> Self {
+        let mut rows = Vec::with_capacity(commits.len());
+        let mut lane_count = 0;
+        // 各レーンが次に待っているコミット
+        let mut lanes: Vec<Option<String>> = vec![];
+
+        for commit in &commits {
+            let waiting = lanes
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| x.as_deref() == Some(commit.id.as_str()))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            let lane = match waiting.first() {
+                Some(lane) => *lane,
+                None => free_lane(&mut lanes),
+            };
+
+            let mut top = vec![];
+            let mut passing = vec![];
+            for (i, x) in lanes.iter().enumerate() {
+                match x {
+                    Some(id) if *id == commit.id => top.push((i, lane)),
+                    Some(_) => {
+                        top.push((i, i));
+                        passing.push(i);
+                    }
+                    None => (),
+                }
+            }
+
+            // 合流したレーンは空ける
+            for i in &waiting {
+                lanes[*i] = None;
+            }
+
+            // 最初の親は同じレーンを引き継ぎ、それ以外は既存のレーンか新しいレーンへ分岐する
+            let mut targets = vec![];
+            for (n, parent) in commit.parents.iter().enumerate() {
+                let target = match lanes.iter().position(|x| x.as_ref() == Some(parent)) {
+                    Some(i) => i,
+                    None if n == 0 && lanes[lane].is_none() => lane,
+                    None => free_lane(&mut lanes),
+                };
+                lanes[target] = Some(parent.clone());
+                targets.push(target);
+            }
+
+            let mut bottom = passing.iter().map(|i| (*i, *i)).collect::<Vec<_>>();
+            bottom.extend(targets.iter().map(|i| (lane, *i)));
+
+            while lanes.last().is_some_and(|x| x.is_none()) {
+                lanes.pop();
+            }
+            lane_count = lane_count.max(lanes.len()).max(lane + 1);
+
+            rows.push(GraphRow { lane, top, bottom });
+        }
+
+        Self {
+            commits,
+            rows,
+            lane_count,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commits.is_empty()
+    }
+
+    pub fn view<'a, Message: 'a>(&'a self) -> Element<'a, Message> {
+        canvas::Canvas::new(self)
+            .width(Length::Fill)
+            .height(Length::Fixed(self.rows.len() as f32 * ROW_HEIGHT))
+            .into()
+    }
+}
+
+impl<Message> canvas::Program<Message> for CommitGraph {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+        let text_x = self.lane_count as f32 * LANE_WIDTH + 8.0;
+
+        for (index, (row, commit)) in self.rows.iter().zip(&self.commits).enumerate() {
+            let y_top = index as f32 * ROW_HEIGHT;
+            let y_mid = y_top + ROW_HEIGHT / 2.0;
+            let y_bottom = y_top + ROW_HEIGHT;
+
+            for (from, to) in &row.top {
+                let path = edge(
+                    Point::new(lane_x(*from), y_top),
+                    Point::new(lane_x(*to), y_mid),
+                );
+                frame.stroke(&path, lane_stroke(*from));
+            }
+            for (from, to) in &row.bottom {
+                let path = edge(
+                    Point::new(lane_x(*from), y_mid),
+                    Point::new(lane_x(*to), y_bottom),
+                );
+                frame.stroke(&path, lane_stroke(*to));
+            }
+
+            let center = Point::new(lane_x(row.lane), y_mid);
+            let is_head = commit.refs.contains(&RefLabel::Head);
+            if is_head {
+                frame.fill(&Path::circle(center, NODE_RADIUS + 2.0), palette.text);
+            }
+            frame.fill(&Path::circle(center, NODE_RADIUS), lane_color(row.lane));
+
+            // ラベルとサマリを横に並べる
+            let mut x = text_x;
+            for label in &commit.refs {
+                let (content, color) = match label {
+                    RefLabel::Head => ("HEAD".to_owned(), palette.danger),
+                    RefLabel::Branch(name) => (name.to_owned(), palette.success),
+                    RefLabel::RemoteBranch(name) => (name.to_owned(), palette.primary),
+                    RefLabel::Tag(name) => (format!("🏷 {}", name), palette.warning),
+                };
+                let content = format!("[{}]", content);
+                // canvas では文字幅を測れないので概算で送る
+                let width = content.chars().count() as f32 * TEXT_SIZE * 0.6;
+                frame.fill_text(canvas::Text {
+                    content,
+                    position: Point::new(x, y_mid),
+                    color,
+                    size: TEXT_SIZE.into(),
+                    align_y: alignment::Vertical::Center,
+                    ..Default::default()
+                });
+                x += width + 6.0;
+            }
+            frame.fill_text(canvas::Text {
+                content: commit.summary.to_owned(),
+                position: Point::new(x, y_mid),
+                color: palette.text,
+                size: TEXT_SIZE.into(),
+                align_y: alignment::Vertical::Center,
+                ..Default::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+/// 空いているレーンを探し、無ければ右端に追加する
+fn free_lane(lanes: &mut Vec<Option<String>>) -> usize {
+    match lanes.iter().position(|x| x.is_none()) {
+        Some(i) => i,
+        None => {
+            lanes.push(None);
+            lanes.len() - 1
+        }
+    }
+}
+
+fn lane_x(lane: usize) -> f32 {
+    lane as f32 * LANE_WIDTH + LANE_WIDTH / 2.0
+}
+
+fn lane_color(lane: usize) -> Color {
+    LANE_COLORS[lane % LANE_COLORS.len()]
+}
+
+fn lane_stroke<'a>(lane: usize) -> Stroke<'a> {
+    Stroke::default()
+        .with_color(lane_color(lane))
+        .with_width(2.0)
+}
+
+/// レーンをまたぐ線は曲線にする
+fn edge(from: Point, to: Point) -> Path {
+    if from.x == to.x {
+        return Path::line(from, to);
+    }
+    let middle = (from.y + to.y) / 2.0;
+    Path::new(|builder| {
+        builder.move_to(from);
+        builder.bezier_curve_to(Point::new(from.x, middle), Point::new(to.x, middle), to);
+    })
+}
@@ -0,0 +1,150 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::app::components::dashboard::card::Card;
+use crate::app::vcs::upstream::UpstreamStatus;
+use crate::app::workspace::{GridOptions, GroupBy, SortKey};
+
+/// タグの無いカードのグループ名
+const UNTAGGED: &str = "(タグなし)";
+
+/// 見出しとそこに並べるカード。グループ分けしないときは見出しが None。
+pub struct Group<'a> {
+    pub title: Option<String>,
+    pub cards: Vec<&'a Card>,
+}
+
+/// 設定に従ってカードを絞り込み、並べ替えてグループに分ける
+pub fn arrange<'a>(
+    cards: &'a [Card],
+    options: &GridOptions,
+    tags: &BTreeMap<PathBuf, String>,
+) -> Vec<Group<'a>> {
+    let mut visible = cards
+        .iter()
+        .filter(|x| is_visible(x, options))
+        .collect::<Vec<_>>();
+    visible.sort_by(|a, b| {
+        let order = compare(a, b, options.sort).then_with(|| name(a).cmp(&name(b)));
+        if options.reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+
+    let title = |card: &Card| match options.group_by {
+        GroupBy::None => None,
+        GroupBy::ParentDirectory => Some(
+            card.path
+                .parent()
+                .map(|x| x.display().to_string())
+                .unwrap_or_default(),
+        ),
+        GroupBy::Tag => Some(
+            tags.get(&card.path)
+                .filter(|x| !x.trim().is_empty())
+                .map_or(UNTAGGED.to_owned(), |x| x.trim().to_owned()),
+        ),
+    };
+
+    // グループは名前順。並べ替えた順のまま振り分けるので、グループ内の順序は保たれる
+    let mut groups = BTreeMap::<Option<String>, Vec<&Card>>::new();
+    for card in visible {
+        groups.entry(title(card)).or_default().push(card);
+    }
+    groups
+        .into_iter()
+        .map(|(title, cards)| Group { title, cards })
+        .collect()
+}
+
+fn name(card: &Card) -> String {
+    card.path
+        .file_name()
+        .unwrap_or(card.path.as_os_str())
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+fn is_visible(card: &Card, options: &GridOptions) -> bool {
+    if options.only_dirty && !card.working_tree_status.is_some_and(|x| x.is_dirty()) {
+        return false;
+    }
+    if options.only_unpushed
+        && !matches!(
+            card.upstream_status,
+            Some(UpstreamStatus::NoUpstream { .. })
+                | Some(UpstreamStatus::Tracking { ahead: 1.., .. })
+        )
+    {
+        return false;
+    }
+    if options.only_detached && card.upstream_status != Some(UpstreamStatus::Detached) {
+        return false;
+    }
+
+    let filter = options.filter.trim().to_lowercase();
+    if filter.is_empty() {
+        return true;
+    }
+    let branch = card
+        .status_digest
+        .as_ref()
+        .map(|x| x.current_branch.to_lowercase())
+        .unwrap_or_default();
+    card.path.to_string_lossy().to_lowercase().contains(&filter) || branch.contains(&filter)
+}
+
+fn compare(a: &Card, b: &Card, key: SortKey) -> Ordering {
+    match key {
+        SortKey::Name => Ordering::Equal,
+        // コミットの無いものは最後
+        SortKey::LastCommit => {
+            let time =
+                |x: &Card| Reverse(x.status_digest.as_ref().map(|x| x.last_commit_timestamp));
+            time(a).cmp(&time(b))
+        }
+        SortKey::Dirty => {
+            let count = |x: &Card| {
+                Reverse(x.working_tree_status.map_or(0, |x| {
+                    x.staged + x.modified + x.untracked + x.deleted + x.renamed + x.conflicted
+                }))
+            };
+            count(a).cmp(&count(b))
+        }
+        // upstream の無いものは最後
+        SortKey::AheadBehind => {
+            let count = |x: &Card| match &x.upstream_status {
+                Some(UpstreamStatus::Tracking { ahead, behind, .. }) => {
+                    Reverse(Some(ahead + behind))
+                }
+                _ => Reverse(None),
+            };
+            count(a).cmp(&count(b))
+        }
+        SortKey::Kind => a.kind.to_string().cmp(&b.kind.to_string()),
+        // ブランチ・作者の無いものは最後
+        SortKey::Branch => {
+            let branch = |x: &Card| {
+                x.status_digest
+                    .as_ref()
+                    .map(|x| x.current_branch.to_lowercase())
+            };
+            compare_present(branch(a), branch(b))
+        }
+        SortKey::Author => {
+            let author = |x: &Card| x.last_commit_author.as_ref().map(|x| x.to_lowercase());
+            compare_present(author(a), author(b))
+        }
+    }
+}
+
+/// None を後ろにして比べる
+fn compare_present<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    }
+}
@@ -0,0 +1,249 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use iced::widget::{Column, column, container, mouse_area, scrollable, space, text};
+use iced::{Element, Length, Task, Theme};
+
+use crate::app::components::dashboard::commit_detail::{self, CommitDetailPane};
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::history::{HistoryCursor, HistoryEntry, HistoryPage, history_page};
+
+/// 一度に読み込むコミット数
+const PAGE_SIZE: usize = 200;
+/// 一行の高さ（仮想化のため固定）
+const ROW_HEIGHT: f32 = 56.0;
+/// 表示範囲の前後に余分に作る行数
+const OVERSCAN: usize = 10;
+/// 最初のスクロールイベントが来るまでに仮定する表示領域の高さ
+const DEFAULT_VIEWPORT_HEIGHT: f32 = 800.0;
+
+/// ページ単位で読み込み、見えている行だけを描画する履歴一覧
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+    kind: RepositoryKind,
+    entries: Vec<HistoryEntry>,
+    loading: bool,
+    /// 次のページを読む位置
+    cursor: Option<HistoryCursor>,
+    /// 最後まで読み込んだか
+    exhausted: bool,
+    error: Option<String>,
+    scroll_offset: f32,
+    viewport_height: f32,
+    /// 選択中のコミットの詳細
+    selected: Option<CommitDetailPane>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Scrolled { offset: f32, height: f32 },
+    PageLoaded(Result<HistoryPage, String>),
+    CommitSelected(usize),
+    Detail(commit_detail::Message),
+}
+
+impl History {
+    pub fn new(path: PathBuf, kind: RepositoryKind) -> Self {
+        Self {
+            path,
+            kind,
+            entries: vec![],
+            loading: false,
+            cursor: None,
+            exhausted: false,
+            error: None,
+            scroll_offset: 0.0,
+            viewport_height: DEFAULT_VIEWPORT_HEIGHT,
+            selected: None,
+        }
+    }
+
+    /// まだ一件も読み込んでいなければ最初のページを読み込む
+    pub fn load_first_page(&mut self) -> Task<Message> {
+        if self.entries.is_empty() && !self.exhausted {
+            self.load_more()
+        } else {
+            Task::none()
+        }
+    }
+
+    /// 詳細を開いているコミット
+    pub fn selected_commit(&self) -> Option<&str> {
+        self.selected.as_ref().map(|x| x.commit_id())
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        if let Some(error) = &self.error
+            && self.entries.is_empty()
+        {
+            return text(format!("コミットを取得できません: {}", error))
+                .style(text::danger)
+                .into();
+        }
+        if self.entries.is_empty() {
+            return text(if self.loading {
+                "読み込み中..."
+            } else {
+                "コミットがありません"
+            })
+            .into();
+        }
+
+        let (first, last) = self.visible_range();
+        let selected_id = self.selected.as_ref().map(|x| x.commit_id());
+        let rows = self.entries[first..last]
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let selected = selected_id == Some(entry.commit_id.as_str());
+                view_entry(first + i, entry, selected)
+            });
+
+        let mut list = Column::new()
+            .push(space().height(first as f32 * ROW_HEIGHT))
+            .extend(rows)
+            .push(space().height((self.entries.len() - last) as f32 * ROW_HEIGHT));
+        if self.loading {
+            list = list.push(text("読み込み中...").height(ROW_HEIGHT));
+        }
+
+        let list = scrollable(list.width(Length::Fill))
+            .width(Length::Fill)
+            .on_scroll(|viewport| Message::Scrolled {
+                offset: viewport.absolute_offset().y,
+                height: viewport.bounds().height,
+            });
+
+        // 一覧を先頭に置いたままにして、詳細の開閉でスクロール位置が失われないようにする
+        match &self.selected {
+            None => column![list.height(Length::Fill)].into(),
+            Some(pane) => column![
+                list.height(Length::FillPortion(2)),
+                scrollable(pane.view().map(Message::Detail))
+                    .width(Length::Fill)
+                    .height(Length::FillPortion(3)),
+            ]
+            .spacing(10)
+            .into(),
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Scrolled { offset, height } => {
+                self.scroll_offset = offset;
+                self.viewport_height = height;
+            }
+            Message::PageLoaded(result) => {
+                self.loading = false;
+                match result {
+                    Ok(page) => {
+                        self.exhausted = page.next.is_none();
+                        self.cursor = page.next;
+                        self.entries.extend(page.entries);
+                        self.error = None;
+                    }
+                    Err(err) => {
+                        // 続きが読めないので、ここで打ち切る
+                        self.exhausted = true;
+                        self.error = Some(err);
+                    }
+                }
+            }
+            Message::CommitSelected(index) => {
+                let Some(entry) = self.entries.get(index) else {
+                    return Task::none();
+                };
+                let diff_view = self
+                    .selected
+                    .take()
+                    .map(|x| x.into_diff_view())
+                    .unwrap_or_default();
+                let pane = CommitDetailPane::new(
+                    self.path.clone(),
+                    self.kind,
+                    entry.commit_id.clone(),
+                    diff_view,
+                );
+                let task = pane.load();
+                self.selected = Some(pane);
+                return task.map(Message::Detail);
+            }
+            Message::Detail(commit_detail::Message::Close) => {
+                self.selected = None;
+            }
+            Message::Detail(msg) => {
+                // 読み込み中に別のコミットを選んだ場合の古い結果は捨てる
+                if let commit_detail::Message::Loaded(Ok(detail)) = &msg
+                    && self.selected.as_ref().map(|x| x.commit_id()) != Some(detail.id.as_str())
+                {
+                    return Task::none();
+                }
+                if let Some(pane) = &mut self.selected {
+                    return pane.update(msg).map(Message::Detail);
+                }
+                return Task::none();
+            }
+        }
+
+        // 表示範囲が読み込み済みの末尾に近づいたら続きを読む
+        if self.visible_range().1 + OVERSCAN >= self.entries.len() {
+            return self.load_more();
+        }
+        Task::none()
+    }
+
+    fn load_more(&mut self) -> Task<Message> {
+        if self.loading || self.exhausted {
+            return Task::none();
+        }
+        self.loading = true;
+
+        let path = self.path.clone();
+        let kind = self.kind;
+        let cursor = self.cursor.clone();
+        Task::perform(
+            async move { history_page(&path, kind, cursor.as_ref(), PAGE_SIZE) },
+            Message::PageLoaded,
+        )
+    }
+
+    /// 描画する行の範囲 [first, last)
+    fn visible_range(&self) -> (usize, usize) {
+        let first = ((self.scroll_offset / ROW_HEIGHT) as usize).saturating_sub(OVERSCAN);
+        let count = (self.viewport_height / ROW_HEIGHT).ceil() as usize + OVERSCAN * 2;
+        let first = first.min(self.entries.len());
+        let last = (first + count).min(self.entries.len());
+        (first, last)
+    }
+}
+
+fn view_entry(index: usize, entry: &HistoryEntry, selected: bool) -> Element<'_, Message> {
+    let datetime: DateTime<Utc> = entry.timestamp.into();
+    let datetime_str = datetime.format("%Y-%m-%d %H:%M:%S").to_string();
+    let short_id = entry.commit_id.chars().take(10).collect::<String>();
+
+    // 行の高さを揃えるため、折り返さずに一行ずつ表示する
+    let row = container(column![
+        text(entry.summary.to_owned()).wrapping(text::Wrapping::None),
+        text(format!("{}  {}  {}", short_id, entry.author, datetime_str))
+            .size(12)
+            .wrapping(text::Wrapping::None),
+    ])
+    .padding([6, 10])
+    .width(Length::Fill)
+    .height(ROW_HEIGHT)
+    .clip(true)
+    .style(move |theme: &Theme| {
+        if selected {
+            container::primary(theme)
+        } else {
+            container::Style::default()
+        }
+    });
+
+    mouse_area(row)
+        .on_press(Message::CommitSelected(index))
+        .into()
+}
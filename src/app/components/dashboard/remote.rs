@@ -0,0 +1,265 @@
+use std::path::PathBuf;
+use std::thread;
+
+use iced::futures::{Stream, channel::mpsc};
+use iced::widget::{Column, button, column, progress_bar, row, text, text_input};
+use iced::{Element, Task};
+
+use crate::app::components::common::dialog::Dialog;
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::remote::{
+    Credentials, RemoteEvent, RemoteOperation, RemoteOutcome, RemoteResult, run_remote_operation,
+};
+
+/// カードの fetch / pull / push とその結果
+#[derive(Debug, Clone)]
+pub struct Remote {
+    path: PathBuf,
+    kind: RepositoryKind,
+    /// 実行中の操作
+    running: Option<RemoteOperation>,
+    /// 直前の操作（認証情報を入れたら同じ操作をやり直す）
+    last_operation: Option<RemoteOperation>,
+    /// 実行中のリモートと、最後に届いた途中経過
+    current: Option<String>,
+    progress: Option<(String, Option<u8>)>,
+    results: Vec<RemoteResult>,
+    /// 一度入力した認証情報は、このカードの以降の操作でも使う
+    credentials: Option<Credentials>,
+    /// 入力中の認証情報。Some の間はダイアログを出す。
+    prompt: Option<Credentials>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Run(RemoteOperation),
+    Event(RemoteEvent),
+    /// すべてのリモートが終わった。親はここでカードの状態を取り直す。
+    Done,
+    UsernameChanged(String),
+    PasswordChanged(String),
+    CredentialsSubmitted,
+    CredentialsCancelled,
+    Dismiss,
+}
+
+impl Remote {
+    pub fn new(path: PathBuf, kind: RepositoryKind) -> Self {
+        Self {
+            path,
+            kind,
+            running: None,
+            last_operation: None,
+            current: None,
+            progress: None,
+            results: vec![],
+            credentials: None,
+            prompt: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let action = |operation: RemoteOperation| {
+            button(text(operation.to_string()).size(12))
+                .padding([2, 8])
+                .on_press_maybe((!self.is_running()).then_some(Message::Run(operation)))
+                .style(button::secondary)
+        };
+        // bare には作業ツリーが無く、jj には pull が無い
+        let mut actions = row![action(RemoteOperation::Fetch)].spacing(4);
+        if self.kind != RepositoryKind::Bare {
+            if !self.kind.is_jj() {
+                actions = actions.push(action(RemoteOperation::Pull));
+            }
+            actions = actions.push(action(RemoteOperation::Push));
+        }
+
+        let mut c = column![actions].spacing(4);
+        if let Some(operation) = self.running {
+            let remote = self.current.as_deref().unwrap_or("...");
+            c = c.push(text(format!("{} {}", operation, remote)).size(12));
+            if let Some((line, percent)) = &self.progress {
+                if let Some(percent) = percent {
+                    c = c.push(progress_bar(0.0..=100.0, *percent as f32).girth(4));
+                }
+                c = c.push(text(line).size(10));
+            }
+        }
+
+        if !self.results.is_empty() {
+            c = c.push(view_results(&self.results));
+            if !self.is_running() {
+                c = c.push(
+                    button(text("閉じる").size(12))
+                        .padding([2, 8])
+                        .on_press(Message::Dismiss)
+                        .style(button::text),
+                );
+            }
+        }
+
+        c.into()
+    }
+
+    /// 認証情報の入力ダイアログ。入力待ちでなければ None。
+    pub fn view_dialog(&self) -> Option<Element<'_, Message>> {
+        let prompt = self.prompt.as_ref()?;
+        let content = column![
+            text(format!("{} の認証が必要です", self.path.display())),
+            text_input("ユーザー名", &prompt.username).on_input(Message::UsernameChanged),
+            text_input("パスワード / アクセストークン", &prompt.password)
+                .on_input(Message::PasswordChanged)
+                .on_submit(Message::CredentialsSubmitted)
+                .secure(true),
+        ]
+        .spacing(10);
+
+        Some(
+            Dialog::new("認証", content)
+                .action("やり直す", Message::CredentialsSubmitted)
+                .secondary_action("中止", Message::CredentialsCancelled)
+                .view(),
+        )
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Run(operation) => return self.run(operation),
+            Message::Event(event) => match event {
+                RemoteEvent::Started(remote) => {
+                    self.current = Some(remote);
+                    self.progress = None;
+                }
+                RemoteEvent::Progress { line, percent } => self.progress = Some((line, percent)),
+                RemoteEvent::Finished(result) => self.results.push(result),
+            },
+            Message::Done => {
+                self.running = None;
+                self.current = None;
+                self.progress = None;
+                let auth_required = self
+                    .results
+                    .iter()
+                    .any(|x| matches!(x.outcome, RemoteOutcome::AuthRequired(_)));
+                if auth_required {
+                    self.prompt = Some(Credentials {
+                        password: String::new(),
+                        ..self.credentials.clone().unwrap_or_default()
+                    });
+                }
+            }
+            Message::UsernameChanged(value) => {
+                if let Some(prompt) = self.prompt.as_mut() {
+                    prompt.username = value;
+                }
+            }
+            Message::PasswordChanged(value) => {
+                if let Some(prompt) = self.prompt.as_mut() {
+                    prompt.password = value;
+                }
+            }
+            Message::CredentialsSubmitted => {
+                if let Some(prompt) = self.prompt.take() {
+                    self.credentials = Some(prompt);
+                    if let Some(operation) = self.last_operation {
+                        return self.run(operation);
+                    }
+                }
+            }
+            Message::CredentialsCancelled => self.prompt = None,
+            Message::Dismiss => self.results.clear(),
+        }
+
+        Task::none()
+    }
+
+    fn run(&mut self, operation: RemoteOperation) -> Task<Message> {
+        if self.is_running() {
+            return Task::none();
+        }
+        self.running = Some(operation);
+        self.last_operation = Some(operation);
+        self.results.clear();
+
+        let stream = remote_stream(
+            self.path.clone(),
+            self.kind,
+            operation,
+            self.credentials.clone(),
+        );
+        Task::run(stream, Message::Event).chain(Task::done(Message::Done))
+    }
+}
+
+/// リモートごとの結果。拒否された ref は一つずつ並べる。
+fn view_results(results: &[RemoteResult]) -> Element<'_, Message> {
+    let mut c = Column::new().spacing(2);
+    for result in results {
+        match &result.outcome {
+            RemoteOutcome::Succeeded(updates) if updates.is_empty() => {
+                c = c.push(
+                    text(format!("✓ {}: 変更なし", result.remote))
+                        .size(12)
+                        .style(text::success),
+                );
+            }
+            RemoteOutcome::Succeeded(updates) => {
+                c = c.push(
+                    text(format!("✓ {}", result.remote))
+                        .size(12)
+                        .style(text::success),
+                );
+                for update in updates {
+                    c = c.push(text(update).size(10));
+                }
+            }
+            RemoteOutcome::Rejected(refs) => {
+                c = c.push(
+                    text(format!("✗ {}: 拒否されました", result.remote))
+                        .size(12)
+                        .style(text::danger),
+                );
+                for x in refs {
+                    c = c.push(text(x).size(10).style(text::danger));
+                }
+            }
+            RemoteOutcome::AuthRequired(_) => {
+                c = c.push(
+                    text(format!("✗ {}: 認証が必要です", result.remote))
+                        .size(12)
+                        .style(text::warning),
+                );
+            }
+            RemoteOutcome::Failed(err) => {
+                c = c.push(
+                    text(format!("✗ {}", result.remote))
+                        .size(12)
+                        .style(text::danger),
+                );
+                c = c.push(text(err).size(10).style(text::danger));
+            }
+        }
+    }
+    c.into()
+}
+
+/// 別スレッドで実行し、途中経過を流す
+fn remote_stream(
+    path: PathBuf,
+    kind: RepositoryKind,
+    operation: RemoteOperation,
+    credentials: Option<Credentials>,
+) -> impl Stream<Item = RemoteEvent> {
+    let (sender, receiver) = mpsc::unbounded();
+    thread::spawn(move || {
+        run_remote_operation(&path, kind, operation, credentials.as_ref(), |event| {
+            // 受信側が drop されていても最後まで実行する
+            let _ = sender.unbounded_send(event);
+        });
+    });
+    receiver
+}
@@ -0,0 +1,370 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use iced::font::Weight;
+use iced::widget::{
+    Column, button, checkbox, column, container, mouse_area, row, scrollable, space, text,
+    text_input,
+};
+use iced::{Alignment, Element, Font, Length, Task};
+
+use crate::app::components::common::dialog::Dialog;
+use crate::app::components::common::diff_view::{self, DiffView};
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::diff::FileDiff;
+use crate::app::vcs::stash::{
+    StashInfo, apply_stash, create_stash, drop_stash, list_stashes, pop_stash, stash_diff,
+};
+
+/// stash の一覧と差分のプレビュー、適用・削除・作成
+#[derive(Debug, Clone)]
+pub struct Stashes {
+    path: PathBuf,
+    kind: RepositoryKind,
+    /// 読み込み中は None
+    stashes: Option<Result<Vec<StashInfo>, String>>,
+    /// 差分を表示している stash の名前
+    selected: Option<String>,
+    /// 読み込み中は None
+    diff: Option<Result<Vec<FileDiff>, String>>,
+    expanded: HashSet<usize>,
+    diff_view: DiffView,
+    message_input: String,
+    include_untracked: bool,
+    keep_index: bool,
+    /// 削除の確認を待っている stash
+    pending_drop: Option<StashInfo>,
+    running: bool,
+    /// 直前の操作が失敗したときのメッセージ
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Refresh,
+    Loaded(Result<Vec<StashInfo>, String>),
+    Selected(String),
+    DiffLoaded(String, Result<Vec<FileDiff>, String>),
+    FileToggled(usize),
+    Diff(diff_view::Message),
+    StashMessageChanged(String),
+    IncludeUntrackedToggled(bool),
+    KeepIndexToggled(bool),
+    Create,
+    Apply(StashInfo),
+    Pop(StashInfo),
+    Drop(StashInfo),
+    DropConfirmed,
+    DropCancelled,
+    /// 成功・失敗にかかわらず、親はここでカードを読み込み直す
+    Finished(Result<(), String>),
+}
+
+impl Stashes {
+    pub fn new(path: PathBuf, kind: RepositoryKind) -> Self {
+        Self {
+            path,
+            kind,
+            stashes: None,
+            selected: None,
+            diff: None,
+            expanded: HashSet::new(),
+            diff_view: DiffView::default(),
+            message_input: String::new(),
+            include_untracked: false,
+            keep_index: false,
+            pending_drop: None,
+            running: false,
+            error: None,
+        }
+    }
+
+    /// 前の一覧は読み込み終わるまで残す
+    pub fn load(&self) -> Task<Message> {
+        let path = self.path.clone();
+        let kind = self.kind;
+        Task::perform(async move { list_stashes(&path, kind) }, Message::Loaded)
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let header = row![
+            text("stash").font(Font {
+                weight: Weight::Bold,
+                ..Default::default()
+            }),
+            space().width(Length::Fill),
+            button(text("更新"))
+                .on_press_maybe((!self.running).then_some(Message::Refresh))
+                .style(button::secondary),
+        ]
+        .align_y(Alignment::Center);
+
+        let mut c = column![header, self.view_create()].spacing(10);
+        if let Some(error) = &self.error {
+            c = c.push(text(error).size(12).style(text::danger));
+        }
+
+        let stashes = match &self.stashes {
+            None => return c.push(text("読み込み中...")).into(),
+            Some(Err(err)) => {
+                return c
+                    .push(text(format!("stash を取得できません: {}", err)).style(text::danger))
+                    .into();
+            }
+            Some(Ok(stashes)) if stashes.is_empty() => {
+                return c.push(text("stash がありません")).into();
+            }
+            Some(Ok(stashes)) => stashes,
+        };
+
+        let mut list =
+            column![Column::with_children(stashes.iter().map(|x| self.view_stash(x))).spacing(8)]
+                .spacing(20);
+        if self.selected.is_some() {
+            list = list.push(self.view_diff());
+        }
+        c.push(scrollable(list).width(Length::Fill).height(Length::Fill))
+            .into()
+    }
+
+    pub fn view_dialog(&self) -> Option<Element<'_, Message>> {
+        let stash = self.pending_drop.as_ref()?;
+        Some(
+            Dialog::new(
+                format!("{} を削除", stash.name),
+                text("stash の変更は失われます。この操作は取り消せません。"),
+            )
+            .danger_action("削除", Message::DropConfirmed)
+            .secondary_action("中止", Message::DropCancelled)
+            .view(),
+        )
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Refresh => return self.load(),
+            Message::Loaded(result) => {
+                // 消えた stash の差分は閉じる
+                let exists = |name: &String| {
+                    result
+                        .as_ref()
+                        .is_ok_and(|x| x.iter().any(|x| &x.name == name))
+                };
+                if !self.selected.as_ref().is_some_and(exists) {
+                    self.selected = None;
+                    self.diff = None;
+                }
+                self.stashes = Some(result);
+            }
+            Message::Selected(name) => {
+                if self.selected.as_ref() == Some(&name) {
+                    self.selected = None;
+                    self.diff = None;
+                } else {
+                    self.selected = Some(name);
+                    self.diff = None;
+                    self.expanded.clear();
+                    return self.load_diff();
+                }
+            }
+            Message::DiffLoaded(name, result) => {
+                // 読み込んでいる間に別の stash を選んだら捨てる
+                if self.selected.as_ref() == Some(&name) {
+                    if let Ok(files) = &result {
+                        self.diff_view.prepare(files);
+                    }
+                    self.diff = Some(result);
+                }
+            }
+            Message::FileToggled(index) => {
+                if !self.expanded.remove(&index) {
+                    self.expanded.insert(index);
+                }
+            }
+            Message::Diff(msg) => {
+                if self.diff_view.update(msg) {
+                    return self.load_diff();
+                }
+            }
+            Message::StashMessageChanged(value) => self.message_input = value,
+            Message::IncludeUntrackedToggled(value) => self.include_untracked = value,
+            Message::KeepIndexToggled(value) => self.keep_index = value,
+            Message::Create => {
+                let message = self.message_input.trim().to_owned();
+                let include_untracked = self.include_untracked;
+                let keep_index = self.keep_index;
+                self.message_input.clear();
+                return self
+                    .run(move |path| create_stash(path, &message, include_untracked, keep_index));
+            }
+            Message::Apply(stash) => return self.run(move |path| apply_stash(path, &stash)),
+            Message::Pop(stash) => return self.run(move |path| pop_stash(path, &stash)),
+            Message::Drop(stash) => self.pending_drop = Some(stash),
+            Message::DropConfirmed => {
+                if let Some(stash) = self.pending_drop.take() {
+                    return self.run(move |path| drop_stash(path, &stash));
+                }
+            }
+            Message::DropCancelled => self.pending_drop = None,
+            Message::Finished(result) => {
+                self.running = false;
+                self.error = result.err();
+                // pop や drop で番号がずれるので差分は閉じる
+                self.selected = None;
+                self.diff = None;
+                return self.load();
+            }
+        }
+
+        Task::none()
+    }
+
+    fn load_diff(&self) -> Task<Message> {
+        let Some(name) = self.selected.clone() else {
+            return Task::none();
+        };
+        let path = self.path.clone();
+        let options = self.diff_view.options().clone();
+        Task::perform(
+            async move {
+                let result = stash_diff(&path, &name, &options);
+                (name, result)
+            },
+            |(name, result)| Message::DiffLoaded(name, result),
+        )
+    }
+
+    fn run(
+        &mut self,
+        operation: impl FnOnce(&Path) -> Result<(), String> + Send + 'static,
+    ) -> Task<Message> {
+        if self.running {
+            return Task::none();
+        }
+        self.running = true;
+        self.error = None;
+
+        let path = self.path.clone();
+        Task::perform(async move { operation(&path) }, Message::Finished)
+    }
+
+    fn view_create(&self) -> Element<'_, Message> {
+        let create = (!self.running).then_some(Message::Create);
+        column![
+            row![
+                text_input("メッセージ（省略可）", &self.message_input)
+                    .on_input(Message::StashMessageChanged)
+                    .on_submit_maybe(create.clone())
+                    .width(Length::Fill),
+                checkbox(self.include_untracked)
+                    .label("untracked を含める")
+                    .on_toggle(Message::IncludeUntrackedToggled),
+                checkbox(self.keep_index)
+                    .label("index を保つ")
+                    .on_toggle(Message::KeepIndexToggled),
+                button(text("stash"))
+                    .on_press_maybe(create)
+                    .style(button::primary),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            text("作業ツリーの変更を stash に退避します")
+                .size(12)
+                .style(text::secondary),
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    fn view_stash<'a>(&'a self, stash: &'a StashInfo) -> Element<'a, Message> {
+        let date = stash
+            .date
+            .map(|x| {
+                let datetime: DateTime<Local> = x.into();
+                datetime.format("%Y-%m-%d %H:%M").to_string()
+            })
+            .unwrap_or_default();
+        let selected = self.selected.as_ref() == Some(&stash.name);
+        let enabled = !self.running;
+
+        let r = row![
+            text(if selected { "▼" } else { "▶" }).size(12),
+            text(stash.index.to_string())
+                .size(12)
+                .font(iced::Font::MONOSPACE)
+                .width(30),
+            text(stash.branch.as_deref().unwrap_or("(no branch)"))
+                .size(12)
+                .width(120),
+            text(&stash.message).size(13).wrapping(text::Wrapping::None),
+            space().width(Length::Fill),
+            text(date).size(12).style(text::secondary),
+            button(text("適用").size(13))
+                .on_press_maybe(enabled.then(|| Message::Apply(stash.clone())))
+                .style(button::secondary),
+            button(text("pop").size(13))
+                .on_press_maybe(enabled.then(|| Message::Pop(stash.clone())))
+                .style(button::secondary),
+            button(text("削除").size(13))
+                .on_press_maybe(enabled.then(|| Message::Drop(stash.clone())))
+                .style(button::danger),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        // 押すと差分を表示する
+        mouse_area(r)
+            .on_press(Message::Selected(stash.name.clone()))
+            .into()
+    }
+
+    fn view_diff(&self) -> Element<'_, Message> {
+        let files: Element<'_, Message> = match &self.diff {
+            None => text("読み込み中...").into(),
+            Some(Err(err)) => text(format!("差分を取得できません: {}", err))
+                .style(text::danger)
+                .into(),
+            Some(Ok(files)) => Column::with_children(
+                files
+                    .iter()
+                    .enumerate()
+                    .map(|(index, file)| self.view_file(index, file)),
+            )
+            .spacing(4)
+            .into(),
+        };
+
+        column![self.diff_view.view_controls().map(Message::Diff), files]
+            .spacing(10)
+            .into()
+    }
+
+    fn view_file<'a>(&'a self, index: usize, file: &'a FileDiff) -> Element<'a, Message> {
+        let expanded = self.expanded.contains(&index);
+        let toggle = button(
+            row![
+                text(if expanded { "▼" } else { "▶" }).size(12),
+                text(file.display_path()).wrapping(text::Wrapping::None),
+                space().width(Length::Fill),
+                text(format!("+{}", file.additions())).style(text::success),
+                text(format!("-{}", file.deletions())).style(text::danger),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        )
+        .width(Length::Fill)
+        .on_press(Message::FileToggled(index))
+        .style(button::text);
+
+        let mut c = column![toggle];
+        if expanded {
+            c = c.push(
+                container(self.diff_view.view_file(file))
+                    .width(Length::Fill)
+                    .clip(true),
+            );
+        }
+        c.into()
+    }
+}
@@ -0,0 +1,188 @@
+use iced::mouse::{self, Interaction};
+use iced::widget::{button, container, mouse_area, row, text};
+use iced::{Element, Event, Length, Subscription, event};
+
+use crate::app::workspace::{ColumnWidths, GridOptions, SortKey};
+
+/// 列幅の下限
+const MIN_WIDTH: f32 = 40.0;
+/// 列の境界のつまみの幅
+const HANDLE_WIDTH: f32 = 6.0;
+
+/// 表の列
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Repository,
+    Kind,
+    Branch,
+    Status,
+    Upstream,
+    LastCommit,
+    Author,
+}
+
+impl Column {
+    pub const ALL: [Column; 7] = [
+        Column::Repository,
+        Column::Kind,
+        Column::Branch,
+        Column::Status,
+        Column::Upstream,
+        Column::LastCommit,
+        Column::Author,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Column::Repository => "リポジトリ",
+            Column::Kind => "種類",
+            Column::Branch => "ブランチ",
+            Column::Status => "変更",
+            Column::Upstream => "ahead/behind",
+            Column::LastCommit => "最終コミット",
+            Column::Author => "作者",
+        }
+    }
+
+    /// 見出しを押したときの並び順
+    fn sort_key(&self) -> SortKey {
+        match self {
+            Column::Repository => SortKey::Name,
+            Column::Kind => SortKey::Kind,
+            Column::Branch => SortKey::Branch,
+            Column::Status => SortKey::Dirty,
+            Column::Upstream => SortKey::AheadBehind,
+            Column::LastCommit => SortKey::LastCommit,
+            Column::Author => SortKey::Author,
+        }
+    }
+
+    pub fn width(&self, widths: &ColumnWidths) -> f32 {
+        match self {
+            Column::Repository => widths.repository,
+            Column::Kind => widths.kind,
+            Column::Branch => widths.branch,
+            Column::Status => widths.status,
+            Column::Upstream => widths.upstream,
+            Column::LastCommit => widths.last_commit,
+            Column::Author => widths.author,
+        }
+    }
+
+    fn width_mut<'a>(&self, widths: &'a mut ColumnWidths) -> &'a mut f32 {
+        match self {
+            Column::Repository => &mut widths.repository,
+            Column::Kind => &mut widths.kind,
+            Column::Branch => &mut widths.branch,
+            Column::Status => &mut widths.status,
+            Column::Upstream => &mut widths.upstream,
+            Column::LastCommit => &mut widths.last_commit,
+            Column::Author => &mut widths.author,
+        }
+    }
+}
+
+/// 見出しの境界をドラッグして列幅を変える
+#[derive(Debug, Default)]
+pub struct ColumnResize {
+    /// ドラッグ中の列と、開始時の列幅・カーソル位置（最初に動かすまでは None）
+    dragging: Option<(Column, f32, Option<f32>)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// 同じ列なら逆順にする
+    SortSelected(SortKey),
+    ResizeStarted(Column),
+    CursorMoved(f32),
+    ResizeEnded,
+}
+
+impl ColumnResize {
+    /// 設定を変えたら true（親が保存する）
+    pub fn update(&mut self, message: Message, options: &mut GridOptions) -> bool {
+        match message {
+            Message::SortSelected(key) => {
+                if options.sort == key {
+                    options.reverse = !options.reverse;
+                } else {
+                    options.sort = key;
+                    options.reverse = false;
+                }
+                true
+            }
+            Message::ResizeStarted(column) => {
+                self.dragging = Some((column, column.width(&options.column_widths), None));
+                false
+            }
+            Message::CursorMoved(x) => {
+                if let Some((column, start_width, start_x)) = &mut self.dragging {
+                    let start_x = *start_x.get_or_insert(x);
+                    *column.width_mut(&mut options.column_widths) =
+                        (*start_width + x - start_x).max(MIN_WIDTH);
+                }
+                false
+            }
+            // 幅はドラッグを終えたときにまとめて保存する
+            Message::ResizeEnded => self.dragging.take().is_some(),
+        }
+    }
+
+    /// ドラッグ中だけ、表の外に出てもウィンドウ全体でカーソルとボタンを離したのを追う
+    pub fn subscription(&self) -> Subscription<Message> {
+        if self.dragging.is_none() {
+            return Subscription::none();
+        }
+        event::listen_with(|event, _, _| match event {
+            Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                Some(Message::CursorMoved(position.x))
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                Some(Message::ResizeEnded)
+            }
+            _ => None,
+        })
+    }
+}
+
+/// 押すと並べ替え、境界をドラッグすると幅を変えられる見出し
+pub fn view_header(options: &GridOptions) -> Element<'_, Message> {
+    let cells = Column::ALL.into_iter().map(|column| {
+        let key = column.sort_key();
+        let label = match (options.sort == key, options.reverse) {
+            (true, false) => format!("{} ▼", column.label()),
+            (true, true) => format!("{} ▲", column.label()),
+            (false, _) => column.label().to_owned(),
+        };
+        let title = button(text(label).size(13))
+            .on_press(Message::SortSelected(key))
+            .padding([2, 4])
+            .style(button::text)
+            .width(Length::Fill);
+        let handle = mouse_area(
+            container(text(""))
+                .width(HANDLE_WIDTH)
+                .height(Length::Fill)
+                .style(container::dark),
+        )
+        .on_press(Message::ResizeStarted(column))
+        .interaction(Interaction::ResizingHorizontally);
+
+        Element::from(
+            row![title, handle]
+                .width(column.width(&options.column_widths))
+                .height(24),
+        )
+    });
+
+    row(cells).into()
+}
+
+/// 幅を固定し、はみ出した分は切る
+pub fn cell<'a, M: 'a>(content: impl Into<Element<'a, M>>, width: f32) -> Element<'a, M> {
+    container(content)
+        .width(width)
+        .padding([0, 4])
+        .clip(true)
+        .into()
+}
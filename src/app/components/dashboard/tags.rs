@@ -0,0 +1,287 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use iced::font::Weight;
+use iced::widget::{
+    Column, button, checkbox, column, container, mouse_area, row, scrollable, space, text,
+    text_input,
+};
+use iced::{Alignment, Element, Font, Length, Task};
+
+use crate::app::components::common::badge::badge;
+use crate::app::components::common::dialog::Dialog;
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::tag::{TagInfo, create_tag, delete_tag, list_tags};
+
+/// タグの一覧と、作成・削除
+#[derive(Debug, Clone)]
+pub struct Tags {
+    path: PathBuf,
+    kind: RepositoryKind,
+    /// 読み込み中は None
+    tags: Option<Result<Vec<TagInfo>, String>>,
+    /// メッセージを広げて表示しているタグ
+    expanded: Option<String>,
+    name_input: String,
+    annotated: bool,
+    message_input: String,
+    /// 削除の確認を待っているタグ
+    pending_delete: Option<String>,
+    running: bool,
+    /// 直前の操作が失敗したときのメッセージ
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Refresh,
+    Loaded(Result<Vec<TagInfo>, String>),
+    TagToggled(String),
+    NameChanged(String),
+    AnnotatedToggled(bool),
+    TagMessageChanged(String),
+    /// 作成するコミット
+    Create(String),
+    Delete(String),
+    DeleteConfirmed,
+    DeleteCancelled,
+    /// 成功・失敗にかかわらず、親はここでカードを読み込み直す
+    Finished(Result<(), String>),
+}
+
+impl Tags {
+    pub fn new(path: PathBuf, kind: RepositoryKind) -> Self {
+        Self {
+            path,
+            kind,
+            tags: None,
+            expanded: None,
+            name_input: String::new(),
+            annotated: false,
+            message_input: String::new(),
+            pending_delete: None,
+            running: false,
+            error: None,
+        }
+    }
+
+    /// 前の一覧は読み込み終わるまで残す
+    pub fn load(&self) -> Task<Message> {
+        let path = self.path.clone();
+        let kind = self.kind;
+        Task::perform(async move { list_tags(&path, kind) }, Message::Loaded)
+    }
+
+    /// selected_commit は履歴で選んでいるコミット。無ければ HEAD（jj では @-）に作る。
+    pub fn view<'a>(&'a self, selected_commit: Option<&'a str>) -> Element<'a, Message> {
+        let header = row![
+            text("タグ").font(Font {
+                weight: Weight::Bold,
+                ..Default::default()
+            }),
+            space().width(Length::Fill),
+            button(text("更新"))
+                .on_press_maybe((!self.running).then_some(Message::Refresh))
+                .style(button::secondary),
+        ]
+        .align_y(Alignment::Center);
+
+        let mut c = column![header, self.view_create(selected_commit)].spacing(10);
+        if let Some(error) = &self.error {
+            c = c.push(text(error).size(12).style(text::danger));
+        }
+
+        match &self.tags {
+            None => c.push(text("読み込み中...")).into(),
+            Some(Err(err)) => c
+                .push(text(format!("タグを取得できません: {}", err)).style(text::danger))
+                .into(),
+            Some(Ok(tags)) if tags.is_empty() => c.push(text("タグがありません")).into(),
+            Some(Ok(tags)) => c
+                .push(
+                    scrollable(
+                        Column::with_children(tags.iter().map(|x| self.view_tag(x))).spacing(8),
+                    )
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+                )
+                .into(),
+        }
+    }
+
+    pub fn view_dialog(&self) -> Option<Element<'_, Message>> {
+        let tag = self.pending_delete.as_ref()?;
+        Some(
+            Dialog::new(
+                format!("{} を削除", tag),
+                text("ローカルのタグだけを削除します。push 済みのタグはリモートに残ります。"),
+            )
+            .danger_action("削除", Message::DeleteConfirmed)
+            .secondary_action("中止", Message::DeleteCancelled)
+            .view(),
+        )
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Refresh => return self.load(),
+            Message::Loaded(result) => self.tags = Some(result),
+            Message::TagToggled(tag) => {
+                self.expanded = if self.expanded.as_ref() == Some(&tag) {
+                    None
+                } else {
+                    Some(tag)
+                };
+            }
+            Message::NameChanged(value) => self.name_input = value,
+            Message::AnnotatedToggled(value) => self.annotated = value,
+            Message::TagMessageChanged(value) => self.message_input = value,
+            Message::Create(target) => {
+                let name = self.name_input.trim().to_owned();
+                let message = self.annotated.then(|| self.message_input.trim().to_owned());
+                self.name_input.clear();
+                self.message_input.clear();
+                return self.run(move |path, kind| {
+                    create_tag(path, kind, &name, &target, message.as_deref())
+                });
+            }
+            Message::Delete(tag) => self.pending_delete = Some(tag),
+            Message::DeleteConfirmed => {
+                if let Some(tag) = self.pending_delete.take() {
+                    return self.run(move |path, kind| delete_tag(path, kind, &tag));
+                }
+            }
+            Message::DeleteCancelled => self.pending_delete = None,
+            Message::Finished(result) => {
+                self.running = false;
+                self.error = result.err();
+                return self.load();
+            }
+        }
+
+        Task::none()
+    }
+
+    fn run(
+        &mut self,
+        operation: impl FnOnce(&Path, RepositoryKind) -> Result<(), String> + Send + 'static,
+    ) -> Task<Message> {
+        if self.running {
+            return Task::none();
+        }
+        self.running = true;
+        self.error = None;
+
+        let path = self.path.clone();
+        let kind = self.kind;
+        Task::perform(async move { operation(&path, kind) }, Message::Finished)
+    }
+
+    fn view_create<'a>(&'a self, selected_commit: Option<&'a str>) -> Element<'a, Message> {
+        let target = match selected_commit {
+            Some(commit) => commit.to_owned(),
+            None if self.kind.is_jj() => "@-".to_owned(),
+            None => "HEAD".to_owned(),
+        };
+        let can_create = !self.running
+            && !self.name_input.trim().is_empty()
+            && (!self.annotated || !self.message_input.trim().is_empty());
+        let create = can_create.then(|| Message::Create(target.clone()));
+
+        let mut r = row![
+            text_input("新しいタグ名", &self.name_input)
+                .on_input(Message::NameChanged)
+                .on_submit_maybe(create.clone())
+                .width(160),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+        // jj のタグは軽量タグだけ
+        if self.kind != RepositoryKind::Jj {
+            r = r.push(
+                checkbox(self.annotated)
+                    .label("注釈付き")
+                    .on_toggle(Message::AnnotatedToggled),
+            );
+        }
+        if self.annotated {
+            r = r.push(
+                text_input("メッセージ", &self.message_input)
+                    .on_input(Message::TagMessageChanged)
+                    .on_submit_maybe(create.clone())
+                    .width(Length::Fill),
+            );
+        } else {
+            r = r.push(space().width(Length::Fill));
+        }
+
+        column![
+            r.push(
+                button(text("作成"))
+                    .on_press_maybe(create)
+                    .style(button::primary)
+            ),
+            text(match selected_commit {
+                Some(_) => format!(
+                    "履歴で選んだコミット {} に作成します",
+                    target.chars().take(10).collect::<String>()
+                ),
+                None => format!(
+                    "{} に作成します（履歴でコミットを選ぶとそこに作成します）",
+                    target
+                ),
+            })
+            .size(12)
+            .style(text::secondary),
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    fn view_tag<'a>(&'a self, tag: &'a TagInfo) -> Element<'a, Message> {
+        let date = tag
+            .date
+            .map(|x| {
+                let datetime: DateTime<Local> = x.into();
+                datetime.format("%Y-%m-%d %H:%M").to_string()
+            })
+            .unwrap_or_default();
+        let expanded = self.expanded.as_ref() == Some(&tag.name);
+
+        let mut header = row![text(&tag.name).width(160)]
+            .spacing(10)
+            .align_y(Alignment::Center);
+        header = header.push(if tag.annotated {
+            badge("注釈付き", container::primary)
+        } else {
+            badge("軽量", container::secondary)
+        });
+        header = header
+            .push(
+                text(tag.target.chars().take(10).collect::<String>())
+                    .size(12)
+                    .font(iced::Font::MONOSPACE),
+            )
+            .push(text(format!("{}  {}", tag.tagger, date)).size(12))
+            .push(space().width(Length::Fill))
+            .push(
+                button(text("削除").size(13))
+                    .on_press_maybe((!self.running).then(|| Message::Delete(tag.name.clone())))
+                    .style(button::danger),
+            );
+
+        // 押すとメッセージ全体を表示する
+        let message = if expanded {
+            tag.message.clone()
+        } else {
+            tag.message.lines().next().unwrap_or_default().to_owned()
+        };
+        mouse_area(
+            column![header, text(message).size(13).style(text::secondary)]
+                .spacing(2)
+                .width(Length::Fill),
+        )
+        .on_press(Message::TagToggled(tag.name.clone()))
+        .into()
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+use ignore::overrides::OverrideBuilder;
+use serde::{Deserialize, Serialize};
+
+/// 常に探索対象から外すディレクトリ
+const DEFAULT_EXCLUDE_PATTERNS: [&str; 2] = ["target/", "node_modules/"];
+
+/// VCS のメタデータディレクトリ（中身は探索しない）
+pub const METADATA_DIR_NAMES: [&str; 2] = [".git", ".jj"];
+
+/// リポジトリの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RepositoryKind {
+    /// .git ディレクトリを持つ通常の Git リポジトリ
+    Git,
+    /// .jj のみを持つ jj リポジトリ
+    Jj,
+    /// .git と .jj が同居するリポジトリ
+    Colocated,
+    /// 作業ツリーを持たない bare リポジトリ
+    Bare,
+    /// `git worktree add` で作られた作業ツリー（.git がファイル）
+    Worktree,
+    /// サブモジュールなど、.git が gitdir を指すファイルのもの
+    GitFile,
+}
+
+impl RepositoryKind {
+    /// jj で操作するリポジトリか（colocated も jj 側で扱う）
+    pub fn is_jj(&self) -> bool {
+        matches!(self, RepositoryKind::Jj | RepositoryKind::Colocated)
+    }
+}
+
+impl std::fmt::Display for RepositoryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            RepositoryKind::Git => "Git",
+            RepositoryKind::Jj => "jj",
+            RepositoryKind::Colocated => "Git + jj",
+            RepositoryKind::Bare => "Git (bare)",
+            RepositoryKind::Worktree => "Git (worktree)",
+            RepositoryKind::GitFile => "Git (gitfile)",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// 探索で見つかったリポジトリ
+#[derive(Debug, Clone)]
+pub struct DiscoveredRepository {
+    pub path: PathBuf,
+    pub kind: RepositoryKind,
+}
+
+/// リポジトリ探索の設定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscoveryOptions {
+    /// 選択フォルダからの最大深さ（0 は選択フォルダ自身のみ）
+    pub max_depth: usize,
+    /// 追加の除外パターン（gitignore 形式の glob）
+    pub exclude_patterns: Vec<String>,
+    /// .gitignore / .ignore に従うか
+    pub respect_gitignore: bool,
+    /// 見つかったリポジトリの作業ツリー内も探索するか
+    pub descend_into_repositories: bool,
+}
+
+impl Default for DiscoveryOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            exclude_patterns: vec![],
+            respect_gitignore: true,
+            descend_into_repositories: false,
+        }
+    }
+}
+
+/// 複数のルートを探索し、ピン留めされたリポジトリと合わせて返す
+pub fn discover_all(
+    roots: &[PathBuf],
+    pinned: &[PathBuf],
+    options: &DiscoveryOptions,
+) -> Vec<DiscoveredRepository> {
+    let pinned = pinned.iter().map(|path| DiscoveredRepository {
+        path: path.clone(),
+        // リポジトリでなくなっていても、カード側でエラーとして見せる
+        kind: repository_kind(path).unwrap_or(RepositoryKind::Git),
+    });
+
+    dedup(
+        roots
+            .iter()
+            .flat_map(|root| discover(root, options))
+            .chain(pinned),
+    )
+}
+
+/// glob として解釈できない除外パターンと、その理由
+pub fn invalid_exclude_patterns(patterns: &[String]) -> Vec<String> {
+    patterns
+        .iter()
+        .filter_map(|pattern| {
+            OverrideBuilder::new("/")
+                .add(&format!("!{}", pattern))
+                .err()
+                .map(|err| format!("除外パターンを無視します: {} ({})", pattern, err))
+        })
+        .collect()
+}
+
+/// root 以下のリポジトリを探索し、見つかったリポジトリを返す
+pub fn discover(root: &Path, options: &DiscoveryOptions) -> Vec<DiscoveredRepository> {
+    let mut overrides = OverrideBuilder::new(root);
+    for pattern in DEFAULT_EXCLUDE_PATTERNS
+        .iter()
+        .map(|x| x.to_string())
+        .chain(options.exclude_patterns.iter().cloned())
+    {
+        // 先頭に "!" を付けると除外パターンとして扱われる。
+        // 不正なパターンは invalid_exclude_patterns で入力欄に表示するので、ここでは無視する。
+        let _ = overrides.add(&format!("!{}", pattern));
+    }
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .max_depth(Some(options.max_depth))
+        .hidden(false)
+        .parents(options.respect_gitignore)
+        .ignore(options.respect_gitignore)
+        .git_ignore(options.respect_gitignore)
+        .git_global(options.respect_gitignore)
+        .git_exclude(options.respect_gitignore)
+        // 選択フォルダ自体がリポジトリでなくても .gitignore を読む
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b));
+    if let Ok(overrides) = overrides.build() {
+        builder.overrides(overrides);
+    }
+
+    let descend_into_repositories = options.descend_into_repositories;
+    builder.filter_entry(move |entry| {
+        if !entry.file_type().is_some_and(|x| x.is_dir()) {
+            return false;
+        }
+        if METADATA_DIR_NAMES.iter().any(|x| entry.file_name() == *x) {
+            return false;
+        }
+        if entry.depth() == 0 || descend_into_repositories {
+            return true;
+        }
+        // 親がリポジトリなら、その作業ツリーには入らない
+        match entry.path().parent() {
+            Some(parent) => !is_repository(parent),
+            None => true,
+        }
+    });
+
+    dedup(builder.build().flatten().filter_map(|entry| {
+        Some(DiscoveredRepository {
+            kind: repository_kind(entry.path())?,
+            path: entry.into_path(),
+        })
+    }))
+}
+
+/// シンボリックリンク経由などで同じリポジトリが二度出てくるのを防ぐ
+fn dedup(repos: impl Iterator<Item = DiscoveredRepository>) -> Vec<DiscoveredRepository> {
+    let mut seen = HashSet::new();
+    repos
+        .filter(|x| seen.insert(fs::canonicalize(&x.path).unwrap_or_else(|_| x.path.clone())))
+        .collect()
+}
+
+/// path がリポジトリのルートかどうか
+pub fn is_repository(path: &Path) -> bool {
+    repository_kind(path).is_some()
+}
+
+/// path がリポジトリのルートであれば、その種類を返す
+pub fn repository_kind(path: &Path) -> Option<RepositoryKind> {
+    if !path.is_dir() {
+        return None;
+    }
+
+    let dot_git = path.join(".git");
+    // colocated なリポジトリは .jj 側で一枚のカードにまとめる
+    if path.join(".jj").is_dir() {
+        return if dot_git.exists() {
+            Some(RepositoryKind::Colocated)
+        } else {
+            Some(RepositoryKind::Jj)
+        };
+    }
+    if dot_git.is_dir() {
+        return Some(RepositoryKind::Git);
+    }
+    if dot_git.is_file() {
+        return gitfile_kind(&dot_git);
+    }
+    if path.join("HEAD").is_file() && path.join("objects").is_dir() && path.join("refs").is_dir() {
+        return Some(RepositoryKind::Bare);
+    }
+    None
+}
+
+/// `gitdir: ...` 形式の .git ファイルから種類を判定する
+fn gitfile_kind(dot_git: &Path) -> Option<RepositoryKind> {
+    let content = fs::read_to_string(dot_git).ok()?;
+    let gitdir = content
+        .lines()
+        .find_map(|x| x.strip_prefix("gitdir:"))?
+        .trim();
+
+    let gitdir = PathBuf::from(gitdir);
+    let gitdir = if gitdir.is_relative() {
+        dot_git.parent()?.join(gitdir)
+    } else {
+        gitdir
+    };
+
+    // linked worktree の gitdir には本体を指す commondir がある
+    if gitdir.join("commondir").is_file() {
+        Some(RepositoryKind::Worktree)
+    } else {
+        Some(RepositoryKind::GitFile)
+    }
+}
@@ -0,0 +1,505 @@
+use std::ops::Range;
+use std::path::Path;
+
+/// 色分けの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    /// 型名や設定ファイルのキー
+    Type,
+    String,
+    Number,
+    Comment,
+}
+
+/// 一行の中の色分けされた範囲（バイト単位）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub range: Range<usize>,
+    pub kind: TokenKind,
+}
+
+/// 言語ごとの字句規則
+#[derive(Debug)]
+pub struct Language {
+    extensions: &'static [&'static str],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    /// 大文字で始まる識別子を型とみなす
+    capitalized_types: bool,
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    /// 行頭のキーと値の区切り（TOML の `=`、YAML の `:`）
+    key_separator: Option<char>,
+    /// `[section]` や `# 見出し` のように行全体で意味を持つ記法
+    line_rules: LineRules,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineRules {
+    None,
+    TomlTable,
+    Markdown,
+}
+
+const RUST: Language = Language {
+    extensions: &["rs"],
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ],
+    types: &[
+        "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+        "i64", "i128", "isize", "f32", "f64",
+    ],
+    capitalized_types: true,
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"'],
+    key_separator: None,
+    line_rules: LineRules::None,
+};
+
+const TOML: Language = Language {
+    extensions: &["toml"],
+    keywords: &["true", "false"],
+    types: &[],
+    capitalized_types: false,
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: &['"', '\''],
+    key_separator: Some('='),
+    line_rules: LineRules::TomlTable,
+};
+
+const MARKDOWN: Language = Language {
+    extensions: &["md", "markdown"],
+    keywords: &[],
+    types: &[],
+    capitalized_types: false,
+    line_comments: &[],
+    block_comment: Some(("<!--", "-->")),
+    quotes: &['`'],
+    key_separator: None,
+    line_rules: LineRules::Markdown,
+};
+
+const JAVASCRIPT_KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "of",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "yield",
+];
+
+const JAVASCRIPT: Language = Language {
+    extensions: &["js", "mjs", "cjs", "jsx"],
+    keywords: JAVASCRIPT_KEYWORDS,
+    types: &[],
+    capitalized_types: true,
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+    key_separator: None,
+    line_rules: LineRules::None,
+};
+
+const TYPESCRIPT: Language = Language {
+    extensions: &["ts", "tsx", "mts", "cts"],
+    keywords: &[
+        "abstract",
+        "as",
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "declare",
+        "default",
+        "do",
+        "else",
+        "enum",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "from",
+        "function",
+        "if",
+        "implements",
+        "import",
+        "in",
+        "interface",
+        "keyof",
+        "let",
+        "namespace",
+        "new",
+        "null",
+        "of",
+        "private",
+        "protected",
+        "public",
+        "readonly",
+        "return",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "type",
+        "typeof",
+        "undefined",
+        "var",
+        "void",
+        "while",
+        "yield",
+    ],
+    types: &[
+        "any", "boolean", "never", "number", "object", "string", "symbol", "unknown",
+    ],
+    capitalized_types: true,
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+    key_separator: None,
+    line_rules: LineRules::None,
+};
+
+const PYTHON: Language = Language {
+    extensions: &["py", "pyi"],
+    keywords: &[
+        "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+        "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global",
+        "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+        "self", "try", "while", "with", "yield",
+    ],
+    types: &[
+        "bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple",
+    ],
+    capitalized_types: true,
+    line_comments: &["#"],
+    block_comment: Some(("\"\"\"", "\"\"\"")),
+    quotes: &['"', '\''],
+    key_separator: None,
+    line_rules: LineRules::None,
+};
+
+const GO: Language = Language {
+    extensions: &["go"],
+    keywords: &[
+        "break",
+        "case",
+        "chan",
+        "const",
+        "continue",
+        "default",
+        "defer",
+        "else",
+        "fallthrough",
+        "false",
+        "for",
+        "func",
+        "go",
+        "goto",
+        "if",
+        "import",
+        "interface",
+        "iota",
+        "map",
+        "nil",
+        "package",
+        "range",
+        "return",
+        "select",
+        "struct",
+        "switch",
+        "true",
+        "type",
+        "var",
+    ],
+    types: &[
+        "any",
+        "bool",
+        "byte",
+        "complex64",
+        "complex128",
+        "error",
+        "float32",
+        "float64",
+        "int",
+        "int8",
+        "int16",
+        "int32",
+        "int64",
+        "rune",
+        "string",
+        "uint",
+        "uint8",
+        "uint16",
+        "uint32",
+        "uint64",
+        "uintptr",
+    ],
+    capitalized_types: false,
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+    key_separator: None,
+    line_rules: LineRules::None,
+};
+
+const YAML: Language = Language {
+    extensions: &["yml", "yaml"],
+    keywords: &["true", "false", "null", "yes", "no", "on", "off"],
+    types: &[],
+    capitalized_types: false,
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: &['"', '\''],
+    key_separator: Some(':'),
+    line_rules: LineRules::None,
+};
+
+const SHELL: Language = Language {
+    extensions: &["sh", "bash", "zsh"],
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "readonly", "return", "select", "set", "then", "until", "while",
+    ],
+    types: &[],
+    capitalized_types: false,
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: &['"', '\''],
+    key_separator: None,
+    line_rules: LineRules::None,
+};
+
+const LANGUAGES: [&Language; 9] = [
+    &RUST,
+    &TOML,
+    &MARKDOWN,
+    &JAVASCRIPT,
+    &TYPESCRIPT,
+    &PYTHON,
+    &GO,
+    &YAML,
+    &SHELL,
+];
+
+/// 拡張子から言語を選ぶ
+pub fn language_for(path: &str) -> Option<&'static Language> {
+    let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    LANGUAGES
+        .into_iter()
+        .find(|x| x.extensions.contains(&extension.as_str()))
+}
+
+/// 行をまたぐ状態（ブロックコメントの途中か）を持ちながら一行ずつ色分けする
+#[derive(Debug)]
+pub struct Highlighter {
+    language: &'static Language,
+    in_block_comment: bool,
+}
+
+impl Highlighter {
+    pub fn new(language: &'static Language) -> Self {
+        Self {
+            language,
+            in_block_comment: false,
+        }
+    }
+
+    pub fn highlight_line(&mut self, line: &str) -> Vec<Token> {
+        let language = self.language;
+        let mut tokens = vec![];
+        let mut pos = 0;
+
+        if self.in_block_comment {
+            pos = self.block_comment_end(line, 0);
+            push(&mut tokens, 0..pos, TokenKind::Comment);
+        } else {
+            match language.line_rules {
+                LineRules::Markdown => {
+                    let trimmed = line.trim_start();
+                    if trimmed.starts_with('#') || trimmed.starts_with("```") {
+                        push(&mut tokens, 0..line.len(), TokenKind::Keyword);
+                        return tokens;
+                    }
+                }
+                LineRules::TomlTable => {
+                    if line.trim_start().starts_with('[') {
+                        push(&mut tokens, 0..line.len(), TokenKind::Keyword);
+                        return tokens;
+                    }
+                }
+                LineRules::None => (),
+            }
+            if let Some(separator) = language.key_separator
+                && let Some(key) = key_range(line, separator)
+            {
+                push(&mut tokens, 0..key.start, TokenKind::Plain);
+                push(&mut tokens, key.clone(), TokenKind::Type);
+                pos = key.end;
+            }
+        }
+
+        while pos < line.len() {
+            let rest = &line[pos..];
+            let c = rest.chars().next().unwrap_or_default();
+
+            if let Some((open, _)) = language.block_comment
+                && rest.starts_with(open)
+            {
+                self.in_block_comment = true;
+                let end = self.block_comment_end(line, pos + open.len());
+                push(&mut tokens, pos..end, TokenKind::Comment);
+                pos = end;
+            } else if language.line_comments.iter().any(|x| rest.starts_with(x)) {
+                push(&mut tokens, pos..line.len(), TokenKind::Comment);
+                pos = line.len();
+            } else if language.quotes.contains(&c) {
+                let end = string_end(line, pos, c);
+                push(&mut tokens, pos..end, TokenKind::String);
+                pos = end;
+            } else if c.is_ascii_digit() {
+                let end = word_end(line, pos, |x| {
+                    x.is_ascii_alphanumeric() || x == '.' || x == '_'
+                });
+                push(&mut tokens, pos..end, TokenKind::Number);
+                pos = end;
+            } else if c.is_alphabetic() || c == '_' {
+                let end = word_end(line, pos, |x| x.is_alphanumeric() || x == '_');
+                let word = &line[pos..end];
+                let kind = if language.keywords.contains(&word) {
+                    TokenKind::Keyword
+                } else if language.types.contains(&word)
+                    || (language.capitalized_types && c.is_uppercase())
+                {
+                    TokenKind::Type
+                } else {
+                    TokenKind::Plain
+                };
+                push(&mut tokens, pos..end, kind);
+                pos = end;
+            } else {
+                push(&mut tokens, pos..pos + c.len_utf8(), TokenKind::Plain);
+                pos += c.len_utf8();
+            }
+        }
+
+        tokens
+    }
+
+    /// from 以降でブロックコメントが閉じる位置。閉じなければ行末を返し、状態を持ち越す。
+    fn block_comment_end(&mut self, line: &str, from: usize) -> usize {
+        let Some((_, close)) = self.language.block_comment else {
+            return line.len();
+        };
+        match line[from..].find(close) {
+            Some(i) => {
+                self.in_block_comment = false;
+                from + i + close.len()
+            }
+            None => line.len(),
+        }
+    }
+}
+
+/// 直前と同じ種類なら繋げる
+fn push(tokens: &mut Vec<Token>, range: Range<usize>, kind: TokenKind) {
+    if range.is_empty() {
+        return;
+    }
+    match tokens.last_mut() {
+        Some(last) if last.kind == kind && last.range.end == range.start => {
+            last.range.end = range.end
+        }
+        _ => tokens.push(Token { range, kind }),
+    }
+}
+
+/// 閉じ引用符の直後の位置（エスケープを考慮する）。閉じなければ行末。
+fn string_end(line: &str, start: usize, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in line[start + quote.len_utf8()..].char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return start + quote.len_utf8() + i + c.len_utf8();
+        }
+    }
+    line.len()
+}
+
+fn word_end(line: &str, start: usize, f: impl Fn(char) -> bool) -> usize {
+    line[start..]
+        .char_indices()
+        .find(|(_, c)| !f(*c))
+        .map(|(i, _)| start + i)
+        .unwrap_or(line.len())
+}
+
+/// 行頭（インデントと YAML の `- ` の後）から区切りまでのキーの範囲
+fn key_range(line: &str, separator: char) -> Option<Range<usize>> {
+    let indent = line.len() - line.trim_start().len();
+    let start = match line[indent..].strip_prefix("- ") {
+        Some(_) => indent + 2,
+        None => indent,
+    };
+    let end = start + line[start..].find(separator)?;
+    let key = &line[start..end];
+    let is_key = !key.trim().is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ' ' | '"' | '\''));
+    is_key.then(|| start..start + key.trim_end().len())
+}
@@ -15,6 +15,5 @@ pub fn system_time_to_string(system_time: SystemTime) -> String {
     )
     .expect("Invalid timestamp")
     .with_timezone(&Local);
-    let x = datetime.to_string();
-    x
+    datetime.to_string()
 }
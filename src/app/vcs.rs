@@ -0,0 +1,86 @@
+pub mod branch;
+pub mod bulk;
+pub mod cleanup;
+pub mod commit;
+pub mod diff;
+pub mod graph;
+pub mod history;
+pub mod remote;
+pub mod stage;
+pub mod stash;
+pub mod status;
+pub mod tag;
+pub mod upstream;
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// リポジトリ内で git を実行し、標準出力を返す
+pub fn git(path: &Path, args: &[&str]) -> Result<String, String> {
+    run(Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        // 読み取り専用の操作で index.lock を取らない
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        // 認証などでターミナルの入力待ちにならないようにする
+        .env("GIT_TERMINAL_PROMPT", "0"))
+}
+
+/// 標準入力に input を渡して git を実行する
+pub fn git_with_input(path: &Path, args: &[&str], input: &str) -> Result<String, String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|err| err.to_string())?;
+    }
+    into_result(child.wait_with_output().map_err(|err| err.to_string())?)
+}
+
+/// リポジトリ内で jj を実行し、標準出力を返す
+pub fn jj(path: &Path, args: &[&str]) -> Result<String, String> {
+    run(Command::new("jj")
+        .arg("-R")
+        .arg(path)
+        .args(["--no-pager", "--color=never"])
+        .args(args))
+}
+
+/// jj を実行し、標準出力をバイト列のまま返す（UTF-8 とは限らないファイルの内容など）
+pub fn jj_bytes(path: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("jj")
+        .arg("-R")
+        .arg(path)
+        .args(["--no-pager", "--color=never"])
+        .args(args)
+        .output()
+        .map_err(|err| err.to_string())?;
+    into_bytes(output)
+}
+
+fn run(command: &mut Command) -> Result<String, String> {
+    into_result(command.output().map_err(|err| err.to_string())?)
+}
+
+fn into_result(output: Output) -> Result<String, String> {
+    into_bytes(output).map(|x| String::from_utf8_lossy(&x).into_owned())
+}
+
+fn into_bytes(output: Output) -> Result<Vec<u8>, String> {
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_owned())
+    }
+}
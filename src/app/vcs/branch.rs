@@ -0,0 +1,377 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::history::{HistoryEntry, parse_entry};
+use crate::app::vcs::upstream::jj_count;
+use crate::app::vcs::{git, jj};
+
+/// ブランチ切り替え時の作業ツリーの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchMode {
+    /// 変更を stash してから切り替える
+    Stash,
+    /// 変更をそのまま持っていく（衝突する場合は失敗する）
+    Carry,
+    /// jj: ブックマークの上に新しい change を作る (`jj new`)
+    JjNew,
+    /// jj: ブックマークの change を直接編集する (`jj edit`)
+    JjEdit,
+}
+
+/// branch に切り替える
+pub fn switch_branch(
+    path: &Path,
+    kind: RepositoryKind,
+    branch: &str,
+    mode: SwitchMode,
+) -> Result<(), String> {
+    match (kind, mode) {
+        (RepositoryKind::Jj | RepositoryKind::Colocated, SwitchMode::JjEdit) => {
+            jj(path, &["edit", branch]).map(|_| ())
+        }
+        (RepositoryKind::Jj | RepositoryKind::Colocated, _) => {
+            jj(path, &["new", branch]).map(|_| ())
+        }
+        (_, SwitchMode::Stash) => {
+            let message = format!("skjra: {} への切り替え前に退避", branch);
+            let before = latest_stash(path);
+            git(
+                path,
+                &["stash", "push", "--include-untracked", "-m", &message],
+            )?;
+            // 変更が無ければ stash は作られない
+            let stashed = latest_stash(path) != before;
+
+            match git(path, &["switch", branch]) {
+                Ok(_) => Ok(()),
+                Err(err) if !stashed => Err(err),
+                // 切り替えに失敗したら、退避した変更を元に戻す
+                Err(err) => match git(path, &["stash", "pop", "--index", "stash@{0}"]) {
+                    Ok(_) => Err(err),
+                    Err(pop_err) => Err(format!(
+                        "{}\n変更は stash「{}」に残っています: {}",
+                        err, message, pop_err
+                    )),
+                },
+            }
+        }
+        _ => git(path, &["switch", branch]).map(|_| ()),
+    }
+}
+
+/// 一番新しい stash のコミット。stash が無ければ None。
+fn latest_stash(path: &Path) -> Option<String> {
+    git(path, &["rev-parse", "-q", "--verify", "refs/stash"])
+        .ok()
+        .map(|x| x.trim().to_owned())
+}
+
+/// ブランチ（jj ではブックマーク）
+#[derive(Debug, Clone)]
+pub struct BranchInfo {
+    pub name: String,
+    /// リモート追跡ブランチならリモート名
+    pub remote: Option<String>,
+    /// 今いるブランチか
+    pub current: bool,
+    /// 追跡している upstream（git は `origin/main`、jj は `main@origin`）
+    pub upstream: Option<String>,
+    /// upstream に対して進んでいる・遅れているコミット数
+    pub ahead: usize,
+    pub behind: usize,
+    /// upstream を設定しているが、リモートでは消えている
+    pub upstream_gone: bool,
+    /// 既定ブランチに取り込み済みか
+    pub merged: bool,
+    /// 指しているコミット（jj で衝突しているブックマークは None）
+    pub last_commit: Option<HistoryEntry>,
+}
+
+impl BranchInfo {
+    /// git の `origin/main`、jj の `main@origin` の形で、リモート追跡ブランチの名前を返す
+    pub fn full_name(&self, kind: RepositoryKind) -> String {
+        match &self.remote {
+            Some(remote) if kind.is_jj() => format!("{}@{}", self.name, remote),
+            Some(remote) => format!("{}/{}", remote, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// 取り込み済みかを判断する既定ブランチ。
+/// git は origin/HEAD、無ければローカルの main か master。jj は trunk()。
+pub fn default_branch(path: &Path, kind: RepositoryKind) -> Option<String> {
+    if kind.is_jj() {
+        return Some("trunk()".to_owned());
+    }
+
+    if let Ok(x) = git(
+        path,
+        &[
+            "symbolic-ref",
+            "--quiet",
+            "--short",
+            "refs/remotes/origin/HEAD",
+        ],
+    ) {
+        return Some(x.trim().to_owned());
+    }
+    ["main", "master"]
+        .into_iter()
+        .find(|x| {
+            let name = format!("refs/heads/{}", x);
+            git(path, &["show-ref", "--verify", "--quiet", &name]).is_ok()
+        })
+        .map(|x| x.to_owned())
+}
+
+/// ローカルとリモートのブランチの一覧
+pub fn list_branches(path: &Path, kind: RepositoryKind) -> Result<Vec<BranchInfo>, String> {
+    if kind.is_jj() {
+        return jj_list_branches(path);
+    }
+
+    let output = git(
+        path,
+        &[
+            "for-each-ref",
+            concat!(
+                "--format=%(refname)%09%(HEAD)%09%(upstream:short)%09%(upstream:track,nobracket)",
+                "%09%(objectname)%09%(authorname)%09%(authordate:unix)%09%(subject)",
+            ),
+            "refs/heads",
+            "refs/remotes",
+        ],
+    )?;
+    let merged = match default_branch(path, kind) {
+        Some(default) => {
+            let merged = format!("--merged={}", default);
+            git(
+                path,
+                &[
+                    "for-each-ref",
+                    &merged,
+                    "--format=%(refname)",
+                    "refs/heads",
+                    "refs/remotes",
+                ],
+            )?
+        }
+        None => String::new(),
+    };
+    let merged = merged.lines().collect::<HashSet<_>>();
+
+    let mut branches = vec![];
+    for line in output.lines() {
+        let mut fields = line.splitn(5, '\t');
+        let (Some(refname), Some(head), Some(upstream), Some(track), Some(commit)) = (
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+        ) else {
+            continue;
+        };
+        let (name, remote) = if let Some(name) = refname.strip_prefix("refs/heads/") {
+            (name, None)
+        } else if let Some((remote, name)) = refname
+            .strip_prefix("refs/remotes/")
+            .and_then(|x| x.split_once('/'))
+            // origin/HEAD は既定ブランチへの参照なので除く
+            && name != "HEAD"
+        {
+            (name, Some(remote.to_owned()))
+        } else {
+            continue;
+        };
+        let (ahead, behind) = parse_track(track);
+
+        branches.push(BranchInfo {
+            name: name.to_owned(),
+            remote,
+            current: head == "*",
+            upstream: (!upstream.is_empty()).then(|| upstream.to_owned()),
+            ahead,
+            behind,
+            upstream_gone: track == "gone",
+            merged: merged.contains(refname),
+            last_commit: parse_entry(commit),
+        });
+    }
+    Ok(branches)
+}
+
+/// `ahead 1, behind 2` の形の %(upstream:track) を解釈する
+fn parse_track(track: &str) -> (usize, usize) {
+    let mut counts = (0, 0);
+    for part in track.split(", ") {
+        match part.split_once(' ') {
+            Some(("ahead", x)) => counts.0 = x.parse().unwrap_or_default(),
+            Some(("behind", x)) => counts.1 = x.parse().unwrap_or_default(),
+            _ => (),
+        }
+    }
+    counts
+}
+
+fn jj_list_branches(path: &Path) -> Result<Vec<BranchInfo>, String> {
+    let output = jj(
+        path,
+        &[
+            "bookmark",
+            "list",
+            "--all-remotes",
+            "-T",
+            concat!(
+                r#"name ++ "\t" ++ if(remote, remote, "") ++ "\t" ++ if(tracked, "*", "")"#,
+                r#" ++ "\t" ++ if(normal_target, normal_target.commit_id()"#,
+                r#" ++ "\t" ++ normal_target.author().name()"#,
+                r#" ++ "\t" ++ normal_target.author().timestamp().format("%s")"#,
+                r#" ++ "\t" ++ normal_target.description().first_line()) ++ "\n""#,
+            ),
+        ],
+    )?;
+    // @ から最も近いブックマークを今いるものとみなす（upstream_status と同じ）
+    let current = jj(
+        path,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            "heads(::@ & bookmarks())",
+            "-T",
+            r#"local_bookmarks.map(|b| b.name()).join("\n") ++ "\n""#,
+        ],
+    )?;
+    let current = current.lines().map(|x| x.trim()).find(|x| !x.is_empty());
+    let merged = jj(
+        path,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            "(bookmarks() | remote_bookmarks()) & ::trunk()",
+            "-T",
+            concat!(
+                r#"local_bookmarks.map(|b| b.name() ++ "\n").join("")"#,
+                r#" ++ remote_bookmarks.map(|b| b.name() ++ "@" ++ b.remote() ++ "\n").join("")"#,
+            ),
+        ],
+    )?;
+    let merged = merged.lines().collect::<HashSet<_>>();
+
+    let mut branches = vec![];
+    for line in output.lines() {
+        let mut fields = line.splitn(4, '\t');
+        let (Some(name), Some(remote), Some(tracked)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        // colocated で git 側の ref を指す @git は除く
+        if remote == "git" {
+            continue;
+        }
+        let branch = BranchInfo {
+            name: name.to_owned(),
+            remote: (!remote.is_empty()).then(|| remote.to_owned()),
+            current: false,
+            upstream: None,
+            ahead: 0,
+            behind: 0,
+            upstream_gone: false,
+            merged: false,
+            last_commit: fields.next().and_then(parse_entry),
+        };
+        let full_name = branch.full_name(RepositoryKind::Jj);
+        branches.push(BranchInfo {
+            current: remote.is_empty() && Some(name) == current,
+            merged: merged.contains(full_name.as_str()),
+            ..branch
+        });
+
+        // 追跡しているリモートのブックマークを、同じ名前のローカルのブックマークの upstream にする
+        if tracked == "*"
+            && let Some(local) = branches
+                .iter_mut()
+                .find(|x| x.name == name && x.remote.is_none())
+        {
+            local.upstream = Some(full_name);
+        }
+    }
+
+    for branch in branches.iter_mut() {
+        if let Some(upstream) = &branch.upstream {
+            let local = format!(r#"bookmarks(exact:"{}")"#, branch.name);
+            let (name, remote) = upstream.split_once('@').unwrap_or_default();
+            let remote = format!(r#"remote_bookmarks(exact:"{}", exact:"{}")"#, name, remote);
+            branch.ahead = jj_count(path, &format!("{}..{}", remote, local))?;
+            branch.behind = jj_count(path, &format!("{}..{}", local, remote))?;
+        }
+    }
+    Ok(branches)
+}
+
+/// start から branch を作る（jj ではブックマーク）。git でも切り替えはしない。
+pub fn create_branch(
+    path: &Path,
+    kind: RepositoryKind,
+    branch: &str,
+    start: &str,
+) -> Result<(), String> {
+    if kind.is_jj() {
+        jj(path, &["bookmark", "create", branch, "-r", start]).map(|_| ())
+    } else {
+        git(path, &["branch", branch, start]).map(|_| ())
+    }
+}
+
+pub fn rename_branch(
+    path: &Path,
+    kind: RepositoryKind,
+    from: &str,
+    to: &str,
+) -> Result<(), String> {
+    if kind.is_jj() {
+        jj(path, &["bookmark", "rename", from, to]).map(|_| ())
+    } else {
+        git(path, &["branch", "-m", from, to]).map(|_| ())
+    }
+}
+
+/// force が false なら、git では取り込まれていないブランチの削除は失敗する
+pub fn delete_branch(
+    path: &Path,
+    kind: RepositoryKind,
+    branch: &str,
+    force: bool,
+) -> Result<(), String> {
+    if kind.is_jj() {
+        return jj(path, &["bookmark", "delete", branch]).map(|_| ());
+    }
+    git(path, &["branch", if force { "-D" } else { "-d" }, branch]).map(|_| ())
+}
+
+/// upstream を設定する。None なら解除する。
+/// jj ではリモートのブックマーク（`main@origin`）を追跡する・やめることになる。
+pub fn set_upstream(
+    path: &Path,
+    kind: RepositoryKind,
+    branch: &BranchInfo,
+    upstream: Option<&str>,
+) -> Result<(), String> {
+    match (kind.is_jj(), upstream) {
+        (true, Some(upstream)) => jj(path, &["bookmark", "track", upstream]).map(|_| ()),
+        (true, None) => match &branch.upstream {
+            Some(upstream) => jj(path, &["bookmark", "untrack", upstream]).map(|_| ()),
+            None => Ok(()),
+        },
+        (false, Some(upstream)) => {
+            let upstream = format!("--set-upstream-to={}", upstream);
+            git(path, &["branch", &upstream, &branch.name]).map(|_| ())
+        }
+        (false, None) => git(path, &["branch", "--unset-upstream", &branch.name]).map(|_| ()),
+    }
+}
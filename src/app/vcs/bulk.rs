@@ -0,0 +1,224 @@
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::branch::{SwitchMode, switch_branch};
+use crate::app::vcs::remote::{RemoteEvent, RemoteOperation, RemoteOutcome, run_remote_operation};
+use crate::app::vcs::{git, jj};
+
+/// シェルのコマンドを待つ時間。これを過ぎたら止める。
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// 複数のリポジトリに同じように実行する操作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkOperation {
+    Fetch,
+    /// fast-forward できるときだけ進める
+    Pull,
+    /// そのブランチがあるリポジトリだけ切り替える
+    Switch(String),
+    /// 現在の位置にブランチを作る（git では切り替えもする）
+    CreateBranch(String),
+    /// リポジトリをカレントディレクトリにしてシェルで実行する
+    Command(String),
+}
+
+impl std::fmt::Display for BulkOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BulkOperation::Fetch => write!(f, "fetch"),
+            BulkOperation::Pull => write!(f, "pull (fast-forward)"),
+            BulkOperation::Switch(branch) => write!(f, "{} に切り替え", branch),
+            BulkOperation::CreateBranch(branch) => write!(f, "{} を作成", branch),
+            BulkOperation::Command(command) => write!(f, "$ {}", command),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkOutcome {
+    /// 出力の要約
+    Succeeded(String),
+    /// 対象外だったので何もしなかった理由
+    Skipped(String),
+    Failed(String),
+}
+
+impl BulkOutcome {
+    pub fn is_failed(&self) -> bool {
+        matches!(self, BulkOutcome::Failed(_))
+    }
+}
+
+pub fn run_bulk_operation(
+    path: &Path,
+    kind: RepositoryKind,
+    operation: &BulkOperation,
+) -> BulkOutcome {
+    let needs_work_tree = matches!(
+        operation,
+        BulkOperation::Pull | BulkOperation::Switch(_) | BulkOperation::CreateBranch(_)
+    );
+    if needs_work_tree && kind == RepositoryKind::Bare {
+        return BulkOutcome::Skipped("bare リポジトリです".to_owned());
+    }
+
+    let result = match operation {
+        BulkOperation::Fetch => remote(path, kind, RemoteOperation::Fetch),
+        BulkOperation::Pull if kind.is_jj() => {
+            return BulkOutcome::Skipped("jj には pull がありません".to_owned());
+        }
+        BulkOperation::Pull => remote(path, kind, RemoteOperation::Pull),
+        BulkOperation::Switch(branch) => {
+            match branch_exists(path, kind, branch) {
+                Ok(true) => (),
+                Ok(false) => return BulkOutcome::Skipped(format!("{} がありません", branch)),
+                Err(err) => return BulkOutcome::Failed(err),
+            }
+            let mode = if kind.is_jj() {
+                SwitchMode::JjNew
+            } else {
+                SwitchMode::Carry
+            };
+            switch_branch(path, kind, branch, mode).map(|_| String::new())
+        }
+        BulkOperation::CreateBranch(branch) if kind.is_jj() => {
+            jj(path, &["bookmark", "create", branch, "-r", "@"])
+        }
+        BulkOperation::CreateBranch(branch) => git(path, &["switch", "-c", branch]),
+        BulkOperation::Command(command) => shell(path, command),
+    };
+
+    match result {
+        Ok(output) => BulkOutcome::Succeeded(output.trim().to_owned()),
+        Err(err) => BulkOutcome::Failed(err),
+    }
+}
+
+fn branch_exists(path: &Path, kind: RepositoryKind, branch: &str) -> Result<bool, String> {
+    if kind.is_jj() {
+        let revset = format!(r#"bookmarks(exact:"{}")"#, branch);
+        let output = jj(
+            path,
+            &[
+                "log",
+                "--no-graph",
+                "-r",
+                &revset,
+                "-T",
+                r#"commit_id ++ "\n""#,
+            ],
+        )?;
+        return Ok(!output.trim().is_empty());
+    }
+
+    let name = format!("refs/heads/{}", branch);
+    Ok(git(path, &["show-ref", "--verify", "--quiet", &name]).is_ok())
+}
+
+/// カードの fetch / pull と同じように実行し、リモートごとの結果をまとめる。
+/// 一括操作では認証情報を聞けないので、認証が必要なリモートは失敗にする。
+fn remote(path: &Path, kind: RepositoryKind, operation: RemoteOperation) -> Result<String, String> {
+    let mut succeeded = vec![];
+    let mut failed = vec![];
+    run_remote_operation(path, kind, operation, None, |event| {
+        let RemoteEvent::Finished(result) = event else {
+            return;
+        };
+        match result.outcome {
+            RemoteOutcome::Succeeded(refs) => succeeded.extend(
+                refs.into_iter()
+                    .map(|x| format!("{}: {}", result.remote, x)),
+            ),
+            RemoteOutcome::Rejected(refs) => failed.extend(
+                refs.into_iter()
+                    .map(|x| format!("{}: {}", result.remote, x)),
+            ),
+            RemoteOutcome::AuthRequired(err) | RemoteOutcome::Failed(err) => {
+                failed.push(format!("{}: {}", result.remote, err))
+            }
+        }
+    });
+    if failed.is_empty() {
+        Ok(succeeded.join("\n"))
+    } else {
+        Err(failed.join("\n"))
+    }
+}
+
+/// 入力を待たないように標準入力を閉じて実行し、COMMAND_TIMEOUT を過ぎたら止める。
+/// 失敗したときは標準出力と標準エラー出力の両方を返す。
+fn shell(path: &Path, command: &str) -> Result<String, String> {
+    let mut shell = if cfg!(windows) {
+        let mut x = Command::new("cmd");
+        x.arg("/C");
+        x
+    } else {
+        let mut x = Command::new("sh");
+        x.arg("-c");
+        x
+    };
+    let mut child = shell
+        .arg(command)
+        .current_dir(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+
+    // パイプが詰まらないように、終わるのを待つ間も読み続ける
+    let read_all = |mut reader: Box<dyn Read + Send>| {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buffer = vec![];
+            let _ = reader.read_to_end(&mut buffer);
+            let _ = sender.send(String::from_utf8_lossy(&buffer).into_owned());
+        });
+        receiver
+    };
+    let stdout = child.stdout.take().map(|x| read_all(Box::new(x)));
+    let stderr = child.stderr.take().map(|x| read_all(Box::new(x)));
+
+    let deadline = Instant::now() + COMMAND_TIMEOUT;
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|err| err.to_string())? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!(
+                "{} 秒で終わらなかったので止めました",
+                COMMAND_TIMEOUT.as_secs()
+            ));
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+
+    // 子プロセスが残ってパイプを開いたままでも、いつまでも待たない
+    let collect = |x: Option<mpsc::Receiver<String>>| {
+        x.and_then(|x| x.recv_timeout(Duration::from_secs(1)).ok())
+            .unwrap_or_default()
+    };
+    let stdout = collect(stdout);
+    let stderr = collect(stderr);
+    if status.success() {
+        return Ok(stdout);
+    }
+
+    let output = [stdout.trim(), stderr.trim()]
+        .into_iter()
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Err(if output.is_empty() {
+        status.to_string()
+    } else {
+        format!("{}\n{}", status, output)
+    })
+}
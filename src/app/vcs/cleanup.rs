@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::branch::{default_branch, list_branches};
+use crate::app::vcs::history::HistoryEntry;
+use crate::app::vcs::{git, jj};
+
+/// 消してよさそうだと判断した理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleReason {
+    /// 既定ブランチに取り込み済み
+    Merged,
+    /// upstream がリモートで消えている
+    UpstreamGone,
+}
+
+impl std::fmt::Display for StaleReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StaleReason::Merged => write!(f, "取り込み済み"),
+            StaleReason::UpstreamGone => write!(f, "upstream 消失"),
+        }
+    }
+}
+
+/// 整理の候補になったローカルのブランチ
+#[derive(Debug, Clone)]
+pub struct StaleBranch {
+    pub name: String,
+    pub reasons: Vec<StaleReason>,
+    pub last_commit: Option<HistoryEntry>,
+}
+
+impl StaleBranch {
+    /// 取り込まれていないブランチは git では -D でないと消せない
+    pub fn needs_force(&self) -> bool {
+        !self.reasons.contains(&StaleReason::Merged)
+    }
+}
+
+/// local_branches（カードで読み込んだローカルブランチ）のうち、
+/// 既定ブランチに取り込み済みか upstream が消えているものを返す。
+/// 今いるブランチと既定ブランチそのものは除く。
+pub fn stale_branches(
+    path: &Path,
+    kind: RepositoryKind,
+    local_branches: &[String],
+) -> Result<Vec<StaleBranch>, String> {
+    let protected = protected_branches(path, kind)?;
+
+    Ok(list_branches(path, kind)?
+        .into_iter()
+        .filter(|x| x.remote.is_none() && !x.current && !protected.contains(&x.name))
+        .filter(|x| local_branches.contains(&x.name))
+        .filter_map(|x| {
+            let mut reasons = vec![];
+            if x.merged {
+                reasons.push(StaleReason::Merged);
+            }
+            if x.upstream_gone {
+                reasons.push(StaleReason::UpstreamGone);
+            }
+            (!reasons.is_empty()).then_some(StaleBranch {
+                name: x.name,
+                reasons,
+                last_commit: x.last_commit,
+            })
+        })
+        .collect())
+}
+
+/// 一つのリポジトリで削除するときに実行するコマンド（dry run で表示する）
+pub fn delete_commands(kind: RepositoryKind, branches: &[&StaleBranch]) -> Vec<String> {
+    delete_args(kind, branches)
+        .into_iter()
+        .map(|args| {
+            let program = if kind.is_jj() { "jj" } else { "git" };
+            format!("{} {}", program, args.join(" "))
+        })
+        .collect()
+}
+
+/// 一つのリポジトリのブランチをまとめて削除し、ブランチごとの結果を返す。
+/// ref の更新が重ならないように、コマンドは順に実行する。
+pub fn delete_stale_branches(
+    path: &Path,
+    kind: RepositoryKind,
+    branches: &[&StaleBranch],
+) -> Vec<(String, Result<(), String>)> {
+    let mut results = vec![];
+    for args in delete_args(kind, branches) {
+        // 先頭の二つはサブコマンドとオプション
+        let names = args[2..].iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        let output = if kind.is_jj() {
+            jj(path, &args)
+        } else {
+            git(path, &args)
+        };
+        match output {
+            Ok(_) => results.extend(names.into_iter().map(|x| (x, Ok(())))),
+            // git branch -d は消せるものは消して続けるので、エラーに名前が出たものと
+            // 残っているものだけを失敗にする
+            Err(err) => results.extend(names.into_iter().map(|x| {
+                let quoted = format!("'{}'", x);
+                let lines = err
+                    .lines()
+                    .filter(|line| line.contains(&quoted))
+                    .collect::<Vec<_>>();
+                let result = if !lines.is_empty() {
+                    Err(lines.join("\n"))
+                } else if branch_exists(path, kind, &x) {
+                    Err(err.clone())
+                } else {
+                    Ok(())
+                };
+                (x, result)
+            })),
+        }
+    }
+    results
+}
+
+/// git では取り込み済みのものを -d、それ以外を -D で消す
+fn delete_args<'a>(kind: RepositoryKind, branches: &[&'a StaleBranch]) -> Vec<Vec<&'a str>> {
+    let names = |force: bool| {
+        branches
+            .iter()
+            .filter(|x| kind.is_jj() || x.needs_force() == force)
+            .map(|x| x.name.as_str())
+            .collect::<Vec<_>>()
+    };
+
+    let groups = if kind.is_jj() {
+        vec![(["bookmark", "delete"], names(false))]
+    } else {
+        vec![
+            (["branch", "-d"], names(false)),
+            (["branch", "-D"], names(true)),
+        ]
+    };
+    groups
+        .into_iter()
+        .filter(|(_, names)| !names.is_empty())
+        .map(|(command, names)| command.into_iter().chain(names).collect())
+        .collect()
+}
+
+fn branch_exists(path: &Path, kind: RepositoryKind, branch: &str) -> bool {
+    if kind.is_jj() {
+        let revset = format!(r#"bookmarks(exact:"{}")"#, branch);
+        return jj(
+            path,
+            &["log", "--no-graph", "-r", &revset, "-T", "commit_id"],
+        )
+        .is_ok_and(|x| !x.trim().is_empty());
+    }
+    let name = format!("refs/heads/{}", branch);
+    git(path, &["show-ref", "--verify", "--quiet", &name]).is_ok()
+}
+
+/// 既定ブランチの名前（取り込み済みでも消さない）
+fn protected_branches(path: &Path, kind: RepositoryKind) -> Result<HashSet<String>, String> {
+    if kind.is_jj() {
+        let output = jj(
+            path,
+            &[
+                "log",
+                "--no-graph",
+                "-r",
+                "trunk()",
+                "-T",
+                concat!(
+                    r#"local_bookmarks.map(|b| b.name() ++ "\n").join("")"#,
+                    r#" ++ remote_bookmarks.map(|b| b.name() ++ "\n").join("")"#,
+                ),
+            ],
+        )?;
+        return Ok(output.lines().map(|x| x.to_owned()).collect());
+    }
+
+    // origin/main なら main
+    Ok(default_branch(path, kind)
+        .map(|x| match x.split_once('/') {
+            Some((_, name)) => name.to_owned(),
+            None => x,
+        })
+        .into_iter()
+        .collect())
+}
@@ -0,0 +1,236 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::diff::{DiffOptions, FileDiff, commit_diff};
+use crate::app::vcs::stage::{jj_ensure_staging, jj_has_staging, jj_verify_staging};
+use crate::app::vcs::{git, jj};
+
+/// author / committer
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub name: String,
+    pub email: String,
+    pub time: SystemTime,
+}
+
+/// コミットの詳細
+#[derive(Debug, Clone)]
+pub struct CommitDetail {
+    pub id: String,
+    pub parents: Vec<String>,
+    pub author: Signature,
+    pub committer: Signature,
+    pub message: String,
+    pub files: Vec<FileDiff>,
+}
+
+/// メッセージ全文・署名・親・変更ファイルの差分をまとめて取得する
+pub fn commit_detail(
+    path: &Path,
+    kind: RepositoryKind,
+    commit_id: &str,
+    options: &DiffOptions,
+) -> Result<CommitDetail, String> {
+    // メッセージは改行を含むので最後のフィールドにする
+    let output = if kind.is_jj() {
+        jj(
+            path,
+            &[
+                "log",
+                "--no-graph",
+                "-r",
+                commit_id,
+                "-T",
+                concat!(
+                    r#"commit_id ++ "\0" ++ parents.map(|c| c.commit_id()).join(" ")"#,
+                    r#" ++ "\0" ++ author.name() ++ "\0" ++ author.email()"#,
+                    r#" ++ "\0" ++ author.timestamp().format("%s")"#,
+                    r#" ++ "\0" ++ committer.name() ++ "\0" ++ committer.email()"#,
+                    r#" ++ "\0" ++ committer.timestamp().format("%s")"#,
+                    r#" ++ "\0" ++ description"#,
+                ),
+            ],
+        )?
+    } else {
+        git(
+            path,
+            &[
+                "show",
+                "-s",
+                "--format=%H%x00%P%x00%an%x00%ae%x00%at%x00%cn%x00%ce%x00%ct%x00%B",
+                commit_id,
+            ],
+        )?
+    };
+
+    let fields = output.splitn(9, '\0').collect::<Vec<_>>();
+    let [
+        id,
+        parents,
+        author_name,
+        author_email,
+        author_time,
+        name,
+        email,
+        time,
+        message,
+    ] = fields[..]
+    else {
+        return Err(format!("コミット {} の情報を解釈できません", commit_id));
+    };
+
+    let parents = parents
+        .split_whitespace()
+        .map(|x| x.to_owned())
+        .collect::<Vec<_>>();
+    let files = commit_diff(path, kind, id, &parents, options)?;
+
+    Ok(CommitDetail {
+        id: id.trim().to_owned(),
+        parents,
+        author: signature(author_name, author_email, author_time),
+        committer: signature(name, email, time),
+        message: message.trim_end().to_owned(),
+        files,
+    })
+}
+
+fn signature(name: &str, email: &str, seconds: &str) -> Signature {
+    Signature {
+        name: name.to_owned(),
+        email: email.to_owned(),
+        time: UNIX_EPOCH + Duration::from_secs(seconds.trim().parse().unwrap_or_default()),
+    }
+}
+
+/// コミットの作り方
+#[derive(Debug, Clone, Default)]
+pub struct CommitOptions {
+    /// 直前のコミットを書き換える（jj ではステージ用の change の変更をその親に移す）
+    pub amend: bool,
+    /// `Signed-off-by:` を付ける
+    pub sign_off: bool,
+    /// `Name <email>` 形式の作者。None なら設定どおり。
+    pub author: Option<String>,
+}
+
+/// jj で直前のコミットにあたるリビジョン。
+/// @- がステージ用の change ならその親、そうでなければ @- そのもの。
+fn jj_last_commit(path: &Path) -> Result<&'static str, String> {
+    Ok(if jj_has_staging(path)? { "@--" } else { "@-" })
+}
+
+/// amend の対象になるコミットのメッセージ
+pub fn last_commit_message(path: &Path, kind: RepositoryKind) -> Result<String, String> {
+    let message = if kind.is_jj() {
+        jj(
+            path,
+            &[
+                "log",
+                "-r",
+                jj_last_commit(path)?,
+                "--no-graph",
+                "-T",
+                "description",
+            ],
+        )?
+    } else {
+        git(path, &["log", "-1", "--format=%B"])?
+    };
+    Ok(message.trim_end().to_owned())
+}
+
+/// 最後のコミットの作者名（jj では amend の対象と同じリビジョン）
+pub fn last_commit_author(path: &Path, kind: RepositoryKind) -> Result<String, String> {
+    let author = if kind.is_jj() {
+        jj(
+            path,
+            &[
+                "log",
+                "-r",
+                jj_last_commit(path)?,
+                "--no-graph",
+                "-T",
+                "author.name()",
+            ],
+        )?
+    } else {
+        git(path, &["log", "-1", "--format=%an"])?
+    };
+    Ok(author.trim().to_owned())
+}
+
+/// コミットを作る。
+/// jj ではステージ用の change (@-) を index の代わりにしているので、amend でなければ
+/// それに説明を付けてコミットにし、@ との間に新しいステージ用の change を差し込む。
+/// amend ならステージした変更を直前のコミットに移して説明を書き換える。
+pub fn create_commit(
+    path: &Path,
+    kind: RepositoryKind,
+    message: &str,
+    options: &CommitOptions,
+) -> Result<(), String> {
+    if !kind.is_jj() {
+        let mut args = vec!["commit", "-m", message];
+        if options.amend {
+            args.push("--amend");
+        }
+        if options.sign_off {
+            args.push("--signoff");
+        }
+        let author = options.author.as_ref().map(|x| format!("--author={}", x));
+        if let Some(author) = &author {
+            args.push(author);
+        }
+        return git(path, &args).map(|_| ());
+    }
+
+    // jj には --signoff が無いので trailer を自分で付ける
+    let message = if options.sign_off {
+        let name = jj(path, &["config", "get", "user.name"])?;
+        let email = jj(path, &["config", "get", "user.email"])?;
+        format!(
+            "{}\n\nSigned-off-by: {} <{}>",
+            message.trim_end(),
+            name.trim(),
+            email.trim()
+        )
+    } else {
+        message.to_owned()
+    };
+
+    let revision = if !options.amend {
+        // 普通のコミットに説明を付け直さないよう、@- がステージ用の change か確かめる
+        jj_verify_staging(path)?;
+        "@-"
+    } else if jj_has_staging(path)? {
+        // 空になったステージ用の change は次の index として残す
+        jj(
+            path,
+            &[
+                "squash",
+                "--from",
+                "@-",
+                "--into",
+                "@--",
+                "--use-destination-message",
+                "--keep-emptied",
+            ],
+        )?;
+        "@--"
+    } else {
+        // ステージ用の change が無ければ、@- そのものが直前のコミット
+        "@-"
+    };
+    let mut args = vec!["describe", revision, "-m", &message];
+    if let Some(author) = &options.author {
+        args.extend(["--author", author.as_str()]);
+    }
+    jj(path, &args)?;
+    if !options.amend {
+        // 作業コピー (@) は動かさず、説明を付けた @- との間に新しい index を作る
+        jj_ensure_staging(path)?;
+    }
+    Ok(())
+}
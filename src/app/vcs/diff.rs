@@ -0,0 +1,290 @@
+use std::path::Path;
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::{git, jj};
+
+/// 差分の一行の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// 差分の一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    pub old_lineno: Option<usize>,
+    pub new_lineno: Option<usize>,
+    /// 先頭の +/-/空白 を除いた内容
+    pub content: String,
+    /// 直後に `\ No newline at end of file` が続く（ファイル末尾に改行が無い）
+    pub missing_newline: bool,
+}
+
+/// `@@ -a,b +c,d @@` で始まるひとかたまり
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub header: String,
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+/// 一ファイル分の差分
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    /// 追加されたファイルでは None
+    pub old_path: Option<String>,
+    /// 削除されたファイルでは None
+    pub new_path: Option<String>,
+    /// `index` 行の blob ID（追加・削除されたファイルでは片方が 0 埋め）
+    pub old_blob: Option<String>,
+    pub new_blob: Option<String>,
+    pub binary: bool,
+    /// `diff --git` から最初のハンクまでのヘッダ行（パッチの組み立てに使う）
+    pub header: Vec<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FileDiff {
+    /// 表示用のパス（rename は `old → new`）
+    pub fn display_path(&self) -> String {
+        match (&self.old_path, &self.new_path) {
+            (Some(old), Some(new)) if old != new => format!("{} → {}", old, new),
+            (_, Some(new)) => new.to_owned(),
+            (Some(old), None) => old.to_owned(),
+            (None, None) => String::new(),
+        }
+    }
+
+    pub fn additions(&self) -> usize {
+        self.count(LineKind::Added)
+    }
+
+    pub fn deletions(&self) -> usize {
+        self.count(LineKind::Removed)
+    }
+
+    fn count(&self, kind: LineKind) -> usize {
+        self.hunks
+            .iter()
+            .flat_map(|x| x.lines.iter())
+            .filter(|x| x.kind == kind)
+            .count()
+    }
+}
+
+/// 差分の取得方法
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// 空白だけの変更を無視する
+    pub ignore_whitespace: bool,
+    /// 変更行の前後に含める行数
+    pub context_lines: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            ignore_whitespace: false,
+            context_lines: 3,
+        }
+    }
+}
+
+/// コミットの差分（マージコミットは最初の親との差分）
+pub fn commit_diff(
+    path: &Path,
+    kind: RepositoryKind,
+    commit_id: &str,
+    parents: &[String],
+    options: &DiffOptions,
+) -> Result<Vec<FileDiff>, String> {
+    if kind.is_jj() {
+        return jj_diff(path, commit_id, options);
+    }
+
+    let mut args = vec!["diff-tree", "-r", "-p", "-M"];
+    match parents.first() {
+        Some(parent) => args.push(parent),
+        None => args.push("--root"),
+    }
+    args.push(commit_id);
+    git_diff(path, &args, options)
+}
+
+/// jj のリビジョンの差分
+pub fn jj_diff(
+    path: &Path,
+    revision: &str,
+    options: &DiffOptions,
+) -> Result<Vec<FileDiff>, String> {
+    let context = options.context_lines.to_string();
+    let mut args = vec![
+        "diff",
+        "--git",
+        "--context",
+        context.as_str(),
+        "-r",
+        revision,
+    ];
+    if options.ignore_whitespace {
+        args.push("--ignore-all-space");
+    }
+    jj(path, &args).map(|x| parse_diff(&x))
+}
+
+/// git の差分を出すコマンドを実行する。args はサブコマンドとその引数で、
+/// 表示のオプションはサブコマンドの直後に差し込む。
+pub fn git_diff(
+    path: &Path,
+    args: &[&str],
+    options: &DiffOptions,
+) -> Result<Vec<FileDiff>, String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(vec![]);
+    };
+    let unified = format!("-U{}", options.context_lines);
+    let mut all = vec![*command, "--no-color", "--full-index", unified.as_str()];
+    if options.ignore_whitespace {
+        all.push("-w");
+    }
+    all.extend(rest);
+    git(path, &all).map(|x| parse_diff(&x))
+}
+
+/// `git diff` 形式の出力を解釈する
+pub fn parse_diff(text: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = vec![];
+    let mut old_lineno = 0;
+    let mut new_lineno = 0;
+
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let (old, new) = split_diff_git_paths(rest);
+            files.push(FileDiff {
+                old_path: Some(old),
+                new_path: Some(new),
+                old_blob: None,
+                new_blob: None,
+                binary: false,
+                header: vec![line.to_owned()],
+                hunks: vec![],
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if file.hunks.is_empty() && !line.starts_with("@@") {
+            // ヘッダ部分
+            if line.starts_with("new file mode") {
+                file.old_path = None;
+            } else if line.starts_with("deleted file mode") {
+                file.new_path = None;
+            } else if let Some(old) = line.strip_prefix("rename from ") {
+                file.old_path = Some(old.to_owned());
+            } else if let Some(new) = line.strip_prefix("rename to ") {
+                file.new_path = Some(new.to_owned());
+            } else if let Some(index) = line.strip_prefix("index ")
+                && let Some((old, new)) = index
+                    .split_whitespace()
+                    .next()
+                    .and_then(|x| x.split_once(".."))
+            {
+                file.old_blob = Some(old.to_owned());
+                file.new_blob = Some(new.to_owned());
+            } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                file.binary = true;
+            }
+            file.header.push(line.to_owned());
+            continue;
+        }
+
+        if line.starts_with("@@") {
+            let Some(hunk) = parse_hunk_header(line) else {
+                continue;
+            };
+            old_lineno = hunk.old_start;
+            new_lineno = hunk.new_start;
+            file.hunks.push(hunk);
+            continue;
+        }
+
+        let Some(hunk) = file.hunks.last_mut() else {
+            continue;
+        };
+        let (kind, content) = match line.chars().next() {
+            Some('+') => (LineKind::Added, &line[1..]),
+            Some('-') => (LineKind::Removed, &line[1..]),
+            Some(' ') => (LineKind::Context, &line[1..]),
+            Some('\\') => {
+                if let Some(last) = hunk.lines.last_mut() {
+                    last.missing_newline = true;
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let (old, new) = match kind {
+            LineKind::Added => (None, Some(new_lineno)),
+            LineKind::Removed => (Some(old_lineno), None),
+            LineKind::Context => (Some(old_lineno), Some(new_lineno)),
+        };
+        if old.is_some() {
+            old_lineno += 1;
+        }
+        if new.is_some() {
+            new_lineno += 1;
+        }
+        hunk.lines.push(DiffLine {
+            kind,
+            old_lineno: old,
+            new_lineno: new,
+            content: content.to_owned(),
+            missing_newline: false,
+        });
+    }
+
+    files
+}
+
+/// `@@ -a,b +c,d @@ ...` を解釈する（行数の省略は 1 とみなす）
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+    let mut parts = line.split_whitespace().skip(1);
+    let (old_start, old_lines) = parse_range(parts.next()?.strip_prefix('-')?)?;
+    let (new_start, new_lines) = parse_range(parts.next()?.strip_prefix('+')?)?;
+
+    Some(Hunk {
+        header: line.to_owned(),
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        lines: vec![],
+    })
+}
+
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, lines)) => Some((start.parse().ok()?, lines.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// `a/foo b/foo` を分ける。パスに空白を含む場合は rename 行で上書きされる。
+fn split_diff_git_paths(rest: &str) -> (String, String) {
+    let rest = rest.trim_matches('"');
+    match rest.split_once(" b/") {
+        Some((old, new)) => (
+            old.trim_start_matches("a/").to_owned(),
+            new.trim_matches('"').to_owned(),
+        ),
+        None => (rest.to_owned(), rest.to_owned()),
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::{git, jj};
+
+/// コミットに付いている ref の表示用ラベル
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefLabel {
+    Head,
+    Branch(String),
+    RemoteBranch(String),
+    Tag(String),
+}
+
+/// グラフ描画用のコミット
+#[derive(Debug, Clone)]
+pub struct GraphCommit {
+    pub id: String,
+    pub parents: Vec<String>,
+    pub refs: Vec<RefLabel>,
+    pub summary: String,
+}
+
+/// ブランチ・タグ・HEAD から辿れるコミットを、子が親より先に来る順で最大 limit 件返す
+pub fn graph_commits(
+    path: &Path,
+    kind: RepositoryKind,
+    limit: usize,
+) -> Result<Vec<GraphCommit>, String> {
+    let output = if kind.is_jj() {
+        let revset = format!(
+            "latest(::(@ | bookmarks() | remote_bookmarks() | tags()), {})",
+            limit
+        );
+        jj(
+            path,
+            &[
+                "log",
+                "--no-graph",
+                "-r",
+                &revset,
+                "-T",
+                concat!(
+                    r#"commit_id ++ "\t""#,
+                    r#" ++ parents.map(|c| c.commit_id()).join(" ") ++ "\t""#,
+                    r#" ++ separate(", ","#,
+                    r#"   if(current_working_copy, "HEAD"),"#,
+                    r#"   local_bookmarks.map(|b| "refs/heads/" ++ b.name()).join(", "),"#,
+                    r#"   remote_bookmarks.map(|b| "refs/remotes/" ++ b.remote() ++ "/" ++ b.name()).join(", "),"#,
+                    r#"   tags.map(|t| "tag: refs/tags/" ++ t.name()).join(", "))"#,
+                    r#" ++ "\t" ++ description.first_line() ++ "\n""#,
+                ),
+            ],
+        )?
+    } else {
+        let limit = limit.to_string();
+        git(
+            path,
+            &[
+                "log",
+                "--branches",
+                "--remotes",
+                "--tags",
+                "HEAD",
+                "--topo-order",
+                "--decorate=full",
+                "-n",
+                &limit,
+                "--format=%H%x09%P%x09%D%x09%s",
+            ],
+        )?
+    };
+
+    Ok(output.lines().filter_map(parse_commit).collect())
+}
+
+/// `id \t parents \t refs \t summary` の一行を解釈する
+fn parse_commit(line: &str) -> Option<GraphCommit> {
+    let mut fields = line.splitn(4, '\t');
+    let id = fields.next()?.trim().to_owned();
+    if id.is_empty() {
+        return None;
+    }
+    let parents = fields
+        .next()?
+        .split_whitespace()
+        .map(|x| x.to_owned())
+        .collect();
+    let refs = fields.next()?.split(", ").flat_map(parse_refs).collect();
+    let summary = fields.next().unwrap_or_default().to_owned();
+
+    Some(GraphCommit {
+        id,
+        parents,
+        refs,
+        summary,
+    })
+}
+
+/// `git log --decorate=full` の %D の一要素を解釈する
+fn parse_refs(decoration: &str) -> Vec<RefLabel> {
+    let decoration = decoration.trim();
+    if let Some(branch) = decoration.strip_prefix("HEAD -> ") {
+        let mut labels = vec![RefLabel::Head];
+        labels.extend(parse_refs(branch));
+        return labels;
+    }
+    if decoration == "HEAD" {
+        return vec![RefLabel::Head];
+    }
+    if let Some(tag) = decoration.strip_prefix("tag: refs/tags/") {
+        return vec![RefLabel::Tag(tag.to_owned())];
+    }
+    if let Some(branch) = decoration.strip_prefix("refs/heads/") {
+        return vec![RefLabel::Branch(branch.to_owned())];
+    }
+    if let Some(branch) = decoration.strip_prefix("refs/remotes/") {
+        // origin/HEAD や jj の git リモートは表示しない
+        if branch.ends_with("/HEAD") || branch.starts_with("git/") {
+            return vec![];
+        }
+        return vec![RefLabel::RemoteBranch(branch.to_owned())];
+    }
+    vec![]
+}
@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::{git, jj};
+
+/// 履歴一覧の一行
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub commit_id: String,
+    pub summary: String,
+    pub author: String,
+    pub timestamp: SystemTime,
+}
+
+/// 履歴の続きを読む位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryCursor {
+    /// git: 読み込み済みの件数（--skip に渡す）
+    Skip(usize),
+    /// jj: まだ読んでいない履歴の先頭（読み込んだコミットの親のうち未読のもの）
+    Heads(Vec<String>),
+}
+
+/// 履歴の一ページ
+#[derive(Debug, Clone)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// 続きを読む位置。最後まで読んだら None。
+    pub next: Option<HistoryCursor>,
+}
+
+/// HEAD（jj では @）から辿った履歴を cursor の位置から最大 limit 件返す。
+/// cursor が None なら先頭から読む。
+pub fn history_page(
+    path: &Path,
+    kind: RepositoryKind,
+    cursor: Option<&HistoryCursor>,
+    limit: usize,
+) -> Result<HistoryPage, String> {
+    if kind.is_jj() {
+        let heads = match cursor {
+            Some(HistoryCursor::Heads(heads)) => heads.as_slice(),
+            _ => &[],
+        };
+        return jj_history_page(path, heads, limit);
+    }
+
+    let offset = match cursor {
+        Some(HistoryCursor::Skip(offset)) => *offset,
+        _ => 0,
+    };
+    let skip = format!("--skip={}", offset);
+    let count = limit.to_string();
+    let output = git(
+        path,
+        &[
+            "log",
+            &skip,
+            "-n",
+            &count,
+            "--format=%H%x09%an%x09%at%x09%s",
+            "HEAD",
+        ],
+    )?;
+
+    let entries = output.lines().filter_map(parse_entry).collect::<Vec<_>>();
+    let next = (entries.len() >= limit).then(|| HistoryCursor::Skip(offset + entries.len()));
+    Ok(HistoryPage { entries, next })
+}
+
+/// jj log には skip が無いので、読み込んだコミットの未読の親から続きを辿る。
+/// 子は必ず親より前に並ぶので、未読の親の祖先はすべて未読になる。
+fn jj_history_page(path: &Path, heads: &[String], limit: usize) -> Result<HistoryPage, String> {
+    let revset = if heads.is_empty() {
+        "::@".to_owned()
+    } else {
+        format!("::({})", heads.join(" | "))
+    };
+    let count = limit.to_string();
+    let output = jj(
+        path,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            &revset,
+            "--limit",
+            &count,
+            "-T",
+            concat!(
+                r#"parents.map(|c| c.commit_id()).join(",")"#,
+                r#" ++ "\t" ++ commit_id ++ "\t" ++ author.name()"#,
+                r#" ++ "\t" ++ author.timestamp().format("%s")"#,
+                r#" ++ "\t" ++ description.first_line() ++ "\n""#,
+            ),
+        ],
+    )?;
+
+    let mut entries = vec![];
+    let mut parents = vec![];
+    for line in output.lines() {
+        let Some((line_parents, rest)) = line.split_once('\t') else {
+            continue;
+        };
+        if let Some(entry) = parse_entry(rest) {
+            parents.extend(line_parents.split(',').filter(|x| !x.is_empty()));
+            entries.push(entry);
+        }
+    }
+
+    let loaded = entries
+        .iter()
+        .map(|x| x.commit_id.as_str())
+        .collect::<HashSet<_>>();
+    let mut next_heads = vec![];
+    for id in heads.iter().map(|x| x.as_str()).chain(parents) {
+        if !loaded.contains(id) && !next_heads.iter().any(|x| x == id) {
+            next_heads.push(id.to_owned());
+        }
+    }
+    let next = (entries.len() >= limit && !next_heads.is_empty())
+        .then_some(HistoryCursor::Heads(next_heads));
+    Ok(HistoryPage { entries, next })
+}
+
+/// `id \t author \t unix time \t summary` の一行を解釈する
+pub fn parse_entry(line: &str) -> Option<HistoryEntry> {
+    let mut fields = line.splitn(4, '\t');
+    let commit_id = fields.next()?.to_owned();
+    let author = fields.next()?.to_owned();
+    let seconds = fields.next()?.trim().parse::<u64>().ok()?;
+    let summary = fields.next().unwrap_or_default().to_owned();
+
+    Some(HistoryEntry {
+        commit_id,
+        summary,
+        author,
+        timestamp: UNIX_EPOCH + Duration::from_secs(seconds),
+    })
+}
@@ -0,0 +1,436 @@
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::{git, jj};
+
+/// 認証情報を渡すための環境変数
+const USERNAME_ENV: &str = "SKJRA_GIT_USERNAME";
+const PASSWORD_ENV: &str = "SKJRA_GIT_PASSWORD";
+
+/// 環境変数から認証情報を返す credential helper（パスワードをコマンドラインに載せない）
+const CREDENTIAL_HELPER: &str = concat!(
+    "credential.helper=!f() { test \"$1\" = get || exit 0; ",
+    "echo \"username=$SKJRA_GIT_USERNAME\"; echo \"password=$SKJRA_GIT_PASSWORD\"; }; f"
+);
+
+/// 認証が必要だったことを示す git のエラーメッセージ
+const AUTH_ERRORS: [&str; 4] = [
+    "terminal prompts disabled",
+    "could not read Username",
+    "could not read Password",
+    "Authentication failed",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteOperation {
+    Fetch,
+    Pull,
+    Push,
+}
+
+impl std::fmt::Display for RemoteOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            RemoteOperation::Fetch => "fetch",
+            RemoteOperation::Pull => "pull",
+            RemoteOperation::Push => "push",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// HTTP(S) のリモートに渡すユーザー名とパスワード（jj には渡せない）
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // パスワードをログに出さない
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 実行中の途中経過と、リモートごとの結果
+#[derive(Debug, Clone)]
+pub enum RemoteEvent {
+    Started(String),
+    Progress { line: String, percent: Option<u8> },
+    Finished(RemoteResult),
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteResult {
+    pub remote: String,
+    pub outcome: RemoteOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteOutcome {
+    /// 更新された ref の一覧など（何も無ければ空）
+    Succeeded(Vec<String>),
+    /// push で拒否された ref と理由
+    Rejected(Vec<String>),
+    /// ユーザー名・パスワードが必要
+    AuthRequired(String),
+    Failed(String),
+}
+
+/// リモートの名前と URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteInfo {
+    pub name: String,
+    pub url: String,
+}
+
+pub fn list_remotes(path: &Path, kind: RepositoryKind) -> Result<Vec<RemoteInfo>, String> {
+    let output = if kind.is_jj() {
+        jj(path, &["git", "remote", "list"])?
+    } else {
+        git(path, &["remote", "-v"])?
+    };
+
+    let mut remotes = Vec::<RemoteInfo>::new();
+    for line in output.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(url)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        // git remote -v は fetch と push の二行ずつ出す
+        if !remotes.iter().any(|x| x.name == name) {
+            remotes.push(RemoteInfo {
+                name: name.to_owned(),
+                url: url.to_owned(),
+            });
+        }
+    }
+    Ok(remotes)
+}
+
+/// fetch / pull / push を実行し、途中経過と結果を on_event に渡す。
+/// fetch はすべてのリモート、pull と push は現在のブランチの upstream（無ければ origin）が対象。
+/// jj には pull が無いので、fetch として扱う。
+pub fn run_remote_operation(
+    path: &Path,
+    kind: RepositoryKind,
+    operation: RemoteOperation,
+    credentials: Option<&Credentials>,
+    mut on_event: impl FnMut(RemoteEvent),
+) {
+    let targets = match remote_targets(path, kind, operation) {
+        Ok(x) => x,
+        Err(err) => {
+            on_event(RemoteEvent::Finished(RemoteResult {
+                remote: "-".to_owned(),
+                outcome: RemoteOutcome::Failed(err),
+            }));
+            return;
+        }
+    };
+    if targets.is_empty() {
+        on_event(RemoteEvent::Finished(RemoteResult {
+            remote: "-".to_owned(),
+            outcome: RemoteOutcome::Failed("リモートがありません".to_owned()),
+        }));
+        return;
+    }
+
+    for (remote, args) in targets {
+        on_event(RemoteEvent::Started(remote.clone()));
+        let mut command = if kind.is_jj() {
+            jj_command(path, &args)
+        } else {
+            git_command(path, &args, credentials)
+        };
+        let outcome = match run_streaming(&mut command, &mut on_event) {
+            Ok(output) => outcome(kind, operation, output),
+            Err(err) => RemoteOutcome::Failed(err),
+        };
+        on_event(RemoteEvent::Finished(RemoteResult { remote, outcome }));
+    }
+}
+
+/// 対象のリモートと、それぞれに渡す引数
+fn remote_targets(
+    path: &Path,
+    kind: RepositoryKind,
+    operation: RemoteOperation,
+) -> Result<Vec<(String, Vec<String>)>, String> {
+    let args = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+    if kind.is_jj() {
+        return match operation {
+            RemoteOperation::Fetch | RemoteOperation::Pull => Ok(jj_remotes(path)?
+                .into_iter()
+                .map(|x| {
+                    let a = args(&["git", "fetch", "--remote", &x]);
+                    (x, a)
+                })
+                .collect()),
+            RemoteOperation::Push => {
+                let remote = jj(path, &["config", "get", "git.push"])
+                    .map(|x| x.trim().to_owned())
+                    .unwrap_or_else(|_| "origin".to_owned());
+                let a = args(&["git", "push", "--remote", &remote]);
+                Ok(vec![(remote, a)])
+            }
+        };
+    }
+
+    if operation == RemoteOperation::Fetch {
+        return Ok(git_remotes(path)?
+            .into_iter()
+            .map(|x| {
+                let a = args(&["fetch", "--progress", "--prune", &x]);
+                (x, a)
+            })
+            .collect());
+    }
+
+    let branch = git(path, &["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .map_err(|_| "ブランチ上にいません".to_owned())?;
+    let branch = branch.trim();
+    let upstream_remote = git(
+        path,
+        &["config", "--get", &format!("branch.{}.remote", branch)],
+    )
+    .map(|x| x.trim().to_owned())
+    .ok();
+
+    match (operation, upstream_remote) {
+        (RemoteOperation::Pull, Some(remote)) => Ok(vec![(
+            remote,
+            // マージコミットを作らず、進められるときだけ進める
+            args(&["pull", "--progress", "--ff-only"]),
+        )]),
+        (RemoteOperation::Pull, None) => Err(format!("{} に upstream がありません", branch)),
+        (_, Some(remote)) => Ok(vec![(remote, args(&["push", "--progress", "--porcelain"]))]),
+        (_, None) => {
+            // upstream が無ければ origin（無ければ最初のリモート）に作る
+            let remotes = git_remotes(path)?;
+            let Some(remote) = remotes
+                .iter()
+                .find(|x| *x == "origin")
+                .or(remotes.first())
+                .cloned()
+            else {
+                return Ok(vec![]);
+            };
+            let a = args(&[
+                "push",
+                "--progress",
+                "--porcelain",
+                "--set-upstream",
+                &remote,
+                "HEAD",
+            ]);
+            Ok(vec![(remote, a)])
+        }
+    }
+}
+
+fn git_remotes(path: &Path) -> Result<Vec<String>, String> {
+    Ok(git(path, &["remote"])?
+        .lines()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_owned())
+        .collect())
+}
+
+/// `jj git remote list` は「名前 URL」の形で一行ずつ出す
+fn jj_remotes(path: &Path) -> Result<Vec<String>, String> {
+    Ok(jj(path, &["git", "remote", "list"])?
+        .lines()
+        .filter_map(|x| x.split_whitespace().next())
+        .map(|x| x.to_owned())
+        .collect())
+}
+
+fn git_command(path: &Path, args: &[String], credentials: Option<&Credentials>) -> Command {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(path).env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(credentials) = credentials {
+        // 既存の helper を外してから、環境変数を読む helper を足す
+        command
+            .args(["-c", "credential.helper=", "-c", CREDENTIAL_HELPER])
+            .env(USERNAME_ENV, &credentials.username)
+            .env(PASSWORD_ENV, &credentials.password);
+    }
+    command.args(args);
+    command
+}
+
+fn jj_command(path: &Path, args: &[String]) -> Command {
+    let mut command = Command::new("jj");
+    command
+        .arg("-R")
+        .arg(path)
+        .args(["--no-pager", "--color=never"])
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0");
+    command
+}
+
+/// 実行結果。stderr は途中経過を除いた行だけを持つ。
+struct StreamedOutput {
+    success: bool,
+    stdout: String,
+    stderr: Vec<String>,
+}
+
+/// stderr の途中経過を一行ずつ on_event に流しながら実行する
+fn run_streaming(
+    command: &mut Command,
+    on_event: &mut impl FnMut(RemoteEvent),
+) -> Result<StreamedOutput, String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    // stdout を読まずに待つと、パイプが詰まって止まることがある
+    let stdout = thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = stdout.read_to_end(&mut buffer);
+        String::from_utf8_lossy(&buffer).into_owned()
+    });
+
+    let mut lines = vec![];
+    let mut line = Vec::new();
+    for byte in BufReader::new(stderr).bytes() {
+        let byte = byte.map_err(|err| err.to_string())?;
+        if byte != b'\r' && byte != b'\n' {
+            line.push(byte);
+            continue;
+        }
+
+        let text = String::from_utf8_lossy(&line).trim().to_owned();
+        line.clear();
+        if text.is_empty() {
+            continue;
+        }
+        // \r で終わる行や割合を含む行は途中経過
+        match percent(&text) {
+            Some(percent) => on_event(RemoteEvent::Progress {
+                line: text,
+                percent: Some(percent),
+            }),
+            None if byte == b'\r' => on_event(RemoteEvent::Progress {
+                line: text,
+                percent: None,
+            }),
+            None => {
+                on_event(RemoteEvent::Progress {
+                    line: text.clone(),
+                    percent: None,
+                });
+                lines.push(text);
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(String::from_utf8_lossy(&line).trim().to_owned());
+    }
+
+    let status = child.wait().map_err(|err| err.to_string())?;
+    Ok(StreamedOutput {
+        success: status.success(),
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: lines,
+    })
+}
+
+/// `Receiving objects:  42% (21/50)` の 42
+fn percent(line: &str) -> Option<u8> {
+    let end = line.find('%')?;
+    let start = line[..end]
+        .rfind(|x: char| !x.is_ascii_digit())
+        .map_or(0, |x| x + 1);
+    line[start..end].parse().ok()
+}
+
+fn outcome(
+    kind: RepositoryKind,
+    operation: RemoteOperation,
+    output: StreamedOutput,
+) -> RemoteOutcome {
+    let stderr = output.stderr.join("\n");
+    if !kind.is_jj() && AUTH_ERRORS.iter().any(|x| stderr.contains(x)) {
+        return RemoteOutcome::AuthRequired(stderr);
+    }
+
+    if operation == RemoteOperation::Push {
+        let rejected = if kind.is_jj() {
+            jj_rejected(&output.stderr)
+        } else {
+            git_rejected(&output.stdout)
+        };
+        if !rejected.is_empty() {
+            return RemoteOutcome::Rejected(rejected);
+        }
+    }
+
+    if !output.success {
+        return RemoteOutcome::Failed(stderr);
+    }
+
+    let updates = if operation == RemoteOperation::Push && !kind.is_jj() {
+        git_pushed(&output.stdout)
+    } else {
+        output
+            .stderr
+            .into_iter()
+            // ref の更新行だけを残す（"From ..." や "To ..." は除く）
+            .filter(|x| x.contains("->") || x.starts_with("Fast-forward") || x.contains("bookmark"))
+            .collect()
+    };
+    RemoteOutcome::Succeeded(updates)
+}
+
+/// `git push --porcelain` の ref ごとの行（`<flag>\t<from>:<to>\t<summary>`）
+fn porcelain_refs(stdout: &str) -> impl Iterator<Item = (char, &str, &str)> {
+    stdout.lines().filter_map(|line| {
+        let mut fields = line.split('\t');
+        let flag = fields.next()?.chars().next()?;
+        let refs = fields.next()?;
+        let summary = fields.next()?;
+        let to = refs.split_once(':').map_or(refs, |(_, to)| to);
+        Some((flag, to.trim_start_matches("refs/heads/"), summary))
+    })
+}
+
+fn git_rejected(stdout: &str) -> Vec<String> {
+    porcelain_refs(stdout)
+        .filter(|(flag, _, _)| *flag == '!')
+        .map(|(_, to, summary)| format!("{} {}", to, summary))
+        .collect()
+}
+
+fn git_pushed(stdout: &str) -> Vec<String> {
+    porcelain_refs(stdout)
+        // '=' は更新なし
+        .filter(|(flag, _, _)| *flag != '=' && *flag != '!')
+        .map(|(_, to, summary)| format!("{} {}", to, summary))
+        .collect()
+}
+
+/// jj は拒否された bookmark を stderr に出す
+fn jj_rejected(stderr: &[String]) -> Vec<String> {
+    stderr
+        .iter()
+        .filter(|x| x.to_lowercase().contains("rejected"))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,609 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::diff::{DiffOptions, FileDiff, LineKind, git_diff, jj_diff};
+use crate::app::vcs::{git, git_with_input, jj, jj_bytes};
+
+/// 選択した行（ハンク番号, ハンク内の行番号）
+pub type LineSelection = BTreeSet<(usize, usize)>;
+
+/// jj で index の代わりにする change の説明。
+/// この説明の change が @- にあるときだけ、@- をステージ先として扱う。
+pub const JJ_STAGING_DESCRIPTION: &str = "skjra: staging";
+
+/// 作業ツリーの変更。
+/// jj には index が無いので、@ と親の間にステージ用の change を作って index の代わりにする
+/// （`jj squash` で @ から変更を移す）。
+#[derive(Debug, Clone, Default)]
+pub struct WorkingTreeChanges {
+    /// git: index と HEAD の差分 / jj: ステージ用の change (@-) の差分。無ければ空。
+    pub staged: Vec<FileDiff>,
+    /// git: 作業ツリーと index の差分 / jj: @ の差分
+    pub unstaged: Vec<FileDiff>,
+    /// 未追跡のファイル（jj では常に空）
+    pub untracked: Vec<String>,
+}
+
+impl WorkingTreeChanges {
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty() && self.unstaged.is_empty() && self.untracked.is_empty()
+    }
+}
+
+pub fn working_tree_changes(
+    path: &Path,
+    kind: RepositoryKind,
+    options: &DiffOptions,
+) -> Result<WorkingTreeChanges, String> {
+    if kind.is_jj() {
+        let staged = if jj_has_staging(path)? {
+            jj_diff(path, "@-", options)?
+        } else {
+            vec![]
+        };
+        return Ok(WorkingTreeChanges {
+            staged,
+            unstaged: jj_diff(path, "@", options)?,
+            untracked: vec![],
+        });
+    }
+
+    let untracked = git(path, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+    Ok(WorkingTreeChanges {
+        staged: git_diff(path, &["diff", "--cached", "-M"], options)?,
+        unstaged: git_diff(path, &["diff"], options)?,
+        untracked: untracked
+            .split('\0')
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_owned())
+            .collect(),
+    })
+}
+
+/// ファイル全体をステージする
+pub fn stage_file(path: &Path, kind: RepositoryKind, file: &FileDiff) -> Result<(), String> {
+    let paths = file_paths(file);
+    if kind.is_jj() {
+        jj_ensure_staging(path)?;
+        jj_squash(path, "@", "@-", &paths)
+    } else {
+        git_paths(path, &["add", "-A"], &paths)
+    }
+}
+
+/// ファイル全体のステージを取り消す
+pub fn unstage_file(path: &Path, kind: RepositoryKind, file: &FileDiff) -> Result<(), String> {
+    let paths = file_paths(file);
+    if kind.is_jj() {
+        jj_verify_staging(path)?;
+        jj_squash(path, "@-", "@", &paths)
+    } else {
+        git_paths(path, &["restore", "--staged"], &paths)
+    }
+}
+
+/// ステージしていないファイル全体の変更を捨てる
+pub fn discard_file(path: &Path, kind: RepositoryKind, file: &FileDiff) -> Result<(), String> {
+    let paths = file_paths(file);
+    if kind.is_jj() {
+        let filesets = paths.iter().map(|x| fileset(x)).collect::<Vec<_>>();
+        let mut args = vec!["restore"];
+        args.extend(filesets.iter().map(|x| x.as_str()));
+        jj(path, &args).map(|_| ())
+    } else {
+        git_paths(path, &["restore", "--worktree"], &paths)
+    }
+}
+
+pub fn stage_untracked(path: &Path, file: &str) -> Result<(), String> {
+    git_paths(path, &["add"], &[file])
+}
+
+/// 未追跡のファイルを削除する
+pub fn discard_untracked(path: &Path, file: &str) -> Result<(), String> {
+    git_paths(path, &["clean", "-f"], &[file])
+}
+
+/// 未ステージの差分のうち、選択した行だけをステージする
+pub fn stage_lines(
+    path: &Path,
+    kind: RepositoryKind,
+    file: &FileDiff,
+    selection: &LineSelection,
+) -> Result<(), String> {
+    if kind.is_jj() {
+        jj_apply_selection(path, file, selection, false)
+    } else {
+        git_apply_selection(path, file, selection, false, &["--cached"])
+    }
+}
+
+/// ステージ済みの差分のうち、選択した行だけをステージから外す
+pub fn unstage_lines(
+    path: &Path,
+    kind: RepositoryKind,
+    file: &FileDiff,
+    selection: &LineSelection,
+) -> Result<(), String> {
+    if kind.is_jj() {
+        jj_apply_selection(path, file, selection, true)
+    } else {
+        git_apply_selection(path, file, selection, true, &["--cached", "-R"])
+    }
+}
+
+/// 未ステージの差分のうち、選択した行の変更を作業ツリーから捨てる。
+/// git apply はリポジトリの外でも使えるので、jj の作業コピーにもそのまま当てる。
+pub fn discard_lines(
+    path: &Path,
+    file: &FileDiff,
+    selection: &LineSelection,
+) -> Result<(), String> {
+    git_apply_selection(path, file, selection, true, &["-R"])
+}
+
+/// ハンク i のすべての行
+pub fn hunk_selection(file: &FileDiff, hunk: usize) -> LineSelection {
+    file.hunks
+        .get(hunk)
+        .map(|x| (0..x.lines.len()).map(|line| (hunk, line)).collect())
+        .unwrap_or_default()
+}
+
+/// 行単位で扱えるファイルか（追加・削除・バイナリはファイル全体で扱う）
+pub fn supports_partial(file: &FileDiff) -> bool {
+    !file.binary && file.old_path.is_some() && file.new_path.is_some() && !file.hunks.is_empty()
+}
+
+/// 選択した行の変更だけを表すパッチを作る。変更行を一つも選んでいなければ None。
+/// reverse が false なら差分の古い側に、true なら新しい側に（`git apply -R` で）当てるものとして作る。
+/// 選ばなかった行は、当てる側にあるものは文脈に、無いものは省く。
+fn selection_patch(file: &FileDiff, selection: &LineSelection, reverse: bool) -> Option<String> {
+    let mut patch = String::new();
+    for line in &file.header {
+        patch.push_str(line);
+        patch.push('\n');
+    }
+    // それまでのハンクで増えた行数（新しい側 - 古い側）
+    let mut delta = 0isize;
+    let mut selected_any = false;
+
+    for (h, hunk) in file.hunks.iter().enumerate() {
+        let selected = |l: usize| selection.contains(&(h, l));
+        if !hunk
+            .lines
+            .iter()
+            .enumerate()
+            .any(|(l, x)| x.kind != LineKind::Context && selected(l))
+        {
+            continue;
+        }
+        selected_any = true;
+
+        let mut body = String::new();
+        let (mut old_count, mut new_count) = (0, 0);
+        for (l, line) in hunk.lines.iter().enumerate() {
+            let prefix = match (line.kind, selected(l), reverse) {
+                (LineKind::Context, _, _) => ' ',
+                (LineKind::Added, true, _) => '+',
+                (LineKind::Removed, true, _) => '-',
+                (LineKind::Removed, false, false) | (LineKind::Added, false, true) => ' ',
+                (LineKind::Added, false, false) | (LineKind::Removed, false, true) => continue,
+            };
+            if prefix != '+' {
+                old_count += 1;
+            }
+            if prefix != '-' {
+                new_count += 1;
+            }
+            body.push(prefix);
+            body.push_str(&line.content);
+            body.push('\n');
+            if line.missing_newline {
+                body.push_str("\\ No newline at end of file\n");
+            }
+        }
+
+        // 当てる側の位置は元の差分のまま、反対側は前のハンクで増減した分だけずらす
+        let (old_first, new_first) = if reverse {
+            let new_first = first_line(hunk.new_start, hunk.new_lines);
+            (new_first - delta, new_first)
+        } else {
+            let old_first = first_line(hunk.old_start, hunk.old_lines);
+            (old_first, old_first + delta)
+        };
+        patch.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_first, old_count),
+            hunk_range(new_first, new_count)
+        ));
+        patch.push_str(&body);
+        delta += new_count as isize - old_count as isize;
+    }
+
+    selected_any.then_some(patch)
+}
+
+/// ハンクの最初の行の位置（行数が 0 のハンクの start は直前の行を指す）
+fn first_line(start: usize, count: usize) -> isize {
+    if count == 0 {
+        start as isize + 1
+    } else {
+        start as isize
+    }
+}
+
+fn hunk_range(first: isize, count: usize) -> String {
+    let start = if count == 0 { first - 1 } else { first };
+    format!("{},{}", start.max(0), count)
+}
+
+/// 選択した行のパッチを `git apply` で当てる。
+/// 一覧を読み込んだ後に内容が変わっていたら、何も変えずに失敗する。
+fn git_apply_selection(
+    path: &Path,
+    file: &FileDiff,
+    selection: &LineSelection,
+    reverse: bool,
+    args: &[&str],
+) -> Result<(), String> {
+    partial_path(file)?;
+    let patch = selection_patch(file, selection, reverse).ok_or("行が選ばれていません")?;
+
+    let mut options = vec!["--whitespace=nowarn"];
+    options.extend(args);
+    // 前後の行数 0 で取った差分は文脈の無いハンクになる
+    if file
+        .hunks
+        .iter()
+        .any(|x| x.lines.iter().all(|x| x.kind != LineKind::Context))
+    {
+        options.push("--unidiff-zero");
+    }
+
+    let mut check = vec!["apply", "--check"];
+    check.extend(&options);
+    git_with_input(path, &check, &patch).map_err(|err| {
+        format!(
+            "差分が最新ではないため適用できません。読み込み直してください: {}",
+            err
+        )
+    })?;
+
+    let mut apply = vec!["apply"];
+    apply.extend(&options);
+    git_with_input(path, &apply, &patch).map(|_| ())
+}
+
+/// base に選択した行の変更だけを当てた内容を作る。
+/// reverse が false なら base は変更前の内容で、選択した変更を加える。
+/// true なら base は変更後の内容で、選択した変更を取り消す。
+/// 文脈の行と置き換える行が base と一致しなければ（差分が古ければ）失敗する。
+fn apply_selection(
+    base: &[u8],
+    file: &FileDiff,
+    selection: &LineSelection,
+    reverse: bool,
+) -> Result<Vec<u8>, String> {
+    let eol: &[u8] = if base.windows(2).any(|x| x == b"\r\n") {
+        b"\r\n"
+    } else {
+        b"\n"
+    };
+    let base_lines = base.split_inclusive(|x| *x == b'\n').collect::<Vec<_>>();
+    let mismatch = |line: usize| {
+        format!(
+            "{} の {} 行目が差分と一致しません。読み込み直してください",
+            file.display_path(),
+            line + 1
+        )
+    };
+    let mut content = Vec::with_capacity(base.len());
+    // base で次に読む行（0 始まり）
+    let mut next = 0;
+
+    for (h, hunk) in file.hunks.iter().enumerate() {
+        let (start, count) = if reverse {
+            (hunk.new_start, hunk.new_lines)
+        } else {
+            (hunk.old_start, hunk.old_lines)
+        };
+        // 行数が 0 のハンクは start 行の直後への挿入
+        let start = if count == 0 {
+            start
+        } else {
+            start.saturating_sub(1)
+        };
+        if start < next || start > base_lines.len() {
+            return Err(mismatch(start));
+        }
+        for x in &base_lines[next..start] {
+            content.extend_from_slice(x);
+        }
+        next = start;
+
+        for (l, line) in hunk.lines.iter().enumerate() {
+            let selected = selection.contains(&(h, l));
+            // (base にある行か, 結果に残すか)
+            let (in_base, keep) = match (line.kind, reverse) {
+                (LineKind::Context, _) => (true, true),
+                (LineKind::Removed, false) | (LineKind::Added, true) => (true, !selected),
+                (LineKind::Added, false) | (LineKind::Removed, true) => (false, selected),
+            };
+            if in_base {
+                let Some(x) = base_lines.get(next) else {
+                    return Err(mismatch(next));
+                };
+                if trim_eol(x) != line.content.as_bytes() {
+                    return Err(mismatch(next));
+                }
+                if keep {
+                    content.extend_from_slice(x);
+                }
+                next += 1;
+            } else if keep {
+                content.extend_from_slice(line.content.as_bytes());
+                if !line.missing_newline {
+                    content.extend_from_slice(eol);
+                }
+            }
+        }
+    }
+
+    for x in &base_lines[next..] {
+        content.extend_from_slice(x);
+    }
+    Ok(content)
+}
+
+fn trim_eol(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn partial_path(file: &FileDiff) -> Result<&str, String> {
+    match &file.new_path {
+        Some(path) if supports_partial(file) => Ok(path),
+        _ => Err(format!("{} は行単位で操作できません", file.display_path())),
+    }
+}
+
+/// rename の場合は両方のパス
+fn file_paths(file: &FileDiff) -> Vec<&str> {
+    let mut paths = vec![];
+    if let Some(old) = &file.old_path {
+        paths.push(old.as_str());
+    }
+    if let Some(new) = &file.new_path
+        && file.old_path.as_ref() != Some(new)
+    {
+        paths.push(new.as_str());
+    }
+    paths
+}
+
+fn git_paths(path: &Path, args: &[&str], paths: &[&str]) -> Result<(), String> {
+    let mut all = args.to_vec();
+    all.push("--");
+    all.extend(paths);
+    git(path, &all).map(|_| ())
+}
+
+/// @- が skjra の作ったステージ用の change なら true
+pub fn jj_has_staging(path: &Path) -> Result<bool, String> {
+    let description = jj(
+        path,
+        &["log", "-r", "@-", "--no-graph", "-T", "description"],
+    )?;
+    Ok(description.trim_end() == JJ_STAGING_DESCRIPTION)
+}
+
+/// @- がステージ用の change でなければエラーにする。
+/// 普通のコミットの変更を @ との間で動かさないよう、squash の前に必ず確かめる。
+pub fn jj_verify_staging(path: &Path) -> Result<(), String> {
+    if jj_has_staging(path)? {
+        Ok(())
+    } else {
+        Err("@- がステージ用の change ではありません。先に変更をステージしてください".to_owned())
+    }
+}
+
+/// ステージ用の change が無ければ、@ の直前に空の change として作る
+pub fn jj_ensure_staging(path: &Path) -> Result<(), String> {
+    if jj_has_staging(path)? {
+        return Ok(());
+    }
+    jj(
+        path,
+        &[
+            "new",
+            "--insert-before",
+            "@",
+            "--no-edit",
+            "-m",
+            JJ_STAGING_DESCRIPTION,
+        ],
+    )
+    .map(|_| ())
+}
+
+/// ワークスペースのルートからのパスを表す fileset
+fn fileset(path: &str) -> String {
+    format!("root-file:{:?}", path)
+}
+
+fn jj_squash(path: &Path, from: &str, into: &str, paths: &[&str]) -> Result<(), String> {
+    let filesets = paths.iter().map(|x| fileset(x)).collect::<Vec<_>>();
+    // 説明文の編集画面を開かず、空になった change も残す
+    let mut args = vec![
+        "squash",
+        "--from",
+        from,
+        "--into",
+        into,
+        "--use-destination-message",
+        "--keep-emptied",
+    ];
+    args.extend(filesets.iter().map(|x| x.as_str()));
+    jj(path, &args).map(|_| ())
+}
+
+/// jj には index が無いので、ステージ用の change (@-) の内容に選択した行を当てて書き戻す
+fn jj_apply_selection(
+    path: &Path,
+    file: &FileDiff,
+    selection: &LineSelection,
+    reverse: bool,
+) -> Result<(), String> {
+    let target = partial_path(file)?;
+    if reverse {
+        jj_verify_staging(path)?;
+    } else {
+        jj_ensure_staging(path)?;
+    }
+    let parent = jj_bytes(path, &["file", "show", "-r", "@-", &fileset(target)])?;
+    let content = apply_selection(&parent, file, selection, reverse)?;
+    jj_set_parent_content(path, target, &content)
+}
+
+/// @ の内容を保ったまま、ステージ用の change (@-) の target を content にする。
+/// 作業コピーに content を書いて @- へ squash し、元の内容を書き戻す。
+fn jj_set_parent_content(path: &Path, target: &str, content: &[u8]) -> Result<(), String> {
+    jj_verify_staging(path)?;
+    let full_path = path.join(target);
+    let current = fs::read(&full_path).map_err(|err| err.to_string())?;
+    fs::write(&full_path, content).map_err(|err| err.to_string())?;
+
+    let result = jj_squash(path, "@", "@-", &[target]);
+    // 失敗しても作業コピーは必ず元に戻す
+    fs::write(&full_path, current).map_err(|err| err.to_string())?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::vcs::diff::parse_diff;
+
+    const HEADER: &str = "diff --git a/f.txt b/f.txt\n--- a/f.txt\n+++ b/f.txt\n";
+
+    fn file(hunks: &str) -> FileDiff {
+        parse_diff(&format!("{}{}", HEADER, hunks)).remove(0)
+    }
+
+    /// `a b d` を `a B c d` にする差分
+    fn replace_and_add() -> FileDiff {
+        file("@@ -1,3 +1,4 @@\n a\n-b\n+B\n+c\n d\n")
+    }
+
+    fn select(lines: &[(usize, usize)]) -> LineSelection {
+        lines.iter().copied().collect()
+    }
+
+    #[test]
+    fn apply_selection_adds_only_selected_lines() {
+        let content = apply_selection(b"a\nb\nd\n", &replace_and_add(), &select(&[(0, 3)]), false);
+        assert_eq!(content.unwrap(), b"a\nb\nc\nd\n");
+
+        let content = apply_selection(
+            b"a\nb\nd\n",
+            &replace_and_add(),
+            &select(&[(0, 1), (0, 2)]),
+            false,
+        );
+        assert_eq!(content.unwrap(), b"a\nB\nd\n");
+    }
+
+    #[test]
+    fn apply_selection_reverts_only_selected_lines() {
+        let content = apply_selection(
+            b"a\nB\nc\nd\n",
+            &replace_and_add(),
+            &select(&[(0, 3)]),
+            true,
+        );
+        assert_eq!(content.unwrap(), b"a\nB\nd\n");
+
+        let content = apply_selection(
+            b"a\nB\nc\nd\n",
+            &replace_and_add(),
+            &select(&[(0, 1), (0, 2)]),
+            true,
+        );
+        assert_eq!(content.unwrap(), b"a\nb\nc\nd\n");
+    }
+
+    #[test]
+    fn apply_selection_rejects_stale_diff() {
+        // 差分を取った後に b が x に変わっている
+        let result = apply_selection(b"a\nx\nd\n", &replace_and_add(), &select(&[(0, 3)]), false);
+        assert!(result.is_err());
+
+        // 行が足りない
+        let result = apply_selection(b"a\n", &replace_and_add(), &select(&[(0, 3)]), false);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn apply_selection_keeps_bytes_and_line_endings() {
+        let file = file("@@ -2,2 +2,3 @@\n a\n+c\n b\n");
+        let content = apply_selection(b"\xff\xfe\na\nb\n", &file, &select(&[(0, 1)]), false);
+        assert_eq!(content.unwrap(), b"\xff\xfe\na\nc\nb\n");
+
+        let content = apply_selection(
+            b"a\r\nb\r\nd\r\n",
+            &replace_and_add(),
+            &select(&[(0, 3)]),
+            false,
+        );
+        assert_eq!(content.unwrap(), b"a\r\nb\r\nc\r\nd\r\n");
+    }
+
+    #[test]
+    fn apply_selection_handles_insertion_hunk() {
+        // 1 行目の直後への挿入
+        let file = file("@@ -1,0 +2,1 @@\n+x\n");
+        let content = apply_selection(b"a\nb\n", &file, &select(&[(0, 0)]), false);
+        assert_eq!(content.unwrap(), b"a\nx\nb\n");
+    }
+
+    #[test]
+    fn selection_patch_turns_unselected_lines_into_context() {
+        let patch = selection_patch(&replace_and_add(), &select(&[(0, 3)]), false).unwrap();
+        assert_eq!(
+            patch,
+            format!("{}@@ -1,3 +1,4 @@\n a\n b\n+c\n d\n", HEADER)
+        );
+
+        let patch = selection_patch(&replace_and_add(), &select(&[(0, 3)]), true).unwrap();
+        assert_eq!(
+            patch,
+            format!("{}@@ -1,3 +1,4 @@\n a\n B\n+c\n d\n", HEADER)
+        );
+    }
+
+    #[test]
+    fn selection_patch_shifts_later_hunks() {
+        let file = file("@@ -1,2 +1,3 @@\n a\n+x\n b\n@@ -10,2 +11,3 @@\n j\n+y\n k\n");
+
+        // 前のハンクを当てないので、古い側の位置のまま
+        let patch = selection_patch(&file, &select(&[(1, 1)]), false).unwrap();
+        assert_eq!(patch, format!("{}@@ -10,2 +10,3 @@\n j\n+y\n k\n", HEADER));
+
+        let patch = selection_patch(&file, &select(&[(0, 1), (1, 1)]), false).unwrap();
+        assert!(patch.ends_with("@@ -10,2 +11,3 @@\n j\n+y\n k\n"));
+
+        // -R では新しい側が今の内容になる
+        let patch = selection_patch(&file, &select(&[(1, 1)]), true).unwrap();
+        assert_eq!(patch, format!("{}@@ -11,2 +11,3 @@\n j\n+y\n k\n", HEADER));
+    }
+
+    #[test]
+    fn selection_patch_needs_a_changed_line() {
+        assert!(selection_patch(&replace_and_add(), &select(&[]), false).is_none());
+        // 文脈の行だけでは何も変わらない
+        assert!(selection_patch(&replace_and_add(), &select(&[(0, 0)]), false).is_none());
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::diff::{DiffOptions, FileDiff, parse_diff};
+use crate::app::vcs::git;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashInfo {
+    /// `stash@{0}` の 0
+    pub index: usize,
+    /// `stash@{0}` の形
+    pub name: String,
+    /// stash のコミット。位置は stash を作ったり消したりするとずれるので、操作の前にこれで確かめる。
+    pub commit_id: String,
+    pub message: String,
+    /// stash したときのブランチ（detached HEAD では None）
+    pub branch: Option<String>,
+    pub date: Option<SystemTime>,
+}
+
+/// stash の一覧（新しいものから）。jj には stash が無いので常に空。
+pub fn list_stashes(path: &Path, kind: RepositoryKind) -> Result<Vec<StashInfo>, String> {
+    if kind.is_jj() || kind == RepositoryKind::Bare {
+        return Ok(vec![]);
+    }
+
+    let output = git(path, &["stash", "list", "--format=%gd%x09%H%x09%ct%x09%gs"])?;
+    Ok(output
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let mut fields = line.splitn(4, '\t');
+            let name = fields.next()?.to_owned();
+            let commit_id = fields.next()?.to_owned();
+            let date = fields
+                .next()?
+                .parse::<u64>()
+                .ok()
+                .map(|x| UNIX_EPOCH + Duration::from_secs(x));
+            let (branch, message) = parse_subject(fields.next()?);
+            Some(StashInfo {
+                index: parse_index(&name).unwrap_or(index),
+                name,
+                commit_id,
+                message,
+                branch,
+                date,
+            })
+        })
+        .collect())
+}
+
+/// stash の数（カードのバッジに使う）
+pub fn stash_count(path: &Path, kind: RepositoryKind) -> Result<usize, String> {
+    list_stashes(path, kind).map(|x| x.len())
+}
+
+/// `stash@{3}` から 3 を取り出す
+fn parse_index(name: &str) -> Option<usize> {
+    name.strip_prefix("stash@{")?
+        .strip_suffix('}')?
+        .parse()
+        .ok()
+}
+
+/// `WIP on main: abc1234 件名` や `On main: メッセージ` をブランチとメッセージに分ける
+fn parse_subject(subject: &str) -> (Option<String>, String) {
+    let rest = subject
+        .strip_prefix("WIP on ")
+        .or_else(|| subject.strip_prefix("On "));
+    match rest.and_then(|x| x.split_once(": ")) {
+        Some(("(no branch)", message)) => (None, message.to_owned()),
+        Some((branch, message)) => (Some(branch.to_owned()), message.to_owned()),
+        None => (None, subject.to_owned()),
+    }
+}
+
+/// stash の差分（追跡していないファイルを含めたものも含む）
+pub fn stash_diff(path: &Path, name: &str, options: &DiffOptions) -> Result<Vec<FileDiff>, String> {
+    // git_diff はサブコマンドの直後に表示のオプションを差し込むので、stash show では使えない
+    let unified = format!("-U{}", options.context_lines);
+    let mut args = vec![
+        "stash",
+        "show",
+        "--no-color",
+        "--full-index",
+        unified.as_str(),
+    ];
+    if options.ignore_whitespace {
+        args.push("-w");
+    }
+    args.extend(["-p", "--include-untracked", name]);
+    git(path, &args).map(|x| parse_diff(&x))
+}
+
+/// 作業ツリーの変更を stash する。message が空なら git の既定のメッセージになる。
+pub fn create_stash(
+    path: &Path,
+    message: &str,
+    include_untracked: bool,
+    keep_index: bool,
+) -> Result<(), String> {
+    let mut args = vec!["stash", "push"];
+    if include_untracked {
+        args.push("--include-untracked");
+    }
+    if keep_index {
+        args.push("--keep-index");
+    }
+    if !message.is_empty() {
+        args.extend(["-m", message]);
+    }
+    git(path, &args).map(|_| ())
+}
+
+/// stash を作業ツリーに適用する（stash は残す）
+pub fn apply_stash(path: &Path, stash: &StashInfo) -> Result<(), String> {
+    verify_stash(path, stash)?;
+    git(path, &["stash", "apply", &stash.name]).map(|_| ())
+}
+
+/// stash を作業ツリーに適用して消す。衝突したときは stash は残る。
+pub fn pop_stash(path: &Path, stash: &StashInfo) -> Result<(), String> {
+    verify_stash(path, stash)?;
+    git(path, &["stash", "pop", &stash.name]).map(|_| ())
+}
+
+pub fn drop_stash(path: &Path, stash: &StashInfo) -> Result<(), String> {
+    verify_stash(path, stash)?;
+    git(path, &["stash", "drop", &stash.name]).map(|_| ())
+}
+
+/// 一覧を読み込んだ後に stash が増減して、同じ位置が別の stash を指していないか確かめる
+fn verify_stash(path: &Path, stash: &StashInfo) -> Result<(), String> {
+    let current = git(path, &["rev-parse", "-q", "--verify", &stash.name]).unwrap_or_default();
+    if current.trim() != stash.commit_id {
+        return Err(format!(
+            "{} が一覧を読み込んだときと違う stash を指しています。読み込み直してください",
+            stash.name
+        ));
+    }
+    Ok(())
+}
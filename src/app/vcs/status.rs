@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::{git, jj};
+
+/// 作業ツリーの変更件数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkingTreeStatus {
+    pub staged: usize,
+    pub modified: usize,
+    pub untracked: usize,
+    pub deleted: usize,
+    pub renamed: usize,
+    pub conflicted: usize,
+}
+
+impl WorkingTreeStatus {
+    /// コミットされていない変更があるか
+    pub fn is_dirty(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// 作業ツリーの変更件数を集計する。bare リポジトリでは None。
+pub fn working_tree_status(
+    path: &Path,
+    kind: RepositoryKind,
+) -> Result<Option<WorkingTreeStatus>, String> {
+    match kind {
+        RepositoryKind::Bare => Ok(None),
+        RepositoryKind::Jj | RepositoryKind::Colocated => jj_status(path).map(Some),
+        _ => git_status(path).map(Some),
+    }
+}
+
+fn git_status(path: &Path) -> Result<WorkingTreeStatus, String> {
+    let output = git(path, &["status", "--porcelain=v1", "-z"])?;
+
+    let mut status = WorkingTreeStatus::default();
+    let mut entries = output.split('\0').filter(|x| !x.is_empty());
+    while let Some(entry) = entries.next() {
+        let mut chars = entry.chars();
+        let (Some(x), Some(y)) = (chars.next(), chars.next()) else {
+            continue;
+        };
+
+        match (x, y) {
+            ('?', '?') => status.untracked += 1,
+            ('!', '!') => (),
+            ('D', 'D') | ('A', 'A') | ('U', _) | (_, 'U') => status.conflicted += 1,
+            _ => {
+                if x != ' ' {
+                    status.staged += 1;
+                }
+                if y == 'M' || y == 'T' {
+                    status.modified += 1;
+                }
+                if x == 'D' || y == 'D' {
+                    status.deleted += 1;
+                }
+                if x == 'R' || y == 'R' {
+                    status.renamed += 1;
+                }
+                // rename / copy は元のパスが次の要素に続く
+                if matches!(x, 'R' | 'C') || matches!(y, 'R' | 'C') {
+                    entries.next();
+                }
+            }
+        }
+    }
+    Ok(status)
+}
+
+/// jj には index が無いので、作業中のリビジョン (@) の差分を数える
+fn jj_status(path: &Path) -> Result<WorkingTreeStatus, String> {
+    let output = jj(path, &["diff", "--summary", "-r", "@"])?;
+
+    let mut status = WorkingTreeStatus::default();
+    for line in output.lines() {
+        match line.chars().next() {
+            Some('M') => status.modified += 1,
+            // jj は新しいファイルも自動で追跡するので、git の untracked に揃える
+            Some('A') | Some('C') => status.untracked += 1,
+            Some('D') => status.deleted += 1,
+            Some('R') => status.renamed += 1,
+            _ => (),
+        }
+    }
+    // 競合が無いときはエラー終了するので 0 件として扱う
+    status.conflicted = jj(path, &["resolve", "--list"])
+        .map(|x| x.lines().count())
+        .unwrap_or(0);
+    Ok(status)
+}
@@ -0,0 +1,329 @@
+use std::cmp::Ordering;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::upstream::jj_count;
+use crate::app::vcs::{git, jj};
+
+#[derive(Debug, Clone)]
+pub struct TagInfo {
+    pub name: String,
+    /// 指しているコミット
+    pub target: String,
+    pub annotated: bool,
+    /// 注釈付きタグなら作った人、軽量タグならコミットの作者
+    pub tagger: String,
+    pub date: Option<SystemTime>,
+    /// 注釈付きタグならタグのメッセージ、軽量タグならコミットのメッセージ
+    pub message: String,
+}
+
+/// 今の位置から最も近いタグ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatestTag {
+    pub name: String,
+    /// タグより後のコミット数
+    pub commits_since: usize,
+}
+
+impl std::fmt::Display for LatestTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.commits_since {
+            0 => write!(f, "{}", self.name),
+            n => write!(f, "{} +{}", self.name, n),
+        }
+    }
+}
+
+/// セマンティックバージョンの新しい順に並べたタグの一覧。
+/// バージョンとして読めないタグは後ろに日付の新しい順で並べる。
+/// colocated ではタグは git 側で扱う（jj は次の操作で取り込む）。
+pub fn list_tags(path: &Path, kind: RepositoryKind) -> Result<Vec<TagInfo>, String> {
+    let mut tags = if kind == RepositoryKind::Jj {
+        jj_list_tags(path)?
+    } else {
+        git_list_tags(path)?
+    };
+    tags.sort_by(|a, b| compare_tags(b, a));
+    Ok(tags)
+}
+
+fn git_list_tags(path: &Path) -> Result<Vec<TagInfo>, String> {
+    // メッセージは複数行になるので NUL で区切る
+    let output = git(
+        path,
+        &[
+            "for-each-ref",
+            concat!(
+                "--format=%(refname:short)%09%(objecttype)%09%(objectname)%09%(*objectname)",
+                "%09%(if)%(taggername)%(then)%(taggername)%(else)%(*authorname)%(authorname)%(end)",
+                "%09%(creatordate:unix)%09%(contents)%00",
+            ),
+            "refs/tags",
+        ],
+    )?;
+
+    Ok(output
+        .split('\0')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(7, '\t');
+            let name = fields.next().filter(|x| !x.is_empty())?.to_owned();
+            let annotated = fields.next()? == "tag";
+            let object = fields.next()?;
+            let peeled = fields.next()?;
+            let tagger = fields.next()?.to_owned();
+            let date = parse_time(fields.next()?);
+            let message = fields.next().unwrap_or_default().trim().to_owned();
+            Some(TagInfo {
+                name,
+                target: if annotated { peeled } else { object }.to_owned(),
+                annotated,
+                tagger,
+                date,
+                message,
+            })
+        })
+        .collect())
+}
+
+/// jj のタグはすべて軽量タグとして扱う
+fn jj_list_tags(path: &Path) -> Result<Vec<TagInfo>, String> {
+    let output = jj(
+        path,
+        &[
+            "tag",
+            "list",
+            "-T",
+            concat!(
+                r#"name ++ "\t" ++ if(normal_target, normal_target.commit_id()"#,
+                r#" ++ "\t" ++ normal_target.author().name()"#,
+                r#" ++ "\t" ++ normal_target.author().timestamp().format("%s")"#,
+                r#" ++ "\t" ++ normal_target.description().first_line()) ++ "\n""#,
+            ),
+        ],
+    )?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, '\t');
+            Some(TagInfo {
+                name: fields.next().filter(|x| !x.is_empty())?.to_owned(),
+                target: fields.next().unwrap_or_default().to_owned(),
+                annotated: false,
+                tagger: fields.next().unwrap_or_default().to_owned(),
+                date: fields.next().and_then(parse_time),
+                message: fields.next().unwrap_or_default().to_owned(),
+            })
+        })
+        .collect())
+}
+
+fn parse_time(seconds: &str) -> Option<SystemTime> {
+    let seconds = seconds.trim().parse::<u64>().ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// バージョンとして読めるタグを大きい方に、読めないものは日付で比べる
+fn compare_tags(a: &TagInfo, b: &TagInfo) -> Ordering {
+    match (Version::parse(&a.name), Version::parse(&b.name)) {
+        (Some(x), Some(y)) => x.cmp(&y).then_with(|| a.name.cmp(&b.name)),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => a.date.cmp(&b.date).then_with(|| a.name.cmp(&b.name)),
+    }
+}
+
+/// `v1.2.3-rc.1` のようなタグ名のバージョン（ビルドメタデータは無視する）
+#[derive(Debug, PartialEq, Eq)]
+struct Version {
+    numbers: [u64; 3],
+    pre: Option<Vec<String>>,
+}
+
+impl Version {
+    fn parse(name: &str) -> Option<Self> {
+        let name = name.strip_prefix('v').unwrap_or(name);
+        let name = name.split_once('+').map_or(name, |(x, _)| x);
+        let (core, pre) = match name.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.split('.').map(|x| x.to_owned()).collect())),
+            None => (name, None),
+        };
+
+        // 1.2 のように省略されたものは 0 で埋める
+        let mut numbers = [0; 3];
+        let mut parts = core.split('.');
+        for (i, part) in parts.by_ref().take(3).enumerate() {
+            numbers[i] = part.parse().ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self { numbers, pre })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.numbers.cmp(&other.numbers).then_with(|| {
+            match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                // プレリリースは正式版より前
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(x), Some(y)) => compare_pre(x, y),
+            }
+        })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// 数字どうしは数値で、それ以外は文字列で比べる。数字は文字列より前。
+fn compare_pre(a: &[String], b: &[String]) -> Ordering {
+    for (x, y) in a.iter().zip(b) {
+        let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => x.cmp(y),
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+/// HEAD（jj では @-）から辿って最も近いタグと、それより後のコミット数。タグが無ければ None。
+pub fn latest_tag(path: &Path, kind: RepositoryKind) -> Result<Option<LatestTag>, String> {
+    if kind == RepositoryKind::Jj {
+        let output = jj(
+            path,
+            &[
+                "log",
+                "--no-graph",
+                "-r",
+                "heads(::@- & tags())",
+                "-T",
+                r#"tags.map(|t| t.name()).join("\n") ++ "\n""#,
+            ],
+        )?;
+        let Some(name) = output.lines().map(|x| x.trim()).find(|x| !x.is_empty()) else {
+            return Ok(None);
+        };
+        let revset = format!(r#"tags(exact:"{}")..@-"#, name);
+        return Ok(Some(LatestTag {
+            name: name.to_owned(),
+            commits_since: jj_count(path, &revset)?,
+        }));
+    }
+
+    // タグが無いと失敗する
+    let Ok(name) = git(path, &["describe", "--tags", "--abbrev=0"]) else {
+        return Ok(None);
+    };
+    let name = name.trim().to_owned();
+    // 同じ名前のブランチと取り違えないように、ref の完全な名前で数える
+    let range = format!("refs/tags/{}..HEAD", name);
+    let count = git(path, &["rev-list", "--count", &range])?;
+    Ok(Some(LatestTag {
+        name,
+        commits_since: count.trim().parse().unwrap_or_default(),
+    }))
+}
+
+/// target にタグを作る。message があれば注釈付きタグにする（jj ではできない）。
+pub fn create_tag(
+    path: &Path,
+    kind: RepositoryKind,
+    name: &str,
+    target: &str,
+    message: Option<&str>,
+) -> Result<(), String> {
+    match (kind == RepositoryKind::Jj, message) {
+        (true, Some(_)) => Err("jj では注釈付きタグを作れません".to_owned()),
+        (true, None) => jj(path, &["tag", "set", name, "-r", target]).map(|_| ()),
+        (false, Some(message)) => {
+            git(path, &["tag", "-a", name, "-m", message, target]).map(|_| ())
+        }
+        (false, None) => git(path, &["tag", name, target]).map(|_| ()),
+    }
+}
+
+/// ローカルのタグだけを消す（リモートには push しない）
+pub fn delete_tag(path: &Path, kind: RepositoryKind, name: &str) -> Result<(), String> {
+    if kind == RepositoryKind::Jj {
+        jj(path, &["tag", "delete", name]).map(|_| ())
+    } else {
+        git(path, &["tag", "-d", name]).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pre(x: &[&str]) -> Vec<String> {
+        x.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn version_parse_reads_prefix_pre_release_and_build() {
+        let version = Version::parse("v1.2.3-rc.1+build.5").unwrap();
+        assert_eq!(version.numbers, [1, 2, 3]);
+        assert_eq!(version.pre, Some(pre(&["rc", "1"])));
+
+        assert_eq!(Version::parse("2.0").unwrap().numbers, [2, 0, 0]);
+        assert_eq!(Version::parse("3").unwrap().pre, None);
+    }
+
+    #[test]
+    fn version_parse_rejects_non_versions() {
+        assert_eq!(Version::parse("release"), None);
+        assert_eq!(Version::parse("v1.2.3.4"), None);
+        assert_eq!(Version::parse("v1.x"), None);
+        assert_eq!(Version::parse(""), None);
+    }
+
+    #[test]
+    fn version_orders_pre_release_before_release() {
+        let version = |x: &str| Version::parse(x).unwrap();
+        assert!(version("v1.0.0-rc.1") < version("v1.0.0"));
+        assert!(version("v1.0.0") < version("v1.0.1-alpha"));
+        assert!(version("v1.10.0") > version("v1.9.0"));
+        assert_eq!(
+            version("v1.0.0+a").cmp(&version("1.0.0+b")),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn compare_pre_follows_semver_precedence() {
+        // 数字は数値で比べる
+        assert_eq!(
+            compare_pre(&pre(&["rc", "2"]), &pre(&["rc", "10"])),
+            Ordering::Less
+        );
+        // 数字は文字列より前
+        assert_eq!(compare_pre(&pre(&["1"]), &pre(&["alpha"])), Ordering::Less);
+        assert_eq!(
+            compare_pre(&pre(&["alpha"]), &pre(&["beta"])),
+            Ordering::Less
+        );
+        // 同じところまで等しければ、短い方が前
+        assert_eq!(
+            compare_pre(&pre(&["alpha"]), &pre(&["alpha", "1"])),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_pre(&pre(&["rc", "1"]), &pre(&["rc", "1"])),
+            Ordering::Equal
+        );
+    }
+}
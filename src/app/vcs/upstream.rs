@@ -0,0 +1,131 @@
+use std::path::Path;
+
+use crate::app::discovery::RepositoryKind;
+use crate::app::vcs::{git, jj};
+
+/// 現在のブランチと upstream との関係
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpstreamStatus {
+    /// ブランチ（jj ではブックマーク）上にいない
+    Detached,
+    /// upstream が設定されていない
+    NoUpstream { branch: String },
+    Tracking {
+        branch: String,
+        upstream: String,
+        ahead: usize,
+        behind: usize,
+    },
+}
+
+/// ローカルにある remote-tracking ref だけを見て ahead/behind を数える（fetch はしない）
+pub fn upstream_status(path: &Path, kind: RepositoryKind) -> Result<UpstreamStatus, String> {
+    match kind {
+        RepositoryKind::Jj | RepositoryKind::Colocated => jj_upstream_status(path),
+        _ => git_upstream_status(path),
+    }
+}
+
+fn git_upstream_status(path: &Path) -> Result<UpstreamStatus, String> {
+    // detached HEAD では失敗する
+    let Ok(branch) = git(path, &["symbolic-ref", "--quiet", "--short", "HEAD"]) else {
+        return Ok(UpstreamStatus::Detached);
+    };
+    let branch = branch.trim().to_owned();
+
+    let Ok(upstream) = git(
+        path,
+        &[
+            "rev-parse",
+            "--abbrev-ref",
+            "--symbolic-full-name",
+            "@{upstream}",
+        ],
+    ) else {
+        return Ok(UpstreamStatus::NoUpstream { branch });
+    };
+
+    let counts = git(
+        path,
+        &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"],
+    )?;
+    let mut counts = counts.split_whitespace().map(|x| x.parse::<usize>());
+    let (Some(Ok(ahead)), Some(Ok(behind))) = (counts.next(), counts.next()) else {
+        return Err(format!(
+            "rev-list の出力を解釈できません: {}",
+            path.display()
+        ));
+    };
+
+    Ok(UpstreamStatus::Tracking {
+        branch,
+        upstream: upstream.trim().to_owned(),
+        ahead,
+        behind,
+    })
+}
+
+/// jj では @ から最も近い祖先のブックマークを現在のブランチとみなす
+fn jj_upstream_status(path: &Path) -> Result<UpstreamStatus, String> {
+    let bookmarks = jj(
+        path,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            "heads(::@ & bookmarks())",
+            "-T",
+            r#"local_bookmarks.map(|b| b.name()).join("\n") ++ "\n""#,
+        ],
+    )?;
+    let Some(branch) = bookmarks.lines().map(|x| x.trim()).find(|x| !x.is_empty()) else {
+        return Ok(UpstreamStatus::Detached);
+    };
+    let branch = branch.to_owned();
+
+    let remote = format!(r#"remote_bookmarks(exact:"{}")"#, branch);
+    let local = format!(r#"bookmarks(exact:"{}")"#, branch);
+    let upstreams = jj(
+        path,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            &remote,
+            "-T",
+            r#"remote_bookmarks.map(|b| b.name() ++ "@" ++ b.remote()).join("\n") ++ "\n""#,
+        ],
+    )?;
+    let Some(upstream) = upstreams
+        .lines()
+        .map(|x| x.trim())
+        .find(|x| x.starts_with(&format!("{}@", branch)))
+    else {
+        return Ok(UpstreamStatus::NoUpstream { branch });
+    };
+    let upstream = upstream.to_owned();
+
+    let ahead = jj_count(path, &format!("{}..{}", remote, local))?;
+    let behind = jj_count(path, &format!("{}..{}", local, remote))?;
+    Ok(UpstreamStatus::Tracking {
+        branch,
+        upstream,
+        ahead,
+        behind,
+    })
+}
+
+pub fn jj_count(path: &Path, revset: &str) -> Result<usize, String> {
+    let output = jj(
+        path,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            revset,
+            "-T",
+            r#"commit_id ++ "\n""#,
+        ],
+    )?;
+    Ok(output.lines().count())
+}
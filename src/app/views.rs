@@ -1 +1 @@
-pub mod dashboard;
\ No newline at end of file
+pub mod dashboard;
@@ -1,211 +1,1049 @@
-use std::{
-    fs::{self},
-    path::{Path, PathBuf},
-};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
 
 use crate::app::components::{
     common::{
         drawer::Drawer,
         select::{self, Select},
     },
-    dashboard::card::{self, Card},
+    dashboard::{
+        branches,
+        bulk::{self, BulkActions},
+        card::{self, Card},
+        card_detail::{self, CardDetail},
+        changes,
+        cleanup::{self, Cleanup},
+        commit_editor, grid, remote, stashes,
+        table::{self, ColumnResize},
+        tags,
+    },
 };
-use chrono::{DateTime, Utc};
+use crate::app::discovery::{self, RepositoryKind};
+use crate::app::vcs::bulk::BulkOutcome;
+use crate::app::vcs::commit::last_commit_author;
+use crate::app::vcs::remote::RemoteOperation;
+use crate::app::vcs::stash::stash_count;
+use crate::app::vcs::status::working_tree_status;
+use crate::app::vcs::tag::latest_tag;
+use crate::app::vcs::upstream::upstream_status;
+use crate::app::watch::{self, Change};
+use crate::app::workspace::{Config, GroupBy, Layout, SortKey, Workspace};
 use endringer::repository::repository;
 use iced::{
     Element,
     Length::Fill,
-    widget::{Container, Row, button, column, container, row, scrollable, stack, text},
+    Subscription, Task,
+    futures::{
+        Stream,
+        channel::{mpsc, oneshot},
+    },
+    task,
+    widget::{
+        Column, Container, Row, button, checkbox, column, container, pick_list, progress_bar, row,
+        scrollable, space, stack, text, text_input,
+    },
 };
 
+/// 表でチェックボックスに使う列の幅
+const TABLE_CHECK_WIDTH: f32 = 30.0;
+
+/// 入力が止まってから設定を保存するまでの時間
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
 #[derive(Default)]
 pub struct Dashboard {
-    selected_path: Option<PathBuf>,
+    config: Config,
+    /// 設定ファイルを読み書きできなかったときのメッセージ
+    config_error: Option<String>,
+    /// 設定ファイルを読み込めなかった。既定の設定で上書きしないように、保存しない。
+    config_load_failed: bool,
+    workspace: Workspace,
     cards: Vec<card::Card>,
-    selected_card_id: Option<usize>,
+    /// 詳細を開いているカード
+    detail: Option<CardDetail>,
+    // 入力途中の値を保持するため、設定とは別にテキストで持つ
+    max_depth_input: String,
+    exclude_patterns_input: String,
+    /// 解釈できなかった除外パターン
+    exclude_pattern_errors: Vec<String>,
+    scan: Option<Scan>,
+    /// 一括操作のために選択したカード
+    checked_card_ids: BTreeSet<usize>,
+    bulk: BulkActions,
+    column_resize: ColumnResize,
+    /// 入力中の保存を待っているなら、その世代
+    save_scheduled: Option<usize>,
+    save_generation: usize,
+    /// 開いている古いブランチの整理
+    cleanup: Option<Cleanup>,
+}
+
+/// 実行中のスキャン
+struct Scan {
+    generation: usize,
+    // drop すると中断される
+    _handle: task::Handle,
+    total: Option<usize>,
+    loaded: usize,
+}
+
+/// スキャンの途中経過
+#[derive(Debug, Clone)]
+pub enum ScanEvent {
+    /// 探索が終わり、読み込むリポジトリ数が確定した
+    Discovered(usize),
+    /// カードを一枚読み込んだ
+    Loaded(Box<Card>),
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     FolderPick,
-    CardMessage(usize, card::Message),
+    FolderPicked(Option<PathBuf>),
+    RepositoryPick,
+    RepositoryPicked(Option<PathBuf>),
+    RootRemove(usize),
+    PinnedRemove(usize),
+    RecentWorkspaceSelected(Workspace),
+    Rescan,
+    FetchAll,
+    Scan(usize, ScanEvent),
+    CardReloaded(Card),
+    RepositoryChanged(watch::Event),
+    CardRefreshed(Card, Change),
+    MaxDepthChanged(String),
+    ExcludePatternsChanged(String),
+    RespectGitignoreToggled(bool),
+    DescendIntoRepositoriesToggled(bool),
+    Card(usize, card::Message),
     DrawerClose,
+    /// 詳細を開いているカードの id。閉じたり別のカードを開いた後に届いたものは捨てる。
+    Detail(usize, card_detail::Message),
+    CardChecked(usize, bool),
+    CheckAll,
+    UncheckAll,
+    Bulk(bulk::Message),
+    GridFilterChanged(String),
+    SortKeySelected(SortKey),
+    SortReverseToggled,
+    OnlyDirtyToggled(bool),
+    OnlyUnpushedToggled(bool),
+    OnlyDetachedToggled(bool),
+    GroupBySelected(GroupBy),
+    TagChanged(PathBuf, String),
+    /// 入力が落ち着いたので保存する
+    SaveWorkspace(usize),
+    LayoutSelected(Layout),
+    Table(table::Message),
+    CleanupOpen,
+    CleanupClose,
+    Cleanup(cleanup::Message),
 }
 
 impl Dashboard {
-    pub fn new() -> Self {
+    /// 最後に使ったワークスペースがあれば、それを復元して読み込みを始める
+    pub fn new() -> (Self, Task<Message>) {
         let cards = vec![];
+        let (config, config_error) = match Config::load() {
+            Ok(config) => (config, None),
+            Err(err) => (
+                Config::default(),
+                Some(format!(
+                    "{}。既定の設定で開いているので、変更は保存しません",
+                    err
+                )),
+            ),
+        };
+        let workspace = config.last_workspace().cloned().unwrap_or_default();
 
-        Self {
-            selected_path: None,
+        let mut dashboard = Self {
+            config,
+            config_load_failed: config_error.is_some(),
+            config_error,
+            workspace: Workspace::default(),
             cards,
-            selected_card_id: None,
-        }
+            detail: None,
+            max_depth_input: String::new(),
+            exclude_patterns_input: String::new(),
+            exclude_pattern_errors: vec![],
+            scan: None,
+            checked_card_ids: BTreeSet::new(),
+            bulk: BulkActions::default(),
+            column_resize: ColumnResize::default(),
+            save_scheduled: None,
+            save_generation: 0,
+            cleanup: None,
+        };
+        let task = dashboard.workspace_update(workspace);
+        (dashboard, task)
     }
 
     pub fn view(&self) -> Element<'_, Message> {
-        // カードのリストをループで生成
-        let card_list = self
-            .cards
-            .iter()
-            .map(|card| {
-                let id = card.id;
-                // 子の view を map して親の Message に変換
-                card.view().map(move |msg| Message::CardMessage(id, msg))
+        // 絞り込み・並べ替えたカードをグループごとに並べる
+        let groups = grid::arrange(&self.cards, &self.workspace.grid, &self.workspace.tags)
+            .into_iter()
+            .map(|group| {
+                let cards = self.view_cards(group.cards);
+                match group.title {
+                    Some(title) => column![text(title).size(18), cards].spacing(10).into(),
+                    None => cards,
+                }
             })
-            .collect::<Vec<_>>();
-
-        let path_display = self
-            .selected_path
-            .as_ref()
-            .map(|p| p.to_string_lossy()) // Cow<str> が返る
-            .unwrap_or_else(|| "(未選択)".into());
+            .collect::<Vec<Element<'_, Message>>>();
 
         let container = Container::new(column![
             text("Dashboard").size(30),
-            button("フォルダを選択").on_press(Message::FolderPick),
-            text(path_display),
-            scrollable(Row::with_children(card_list).spacing(20).padding(20).wrap())
+            row![
+                button("フォルダを追加").on_press(Message::FolderPick),
+                button("リポジトリをピン留め").on_press(Message::RepositoryPick),
+                button("再スキャン")
+                    .on_press_maybe((!self.workspace.is_empty()).then_some(Message::Rescan)),
+                button("すべて fetch")
+                    .on_press_maybe((!self.cards.is_empty()).then_some(Message::FetchAll)),
+                button("すべて選択")
+                    .on_press_maybe((!self.cards.is_empty()).then_some(Message::CheckAll)),
+                button("古いブランチを整理")
+                    .on_press_maybe((!self.cards.is_empty()).then_some(Message::CleanupOpen)),
+                pick_list(
+                    self.config.recent_workspaces.as_slice(),
+                    None::<Workspace>,
+                    Message::RecentWorkspaceSelected,
+                )
+                .placeholder("最近のワークスペース"),
+            ]
+            .spacing(10),
+            self.view_config_error(),
+            self.view_workspace(),
+            self.view_discovery_options(),
+            self.view_scan_progress(),
+            self.view_grid_options(),
+            self.view_bulk_actions(),
+            scrollable(Column::with_children(groups).spacing(30).padding(20))
+                .direction(self.grid_scroll_direction())
         ])
         .width(Fill)
         .height(Fill);
         stack![
             container,
-            if let Some(selected_card_id) = self.selected_card_id {
-                // ここで CardData を Element に変換する
-                let card = &self.cards[selected_card_id];
-
-                // 汎用 Drawer に詳細表示用ウィジェットを流し込む
+            if let Some(detail) = &self.detail
+                && let Some(card) = self.cards.iter().find(|x| x.id == detail.id)
+            {
+                // 汎用 Drawer に詳細のコンポーネントを流し込む
                 Drawer::new(
-                    format!(
-                        "詳細: {}",
-                        card.path.file_name().unwrap_or_default().to_string_lossy()
-                    ),
-                    self.view_card_details(),
+                    format!("詳細: {}", card.name()),
+                    detail.view(card).map(move |x| Message::Detail(card.id, x)),
                 )
                 .on_close(Message::DrawerClose)
+                // 履歴などはタブごとに自前でスクロールする
+                .scrollable(false)
                 .view()
+            } else if let Some(cleanup) = &self.cleanup {
+                Drawer::new("古いブランチの整理", cleanup.view().map(Message::Cleanup))
+                    .on_close(Message::CleanupClose)
+                    .scrollable(false)
+                    .view()
             } else {
                 text("").into()
-            }
+            },
+            self.view_dialog(),
         ]
         .into()
     }
 
-    pub fn update(&mut self, message: Message) {
+    /// 読み込んだリポジトリを監視し、変更があったカードだけを読み込み直す。
+    /// 表の列幅をドラッグしている間はカーソルも追う。
+    pub fn subscription(&self) -> Subscription<Message> {
+        Subscription::batch([
+            Subscription::batch(
+                self.cards
+                    .iter()
+                    .map(|card| watch::watch(card.id, card.path.clone(), card.kind)),
+            )
+            .map(Message::RepositoryChanged),
+            self.column_resize.subscription().map(Message::Table),
+        ])
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::FolderPick => {
-                let path = rfd::FileDialog::new()
-                    .set_title("フォルダを選択してください")
-                    .pick_folder();
-                if let Some(path) = path {
-                    self.selected_path = Some(path);
-                    self.cards_update();
-                }
-            }
-            Message::CardMessage(id, card_message) => {
-                match self.cards.iter_mut().find(|x| x.id == id) {
-                    Some(x) => {
-                        x.update(card_message.clone());
-
-                        match card_message {
-                            card::Message::ShowDetail => self.selected_card_id = Some(id),
-                            _ => (),
+                return Task::perform(
+                    rfd::AsyncFileDialog::new()
+                        .set_title("フォルダを選択してください")
+                        .pick_folder(),
+                    |handle| Message::FolderPicked(handle.map(|x| x.path().to_path_buf())),
+                );
+            }
+            Message::FolderPicked(path) => {
+                if let Some(path) = path
+                    && !self.workspace.roots.contains(&path)
+                {
+                    self.workspace.roots.push(path);
+                    return self.cards_update();
+                }
+            }
+            Message::RepositoryPick => {
+                return Task::perform(
+                    rfd::AsyncFileDialog::new()
+                        .set_title("リポジトリを選択してください")
+                        .pick_folder(),
+                    |handle| Message::RepositoryPicked(handle.map(|x| x.path().to_path_buf())),
+                );
+            }
+            Message::RepositoryPicked(path) => {
+                if let Some(path) = path
+                    && !self.workspace.pinned.contains(&path)
+                {
+                    self.workspace.pinned.push(path);
+                    return self.cards_update();
+                }
+            }
+            Message::RootRemove(index) => {
+                if index < self.workspace.roots.len() {
+                    self.workspace.roots.remove(index);
+                    return self.cards_update();
+                }
+            }
+            Message::PinnedRemove(index) => {
+                if index < self.workspace.pinned.len() {
+                    self.workspace.pinned.remove(index);
+                    return self.cards_update();
+                }
+            }
+            Message::RecentWorkspaceSelected(workspace) => {
+                return self.workspace_update(workspace);
+            }
+            Message::Rescan => return self.cards_update(),
+            Message::FetchAll => {
+                let tasks = self
+                    .cards
+                    .iter_mut()
+                    // 読み込めなかったカードと実行中のカードは飛ばす
+                    .filter(|x| x.error.is_none() && !x.remote.is_running())
+                    .map(|x| {
+                        let id = x.id;
+                        x.update(card::Message::Remote(remote::Message::Run(
+                            RemoteOperation::Fetch,
+                        )))
+                        .map(move |msg| Message::Card(id, msg))
+                    })
+                    .collect::<Vec<_>>();
+                return Task::batch(tasks);
+            }
+            Message::Scan(generation, event) => {
+                let Some(scan) = self.scan.as_mut() else {
+                    return Task::none();
+                };
+                // 中断済みのスキャンから届いた分は捨てる
+                if scan.generation != generation {
+                    return Task::none();
+                }
+                match event {
+                    ScanEvent::Discovered(total) => scan.total = Some(total),
+                    ScanEvent::Loaded(card) => {
+                        scan.loaded += 1;
+                        // 読み込み順に関わらず、探索順で並べる
+                        let index = self.cards.partition_point(|x| x.id < card.id);
+                        self.cards.insert(index, *card);
+                    }
+                }
+                if scan.total.is_some_and(|total| scan.loaded >= total) {
+                    self.scan = None;
+                }
+            }
+            Message::CardReloaded(card) => {
+                // 読み込み中にスキャンし直した場合は別のカードなので捨てる
+                if let Some(x) = self
+                    .cards
+                    .iter_mut()
+                    .find(|x| x.id == card.id && x.path == card.path)
+                {
+                    x.refresh(card);
+                    // 詳細を開いていれば、履歴や変更も取り直す
+                    if let Some(detail) = self.detail.as_mut().filter(|d| d.id == x.id) {
+                        let id = x.id;
+                        return detail
+                            .reload(Change::Metadata)
+                            .map(move |x| Message::Detail(id, x));
+                    }
+                }
+            }
+            Message::RepositoryChanged(watch::Event::Changed { id, change }) => {
+                if let Some(card) = self.cards.iter().find(|x| x.id == id) {
+                    return refresh_card(card, change);
+                }
+            }
+            Message::RepositoryChanged(watch::Event::Failed { id, error }) => {
+                if let Some(card) = self.cards.iter_mut().find(|x| x.id == id) {
+                    card.watch_error = Some(error);
+                }
+            }
+            Message::CardRefreshed(card, change) => {
+                // 読み込み中にスキャンし直した場合は別のカードなので捨てる
+                if let Some(x) = self
+                    .cards
+                    .iter_mut()
+                    .find(|x| x.id == card.id && x.path == card.path)
+                {
+                    x.refresh(card);
+                    if let Some(detail) = self.detail.as_mut().filter(|d| d.id == x.id) {
+                        let id = x.id;
+                        return detail.reload(change).map(move |x| Message::Detail(id, x));
+                    }
+                }
+            }
+            Message::MaxDepthChanged(value) => {
+                if let Ok(max_depth) = value.trim().parse::<usize>() {
+                    self.workspace.discovery.max_depth = max_depth;
+                }
+                self.max_depth_input = value;
+            }
+            Message::ExcludePatternsChanged(value) => {
+                self.workspace.discovery.exclude_patterns = value
+                    .split(',')
+                    .map(|x| x.trim())
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.to_owned())
+                    .collect();
+                self.exclude_pattern_errors =
+                    discovery::invalid_exclude_patterns(&self.workspace.discovery.exclude_patterns);
+                self.exclude_patterns_input = value;
+            }
+            Message::RespectGitignoreToggled(value) => {
+                self.workspace.discovery.respect_gitignore = value
+            }
+            Message::DescendIntoRepositoriesToggled(value) => {
+                self.workspace.discovery.descend_into_repositories = value
+            }
+            Message::Card(id, card_message) => {
+                if let Some(x) = self.cards.iter_mut().find(|x| x.id == id) {
+                    let task = x
+                        .update(card_message.clone())
+                        .map(move |msg| Message::Card(id, msg));
+
+                    match card_message {
+                        // 同じカードの詳細を開き直したときはタブなどをそのまま残す
+                        card::Message::ShowDetail => {
+                            if self.detail.as_ref().is_none_or(|d| d.id != id) {
+                                self.detail = Some(CardDetail::new(x));
+                            }
+                            if let Some(detail) = self.detail.as_mut() {
+                                return detail.load().map(move |x| Message::Detail(id, x));
+                            }
+                        }
+                        // ブランチを切り替えたら StatusDigest などを取り直す
+                        card::Message::Retry | card::Message::SwitchFinished(Ok(())) => {
+                            return reload_card(x);
                         }
+                        // ahead/behind やブランチを取り直す
+                        card::Message::Remote(remote::Message::Done) => {
+                            return Task::batch([task, refresh_card(x, Change::Metadata)]);
+                        }
+                        _ => (),
                     }
-                    _ => (),
+                    return task;
+                }
+            }
+            Message::DrawerClose => self.detail = None,
+            Message::CleanupOpen => {
+                // 読み込めなかったカードは対象にしない
+                let targets = self
+                    .cards
+                    .iter()
+                    .filter(|x| x.error.is_none())
+                    .map(|x| {
+                        let target = bulk::Target {
+                            id: x.id,
+                            name: x.name(),
+                            path: x.path.clone(),
+                            kind: x.kind,
+                        };
+                        (target, x.local_branches.clone())
+                    })
+                    .collect();
+                let (cleanup, task) = Cleanup::new(targets);
+                self.cleanup = Some(cleanup);
+                return task.map(Message::Cleanup);
+            }
+            Message::CleanupClose => self.cleanup = None,
+            Message::Cleanup(cleanup_message) => {
+                let Some(cleanup) = self.cleanup.as_mut() else {
+                    return Task::none();
                 };
+                let task = cleanup
+                    .update(cleanup_message.clone())
+                    .map(Message::Cleanup);
+                // 消したらブランチのセレクトなどを取り直す
+                if let cleanup::Message::Deleted(id, results) = cleanup_message
+                    && results.iter().any(|(_, x)| x.is_ok())
+                    && let Some(card) = self.cards.iter().find(|x| x.id == id)
+                {
+                    return Task::batch([task, refresh_card(card, Change::Metadata)]);
+                }
+                return task;
+            }
+            Message::CardChecked(id, value) => {
+                if value {
+                    self.checked_card_ids.insert(id);
+                } else {
+                    self.checked_card_ids.remove(&id);
+                }
+            }
+            // 絞り込みで見えているカードだけを選ぶ
+            Message::CheckAll => {
+                self.checked_card_ids =
+                    grid::arrange(&self.cards, &self.workspace.grid, &self.workspace.tags)
+                        .into_iter()
+                        .flat_map(|x| x.cards)
+                        .map(|x| x.id)
+                        .collect()
+            }
+            Message::GridFilterChanged(value) => {
+                self.workspace.grid.filter = value;
+                return self.schedule_save();
+            }
+            Message::SortKeySelected(key) => {
+                self.workspace.grid.sort = key;
+                self.save_workspace();
+            }
+            Message::SortReverseToggled => {
+                self.workspace.grid.reverse = !self.workspace.grid.reverse;
+                self.save_workspace();
+            }
+            Message::OnlyDirtyToggled(value) => {
+                self.workspace.grid.only_dirty = value;
+                self.save_workspace();
+            }
+            Message::OnlyUnpushedToggled(value) => {
+                self.workspace.grid.only_unpushed = value;
+                self.save_workspace();
+            }
+            Message::OnlyDetachedToggled(value) => {
+                self.workspace.grid.only_detached = value;
+                self.save_workspace();
+            }
+            Message::GroupBySelected(group_by) => {
+                self.workspace.grid.group_by = group_by;
+                self.save_workspace();
+            }
+            Message::LayoutSelected(layout) => {
+                self.workspace.grid.layout = layout;
+                self.save_workspace();
+            }
+            Message::Table(table_message) => {
+                if self
+                    .column_resize
+                    .update(table_message, &mut self.workspace.grid)
+                {
+                    self.save_workspace();
+                }
+            }
+            Message::TagChanged(path, value) => {
+                if value.trim().is_empty() {
+                    self.workspace.tags.remove(&path);
+                } else {
+                    self.workspace.tags.insert(path, value);
+                }
+                return self.schedule_save();
+            }
+            Message::SaveWorkspace(generation) => {
+                if self.save_scheduled == Some(generation) {
+                    self.save_workspace();
+                }
+            }
+            Message::UncheckAll => self.checked_card_ids.clear(),
+            Message::Bulk(bulk::Message::Run(operation)) => {
+                // 読み込めなかったカードは対象にしない
+                let checked = self.visible_checked_card_ids();
+                let targets = self
+                    .cards
+                    .iter()
+                    .filter(|x| checked.contains(&x.id) && x.error.is_none())
+                    .map(|x| bulk::Target {
+                        id: x.id,
+                        name: x
+                            .path
+                            .file_name()
+                            .unwrap_or(x.path.as_os_str())
+                            .to_string_lossy()
+                            .into_owned(),
+                        path: x.path.clone(),
+                        kind: x.kind,
+                    })
+                    .collect();
+                return self.bulk.start(operation, targets).map(Message::Bulk);
+            }
+            Message::Bulk(bulk_message) => {
+                let task = self.bulk.update(bulk_message.clone()).map(Message::Bulk);
+                if let bulk::Message::Finished(_, id, BulkOutcome::Succeeded(_)) = bulk_message
+                    && let Some(card) = self.cards.iter().find(|x| x.id == id)
+                {
+                    return Task::batch([task, refresh_card(card, Change::Metadata)]);
+                }
+                return task;
+            }
+            Message::Detail(id, detail_message) => {
+                let Some(detail) = self.detail.as_mut().filter(|d| d.id == id) else {
+                    return Task::none();
+                };
+                let task = detail
+                    .update(detail_message.clone())
+                    .map(move |x| Message::Detail(id, x));
+                let Some(card) = self.cards.iter().find(|x| x.id == detail.id) else {
+                    return task;
+                };
+                match detail_message {
+                    // ステージなどの結果をすぐにバッジへ反映する
+                    card_detail::Message::Changes(changes::Message::Finished(_)) => {
+                        return Task::batch([task, refresh_card(card, Change::WorkingTree)]);
+                    }
+                    // ブランチのセレクトや upstream のバッジを取り直す
+                    card_detail::Message::Branches(branches::Message::Finished(_)) => {
+                        return Task::batch([task, refresh_card(card, Change::Metadata)]);
+                    }
+                    // 最新のタグを取り直す
+                    card_detail::Message::Tags(tags::Message::Finished(_)) => {
+                        return Task::batch([task, refresh_card(card, Change::Metadata)]);
+                    }
+                    // 作業ツリーと stash の数が変わる
+                    card_detail::Message::Stashes(stashes::Message::Finished(_)) => {
+                        return Task::batch([task, refresh_card(card, Change::Metadata)]);
+                    }
+                    // コミットしたらカードと詳細を読み込み直す
+                    card_detail::Message::CommitEditor(commit_editor::Message::Finished(Ok(_))) => {
+                        return Task::batch([task, reload_card(card)]);
+                    }
+                    _ => return task,
+                }
             }
-            Message::DrawerClose => self.selected_card_id = None,
         }
+
+        Task::none()
     }
 
-    fn cards_update(self: &mut Self) {
-        // let mut ret: Vec<Card> = vec![];
+    /// ワークスペースを切り替えて読み込み直す
+    fn workspace_update(&mut self, workspace: Workspace) -> Task<Message> {
+        // 入力中の変更を、切り替える前のワークスペースに書いておく
+        if self.save_scheduled.is_some() {
+            self.save_workspace();
+        }
+        self.max_depth_input = workspace.discovery.max_depth.to_string();
+        self.exclude_patterns_input = workspace.discovery.exclude_patterns.join(", ");
+        self.exclude_pattern_errors =
+            discovery::invalid_exclude_patterns(&workspace.discovery.exclude_patterns);
+        self.workspace = workspace;
+        // 開いたワークスペースを最近使ったものの先頭に置く
+        if !self.workspace.is_empty() {
+            self.config.touch(&self.workspace);
+        }
+        self.cards_update()
+    }
 
-        if let Some(path) = self.selected_path.as_ref() {
-            if path.is_dir() && path.join(".git").is_dir() {
-                self.cards = vec![card(0, path.as_path())];
-                return;
-            }
+    /// スキャンをやり直す。実行中のスキャンは中断される。
+    fn cards_update(&mut self) -> Task<Message> {
+        self.detail = None;
+        self.cleanup = None;
+        self.checked_card_ids.clear();
+        self.cards = vec![];
+
+        if self.workspace.is_empty() {
+            self.scan = None;
+            return Task::none();
         }
 
-        let repos = match fs::read_dir(self.selected_path.clone().unwrap_or_default()) {
-            Ok(entries) => {
-                entries
-                    .flatten()
-                    .filter(|entry| {
-                        let path = entry.path();
+        // フォルダの追加・削除を保存する
+        self.save_workspace();
 
-                        // 2. ディレクトリかどうかを確認
-                        if path.is_dir() {
-                            // 3. その中に ".git" フォルダが存在するか確認
-                            if path.join(".git").is_dir() {
-                                return true;
-                            }
-                        }
-                        false
-                    })
-                    .collect()
+        let generation = self.scan.as_ref().map_or(0, |x| x.generation + 1);
+        let (task, handle) = Task::run(scan(self.workspace.clone()), move |x| {
+            Message::Scan(generation, x)
+        })
+        .abortable();
+        self.scan = Some(Scan {
+            generation,
+            _handle: handle.abort_on_drop(),
+            total: None,
+            loaded: 0,
+        });
+
+        task
+    }
+
+    /// 並べ替えや絞り込みの設定もワークスペースと一緒に保存する。
+    /// 開いているワークスペースを置き換えるだけで、最近使ったものは増やさない。
+    fn save_workspace(&mut self) {
+        self.save_scheduled = None;
+        if self.workspace.is_empty() {
+            return;
+        }
+        self.config.update_current(&self.workspace);
+        // 読み込めなかった設定ファイルは直してもらうまで残し、エラーも出したままにする
+        if self.config_load_failed {
+            return;
+        }
+        self.config_error = self
+            .config
+            .save()
+            .err()
+            .map(|err| format!("設定を保存できません: {}", err));
+    }
+
+    /// 入力のたびに書き込まないように、SAVE_DELAY の間に次の入力が無ければ保存する
+    fn schedule_save(&mut self) -> Task<Message> {
+        self.save_generation += 1;
+        let generation = self.save_generation;
+        self.save_scheduled = Some(generation);
+        Task::perform(delay(SAVE_DELAY), move |_| {
+            Message::SaveWorkspace(generation)
+        })
+    }
+
+    /// 選択したカードのうち、絞り込みで見えているもの
+    fn visible_checked_card_ids(&self) -> BTreeSet<usize> {
+        grid::arrange(&self.cards, &self.workspace.grid, &self.workspace.tags)
+            .into_iter()
+            .flat_map(|x| x.cards)
+            .map(|x| x.id)
+            .filter(|x| self.checked_card_ids.contains(x))
+            .collect()
+    }
+
+    fn view_config_error(&self) -> Element<'_, Message> {
+        match &self.config_error {
+            Some(error) => text(error).size(12).style(text::danger).into(),
+            None => space().into(),
+        }
+    }
+
+    fn view_scan_progress(&self) -> Element<'_, Message> {
+        match &self.scan {
+            Some(Scan {
+                total: Some(total),
+                loaded,
+                ..
+            }) => row![
+                progress_bar(0.0..=*total as f32, *loaded as f32).length(200),
+                text(format!("読み込み中: {} / {}", loaded, total)),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+            .into(),
+            Some(_) => text("リポジトリを探索中...").into(),
+            None => text("").into(),
+        }
+    }
+
+    /// 表は横にも長くなるので横にもスクロールする（カードは折り返す）
+    fn grid_scroll_direction(&self) -> scrollable::Direction {
+        match self.workspace.grid.layout {
+            Layout::Table => scrollable::Direction::Both {
+                vertical: scrollable::Scrollbar::default(),
+                horizontal: scrollable::Scrollbar::default(),
+            },
+            _ => scrollable::Direction::Vertical(scrollable::Scrollbar::default()),
+        }
+    }
+
+    /// 選んだ表示形式でカードを並べる
+    fn view_cards<'a>(&'a self, cards: Vec<&'a Card>) -> Element<'a, Message> {
+        match self.workspace.grid.layout {
+            Layout::Grid => Row::with_children(cards.into_iter().map(|card| self.view_card(card)))
+                .spacing(20)
+                .wrap()
+                .into(),
+            Layout::List => Column::with_children(cards.into_iter().map(|card| {
+                let id = card.id;
+                Element::from(
+                    row![
+                        self.view_card_check(card),
+                        card.view_compact().map(move |msg| Message::Card(id, msg)),
+                    ]
+                    .spacing(8)
+                    .align_y(iced::Alignment::Center),
+                )
+            }))
+            .spacing(6)
+            .into(),
+            Layout::Table => {
+                let widths = &self.workspace.grid.column_widths;
+                let header = row![
+                    // チェックボックスの列の分だけずらす
+                    space().width(TABLE_CHECK_WIDTH),
+                    table::view_header(&self.workspace.grid).map(Message::Table),
+                ];
+                let rows = cards.into_iter().map(|card| {
+                    let id = card.id;
+                    Element::from(row![
+                        container(self.view_card_check(card)).width(TABLE_CHECK_WIDTH),
+                        card.view_table_row(widths)
+                            .map(move |msg| Message::Card(id, msg)),
+                    ])
+                });
+
+                column![header, Column::with_children(rows).spacing(2)]
+                    .spacing(4)
+                    .into()
             }
-            Err(_) => vec![],
-        };
+        }
+    }
 
-        self.cards = repos
-            .iter()
-            .enumerate()
-            .map(|(id, x)| card(id, x.path().as_path()))
-            .collect();
+    fn view_card_check(&self, card: &Card) -> Element<'_, Message> {
+        let id = card.id;
+        checkbox(self.checked_card_ids.contains(&id))
+            .on_toggle(move |value| Message::CardChecked(id, value))
+            .into()
+    }
+
+    /// 選択用のチェックボックスとタグ入力を添えたカード
+    fn view_card<'a>(&'a self, card: &'a Card) -> Element<'a, Message> {
+        let id = card.id;
+        let mut header = row![self.view_card_check(card)]
+            .spacing(4)
+            .align_y(iced::Alignment::Center);
+        // タグはタグでグループ分けしているときだけ編集できる
+        if self.workspace.grid.group_by == GroupBy::Tag {
+            let path = card.path.clone();
+            header = header.push(
+                text_input(
+                    "タグ",
+                    self.workspace
+                        .tags
+                        .get(&card.path)
+                        .map_or("", |x| x.as_str()),
+                )
+                .on_input(move |value| Message::TagChanged(path.clone(), value))
+                .size(12)
+                .width(120),
+            );
+        }
+
+        column![
+            header,
+            // 子の view を map して親の Message に変換
+            card.view().map(move |msg| Message::Card(id, msg)),
+        ]
+        .spacing(4)
+        .into()
     }
 
-    fn view_card_details(&self) -> Element<'_, Message> {
-        let card = self
-            .cards
+    fn view_grid_options(&self) -> Element<'_, Message> {
+        let grid = &self.workspace.grid;
+        row![
+            text_input("名前・パス・ブランチで絞り込み", &grid.filter)
+                .on_input(Message::GridFilterChanged)
+                .width(250),
+            text("並び順"),
+            pick_list(SortKey::ALL, Some(grid.sort), Message::SortKeySelected),
+            button(if grid.reverse { "↑ 逆順" } else { "↓" })
+                .on_press(Message::SortReverseToggled)
+                .style(button::secondary),
+            checkbox(grid.only_dirty)
+                .label("変更ありのみ")
+                .on_toggle(Message::OnlyDirtyToggled),
+            checkbox(grid.only_unpushed)
+                .label("未 push のみ")
+                .on_toggle(Message::OnlyUnpushedToggled),
+            checkbox(grid.only_detached)
+                .label("detached のみ")
+                .on_toggle(Message::OnlyDetachedToggled),
+            pick_list(GroupBy::ALL, Some(grid.group_by), Message::GroupBySelected),
+            pick_list(Layout::ALL, Some(grid.layout), Message::LayoutSelected),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into()
+    }
+
+    /// カードを選択しているか結果があるときだけ、一括操作のバーを出す
+    fn view_bulk_actions(&self) -> Element<'_, Message> {
+        if self.checked_card_ids.is_empty() && !self.bulk.has_results() {
+            return text("").into();
+        }
+
+        // 絞り込みで隠れたカードは一括操作の対象にしない
+        column![
+            self.bulk
+                .view(self.visible_checked_card_ids().len())
+                .map(Message::Bulk),
+            button("選択を解除")
+                .on_press_maybe((!self.checked_card_ids.is_empty()).then_some(Message::UncheckAll))
+                .style(button::text),
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    /// 確認待ちのカードや詳細があればダイアログを重ねる
+    fn view_dialog(&self) -> Element<'_, Message> {
+        if let Some(dialog) = self.cleanup.as_ref().and_then(|x| x.view_dialog()) {
+            return dialog.map(Message::Cleanup);
+        }
+        if let Some(detail) = &self.detail
+            && let Some(dialog) = detail.view_dialog()
+        {
+            let id = detail.id;
+            return dialog.map(move |x| Message::Detail(id, x));
+        }
+        self.cards
             .iter()
-            .find(|x| {
-                if let Some(card_id) = self.selected_card_id {
-                    card_id == x.id
-                } else {
-                    false
-                }
+            .find_map(|card| {
+                let id = card.id;
+                card.view_dialog()
+                    .map(|x| x.map(move |msg| Message::Card(id, msg)))
             })
-            .expect("failed to find selected card");
+            .unwrap_or_else(|| text("").into())
+    }
+
+    fn view_workspace(&self) -> Element<'_, Message> {
+        if self.workspace.is_empty() {
+            return text("(未選択)").into();
+        }
+
+        let roots = self.workspace.roots.iter().enumerate().map(|(index, x)| {
+            Element::from(
+                row![
+                    text(x.to_string_lossy()),
+                    button("✕")
+                        .on_press(Message::RootRemove(index))
+                        .style(button::text),
+                ]
+                .align_y(iced::Alignment::Center),
+            )
+        });
+        let pinned = self.workspace.pinned.iter().enumerate().map(|(index, x)| {
+            Element::from(
+                row![
+                    text(format!("📌 {}", x.to_string_lossy())),
+                    button("✕")
+                        .on_press(Message::PinnedRemove(index))
+                        .style(button::text),
+                ]
+                .align_y(iced::Alignment::Center),
+            )
+        });
+
+        Column::with_children(roots.chain(pinned)).into()
+    }
 
-        let commits = card
-            .repository
-            .list_commits()
-            .expect("failed to get commits");
+    fn view_discovery_options(&self) -> Element<'_, Message> {
+        let options = row![
+            text("探索の深さ"),
+            text_input("4", &self.max_depth_input)
+                .on_input(Message::MaxDepthChanged)
+                .width(60),
+            text("除外パターン"),
+            text_input("vendor/, *.bak", &self.exclude_patterns_input)
+                .on_input(Message::ExcludePatternsChanged)
+                .width(250),
+            checkbox(self.workspace.discovery.respect_gitignore)
+                .label(".gitignore に従う")
+                .on_toggle(Message::RespectGitignoreToggled),
+            checkbox(self.workspace.discovery.descend_into_repositories)
+                .label("リポジトリ内も探索")
+                .on_toggle(Message::DescendIntoRepositoriesToggled),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center);
 
-        let rows = commits
+        let errors = self
+            .exclude_pattern_errors
             .iter()
-            .map(|x| {
-                let datetime: DateTime<Utc> = x.timestamp.into();
-                let datetime_str = datetime.format("%Y-%m-%d %H:%M:%S").to_string();
-
-                // 各行の Column
-                container(column![
-                    text(x.commit_id.to_string()),
-                    text(x.summary.to_owned()),
-                    text(x.author.to_owned()),
-                    text(datetime_str),
-                ])
-                .padding(10)
-                .into()
-            })
-            .collect::<Vec<_>>();
+            .map(|x| text(x).size(12).style(text::danger).into());
+        column![options, Column::with_children(errors)]
+            .spacing(4)
+            .into()
+    }
+}
+
+/// カードを読み込み直す
+fn reload_card(card: &Card) -> Task<Message> {
+    let id = card.id;
+    let path = card.path.clone();
+    let kind = card.kind;
+    Task::perform(
+        async move { self::card(id, &path, kind) },
+        Message::CardReloaded,
+    )
+}
+
+/// 監視で変更を検知したカードの状態を読み込み直す
+fn refresh_card(card: &Card, change: Change) -> Task<Message> {
+    let id = card.id;
+    let path = card.path.clone();
+    let kind = card.kind;
+    Task::perform(async move { self::card(id, &path, kind) }, move |card| {
+        Message::CardRefreshed(card, change)
+    })
+}
+
+/// iced の thread-pool executor にはタイマーが無いので、スレッドで待つ
+async fn delay(duration: Duration) {
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || {
+        thread::sleep(duration);
+        let _ = sender.send(());
+    });
+    let _ = receiver.await;
+}
+
+/// ワークスペース内のリポジトリを探索し、並列に読み込む
+fn scan(workspace: Workspace) -> impl Stream<Item = ScanEvent> {
+    let (sender, receiver) = mpsc::unbounded();
+
+    thread::spawn(move || {
+        let repos =
+            discovery::discover_all(&workspace.roots, &workspace.pinned, &workspace.discovery);
+        if sender
+            .unbounded_send(ScanEvent::Discovered(repos.len()))
+            .is_err()
+        {
+            return;
+        }
+
+        let next = AtomicUsize::new(0);
+        let workers = thread::available_parallelism().map_or(4, |x| x.get());
+        thread::scope(|scope| {
+            for _ in 0..workers.min(repos.len()) {
+                scope.spawn(|| {
+                    loop {
+                        let id = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(repo) = repos.get(id) else {
+                            break;
+                        };
+                        let card = card(id, &repo.path, repo.kind);
+                        // 受信側が drop されていれば中断されたということ
+                        if sender
+                            .unbounded_send(ScanEvent::Loaded(Box::new(card)))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+    });
+
+    receiver
+}
 
-        column(rows).into()
+fn card(id: usize, path: &Path, kind: RepositoryKind) -> Card {
+    match load_card(id, path, kind) {
+        Ok(card) => card,
+        Err(err) => Card::failed(id, path.to_path_buf(), kind, err),
     }
 }
 
-fn card(id: usize, path: &Path) -> Card {
-    let repository = repository(path).expect("failed to get repository");
+fn load_card(id: usize, path: &Path, kind: RepositoryKind) -> Result<Card, String> {
+    let repository = repository(path).map_err(|err| format!("リポジトリを開けません: {}", err))?;
 
-    let status_digest = match repository.status_digest() {
-        Ok(a) => Some(a),
-        Err(_) => None,
-    };
-    let local_branches = repository.local_branches().expect("failed to get branches");
+    let status_digest = repository.status_digest().ok();
+    let local_branches = repository
+        .local_branches()
+        .map_err(|err| format!("ブランチを取得できません: {}", err))?;
     let options = local_branches
         .iter()
         .enumerate()
@@ -214,13 +1052,24 @@ fn card(id: usize, path: &Path) -> Card {
             label: x.name.to_owned(),
         })
         .collect();
-    let branch_selector = Select::new(options, "test".to_owned());
+    let branch_selector = Select::new(options, "ブランチ".to_owned());
 
-    Card {
+    let mut card = Card::new(
         id,
-        path: path.to_path_buf(),
+        path.to_path_buf(),
+        kind,
         repository,
         status_digest,
         branch_selector,
+    );
+    // 変更件数が取れなくてもカード自体は表示する
+    card.working_tree_status = working_tree_status(path, kind).unwrap_or_default();
+    card.last_commit_author = last_commit_author(path, kind).ok();
+    card.local_branches = local_branches.iter().map(|x| x.name.to_owned()).collect();
+    card.latest_tag = latest_tag(path, kind).ok().flatten();
+    card.stash_count = stash_count(path, kind).unwrap_or_default();
+    if kind != RepositoryKind::Bare {
+        card.upstream_status = upstream_status(path, kind).ok();
     }
+    Ok(card)
 }
@@ -0,0 +1,324 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use iced::Subscription;
+use iced::futures::channel::{mpsc, oneshot};
+use iced::futures::{Stream, StreamExt, future, stream};
+use ignore::WalkBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{DebounceEventResult, Debouncer, new_debouncer};
+
+use crate::app::discovery::{METADATA_DIR_NAMES, RepositoryKind, is_repository};
+use crate::app::vcs::git;
+
+/// 連続した変更をまとめる間隔
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 変更があったときに読み直す git のメタデータ（.git からの相対パスの先頭）
+const GIT_METADATA: [&str; 7] = [
+    "HEAD",
+    "index",
+    "packed-refs",
+    "refs",
+    "MERGE_HEAD",
+    "REBASE_HEAD",
+    "CHERRY_PICK_HEAD",
+];
+
+/// 検知した変更の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    /// 作業ツリーのファイルだけが変わった
+    WorkingTree,
+    /// index だけが変わった（ステージなど）
+    Index,
+    /// HEAD・refs・index・op log が変わった（コミットやブランチの切り替えなど）
+    Metadata,
+}
+
+/// リポジトリ id ごとの通知
+#[derive(Debug, Clone)]
+pub enum Event {
+    Changed {
+        id: usize,
+        change: Change,
+    },
+    /// 監視できなかった（監視の上限に達したなど）
+    Failed {
+        id: usize,
+        error: String,
+    },
+}
+
+/// 監視のコールバックから stream に渡すもの
+enum Notice {
+    Changed {
+        change: Option<Change>,
+        /// 新しくできたので監視に加えるディレクトリ
+        dirs: Vec<PathBuf>,
+    },
+    Failed(String),
+}
+
+/// 監視するリポジトリ。Subscription の識別に使う。
+#[derive(Debug, Clone, Hash)]
+struct Target {
+    id: usize,
+    path: PathBuf,
+    kind: RepositoryKind,
+}
+
+/// リポジトリの作業ツリーとメタデータを監視する。
+/// 同じ id・パスの Subscription は iced が使い回すので、毎回呼んでよい。
+pub fn watch(id: usize, path: PathBuf, kind: RepositoryKind) -> Subscription<Event> {
+    Subscription::run_with(Target { id, path, kind }, watch_stream)
+}
+
+fn watch_stream(target: &Target) -> impl Stream<Item = Event> + use<> {
+    let target = target.clone();
+    let id = target.id;
+    let (sender, receiver) = mpsc::unbounded();
+    // git rev-parse や作業ツリーの走査は時間がかかるので、iced のスレッドでは行わず
+    // 別のスレッドで監視を登録する
+    let (started, setup) = oneshot::channel();
+    thread::spawn(move || {
+        let _ = started.send(start(&target, sender));
+    });
+
+    stream::once(async move {
+        let (mut debouncer, errors) = setup
+            .await
+            .unwrap_or_else(|_| (None, vec!["監視を始められません".to_owned()]));
+        // 最初の失敗はまとめて一度だけ知らせる
+        let failed = (!errors.is_empty()).then(|| Event::Failed {
+            id,
+            error: errors.join("\n"),
+        });
+        // ストリームが drop されるまで監視を続ける
+        stream::iter(failed).chain(receiver.filter_map(move |notice| {
+            let event = match notice {
+                Notice::Changed { change, dirs } => {
+                    if let Some(debouncer) = &mut debouncer {
+                        for dir in &dirs {
+                            // 作ってすぐ消されたディレクトリもあるので、失敗は無視する
+                            let _ = debouncer.watcher().watch(dir, RecursiveMode::NonRecursive);
+                        }
+                    }
+                    change.map(|change| Event::Changed { id, change })
+                }
+                Notice::Failed(error) => Some(Event::Failed { id, error }),
+            };
+            future::ready(event)
+        }))
+    })
+    .flatten()
+}
+
+/// 監視を登録し、届いた変更を sender に渡す。登録できなかったディレクトリはエラーとして返す。
+fn start(
+    target: &Target,
+    sender: mpsc::UnboundedSender<Notice>,
+) -> (Option<Debouncer<RecommendedWatcher>>, Vec<String>) {
+    let filter = Filter::new(&target.path, target.kind);
+    let roots = filter.roots();
+
+    let mut errors = vec![];
+    let debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+        let notice = match result {
+            Ok(events) => Notice::Changed {
+                change: events.iter().filter_map(|x| filter.classify(&x.path)).max(),
+                dirs: events
+                    .iter()
+                    .filter(|x| filter.is_new_dir(&x.path))
+                    .flat_map(|x| filter.walk(&x.path))
+                    .collect(),
+            },
+            Err(err) => Notice::Failed(err.to_string()),
+        };
+        let _ = sender.unbounded_send(notice);
+    })
+    .map(|mut debouncer| {
+        for (root, mode) in &roots {
+            if let Err(err) = debouncer.watcher().watch(root, *mode) {
+                errors.push(format!("{} を監視できません: {}", root.display(), err));
+            }
+        }
+        debouncer
+    });
+    match debouncer {
+        Ok(x) => (Some(x), errors),
+        Err(err) => {
+            errors.push(format!(
+                "{} を監視できません: {}",
+                target.path.display(),
+                err
+            ));
+            (None, errors)
+        }
+    }
+}
+
+/// 監視で届いたパスを変更の種類に振り分ける
+struct Filter {
+    /// 作業ツリーのルート（bare なら None）
+    work_tree: Option<PathBuf>,
+    /// git のメタデータディレクトリ（worktree では共通ディレクトリも含む）
+    git_dirs: Vec<PathBuf>,
+    jj_dir: Option<PathBuf>,
+    gitignore: Gitignore,
+}
+
+impl Filter {
+    fn new(path: &Path, kind: RepositoryKind) -> Self {
+        let (work_tree, git_dirs) = match kind {
+            RepositoryKind::Bare => (None, vec![path.to_path_buf()]),
+            // .git はファイルなので、実体のディレクトリを git に聞く
+            RepositoryKind::Worktree | RepositoryKind::GitFile => {
+                (Some(path.to_path_buf()), resolve_git_dirs(path))
+            }
+            RepositoryKind::Git | RepositoryKind::Colocated => {
+                (Some(path.to_path_buf()), vec![path.join(".git")])
+            }
+            RepositoryKind::Jj => (Some(path.to_path_buf()), vec![]),
+        };
+        let jj_dir = kind.is_jj().then(|| path.join(".jj"));
+
+        let mut builder = GitignoreBuilder::new(path);
+        builder.add(path.join(".gitignore"));
+        builder.add(path.join(".git").join("info").join("exclude"));
+        let gitignore = builder.build().unwrap_or_else(|_| Gitignore::empty());
+
+        Self {
+            work_tree,
+            git_dirs,
+            jj_dir,
+            gitignore,
+        }
+    }
+
+    /// 監視を登録するディレクトリ。
+    /// 作業ツリーは無視されていないディレクトリを一つずつ、メタデータは読み直すものだけを監視する。
+    fn roots(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let mut roots = vec![];
+        if let Some(work_tree) = &self.work_tree {
+            roots.extend(
+                self.walk(work_tree)
+                    .into_iter()
+                    .map(|x| (x, RecursiveMode::NonRecursive)),
+            );
+        }
+        for dir in &self.git_dirs {
+            roots.push((dir.clone(), RecursiveMode::NonRecursive));
+            roots.push((dir.join("refs"), RecursiveMode::Recursive));
+        }
+        if let Some(dir) = &self.jj_dir {
+            roots.push((dir.join("repo").join("op_heads"), RecursiveMode::Recursive));
+        }
+
+        // worktree の gitdir が共通ディレクトリと重なることがある
+        let mut seen = vec![];
+        roots.retain(|(x, _)| {
+            let keep = x.is_dir() && !seen.contains(x);
+            seen.push(x.clone());
+            keep
+        });
+        roots
+    }
+
+    /// dir 以下の無視されていないディレクトリ。メタデータと入れ子のリポジトリには入らない。
+    fn walk(&self, dir: &Path) -> Vec<PathBuf> {
+        let Some(work_tree) = self.work_tree.clone() else {
+            return vec![];
+        };
+        WalkBuilder::new(dir)
+            .hidden(false)
+            .parents(true)
+            .require_git(false)
+            .filter_entry(move |entry| {
+                entry.file_type().is_some_and(|x| x.is_dir())
+                    && !METADATA_DIR_NAMES.iter().any(|x| entry.file_name() == *x)
+                    && (entry.path() == work_tree || !is_repository(entry.path()))
+            })
+            .build()
+            .flatten()
+            .map(|x| x.into_path())
+            .collect()
+    }
+
+    /// 作業ツリーに新しくできた、監視に加えるべきディレクトリか
+    fn is_new_dir(&self, path: &Path) -> bool {
+        path.is_dir() && self.classify(path) == Some(Change::WorkingTree) && !is_repository(path)
+    }
+
+    /// 無視してよい変更なら None
+    fn classify(&self, path: &Path) -> Option<Change> {
+        // ロックファイルは書き込みの途中経過なので見ない
+        if path.extension().is_some_and(|x| x == "lock") {
+            return None;
+        }
+
+        let mut in_metadata = false;
+        for dir in &self.git_dirs {
+            if let Ok(relative) = path.strip_prefix(dir) {
+                in_metadata = true;
+                let first = relative.components().next();
+                if first.is_some_and(|x| x.as_os_str() == "index") {
+                    return Some(Change::Index);
+                }
+                if first.is_some_and(|x| GIT_METADATA.iter().any(|y| x.as_os_str() == *y)) {
+                    return Some(Change::Metadata);
+                }
+            }
+        }
+        if let Some(dir) = &self.jj_dir
+            && let Ok(relative) = path.strip_prefix(dir)
+        {
+            // 作業コピーの状態は jj を実行するたびに書き換わるので、op log だけを見る
+            return relative
+                .starts_with(Path::new("repo").join("op_heads"))
+                .then_some(Change::Metadata);
+        }
+        if in_metadata {
+            return None;
+        }
+
+        let work_tree = self.work_tree.as_ref()?;
+        if !path.starts_with(work_tree) || path == work_tree.as_path() {
+            return None;
+        }
+        if self
+            .gitignore
+            .matched_path_or_any_parents(path, path.is_dir())
+            .is_ignore()
+        {
+            return None;
+        }
+        Some(Change::WorkingTree)
+    }
+}
+
+/// worktree やサブモジュールの gitdir と共通ディレクトリ
+fn resolve_git_dirs(path: &Path) -> Vec<PathBuf> {
+    let Ok(output) = git(
+        path,
+        &[
+            "rev-parse",
+            "--path-format=absolute",
+            "--git-dir",
+            "--git-common-dir",
+        ],
+    ) else {
+        return vec![];
+    };
+
+    let mut dirs = Vec::<PathBuf>::new();
+    for line in output.lines().filter(|x| !x.is_empty()) {
+        let dir = PathBuf::from(line);
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
@@ -1,5 +1,5 @@
 // app.rs
-use iced::Element; // 子をインポート
+use iced::{Element, Subscription, Task}; // 子をインポート
 
 use crate::app::views::dashboard;
 
@@ -14,9 +14,9 @@ pub enum Message {
 }
 
 impl Window {
-    pub fn new() -> Self {
-        let dashboard = dashboard::Dashboard::new();
-        Self { dashboard }
+    pub fn new() -> (Self, Task<Message>) {
+        let (dashboard, task) = dashboard::Dashboard::new();
+        (Self { dashboard }, task.map(Message::Search))
     }
 
     pub fn view(&self) -> Element<'_, Message> {
@@ -24,11 +24,15 @@ impl Window {
         self.dashboard.view().map(Message::Search)
     }
 
-    pub fn update(&mut self, message: Message) {
+    pub fn subscription(&self) -> Subscription<Message> {
+        self.dashboard.subscription().map(Message::Search)
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::Search(sub_msg) => {
-                // 子の update を呼び出す
-                let _ = self.dashboard.update(sub_msg);
+                // 子の update を呼び出し、返ってきた Task を親の Message に変換
+                self.dashboard.update(sub_msg).map(Message::Search)
             }
         }
     }
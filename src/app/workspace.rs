@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::discovery::DiscoveryOptions;
+
+/// 最近使ったワークスペースとして残す数
+const RECENT_WORKSPACES_LIMIT: usize = 10;
+
+/// 探索対象のまとまり
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    /// 探索するルートフォルダ
+    pub roots: Vec<PathBuf>,
+    /// 探索とは別に常に表示するリポジトリ
+    pub pinned: Vec<PathBuf>,
+    pub discovery: DiscoveryOptions,
+    /// カードの並べ方
+    pub grid: GridOptions,
+    /// リポジトリごとに付けたタグ（グループ分けに使う）
+    pub tags: BTreeMap<PathBuf, String>,
+}
+
+/// カードの並び順
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortKey {
+    #[default]
+    Name,
+    /// 新しいものから
+    LastCommit,
+    /// 変更の多いものから
+    Dirty,
+    /// ahead/behind の多いものから
+    AheadBehind,
+    Kind,
+    Branch,
+    Author,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 7] = [
+        SortKey::Name,
+        SortKey::LastCommit,
+        SortKey::Dirty,
+        SortKey::AheadBehind,
+        SortKey::Kind,
+        SortKey::Branch,
+        SortKey::Author,
+    ];
+}
+
+impl std::fmt::Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            SortKey::Name => "名前",
+            SortKey::LastCommit => "最終コミット",
+            SortKey::Dirty => "変更の多さ",
+            SortKey::AheadBehind => "ahead/behind",
+            SortKey::Kind => "種類",
+            SortKey::Branch => "ブランチ",
+            SortKey::Author => "作者",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// カードのグループ分け
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GroupBy {
+    #[default]
+    None,
+    ParentDirectory,
+    Tag,
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 3] = [GroupBy::None, GroupBy::ParentDirectory, GroupBy::Tag];
+}
+
+impl std::fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            GroupBy::None => "グループなし",
+            GroupBy::ParentDirectory => "親フォルダ",
+            GroupBy::Tag => "タグ",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// カードの並べ替え・絞り込み・グループ分けの設定
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GridOptions {
+    pub sort: SortKey,
+    /// 並び順を逆にする
+    pub reverse: bool,
+    /// 名前・パス・ブランチに含まれる文字列
+    pub filter: String,
+    pub only_dirty: bool,
+    /// ahead があるか upstream が無いもの
+    pub only_unpushed: bool,
+    pub only_detached: bool,
+    pub group_by: GroupBy,
+    pub layout: Layout,
+    pub column_widths: ColumnWidths,
+}
+
+/// カードの表示形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Layout {
+    #[default]
+    Grid,
+    /// 一行ずつの一覧
+    List,
+    Table,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 3] = [Layout::Grid, Layout::List, Layout::Table];
+}
+
+impl std::fmt::Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Layout::Grid => "カード",
+            Layout::List => "一覧",
+            Layout::Table => "表",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// 表の列幅
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnWidths {
+    pub repository: f32,
+    pub kind: f32,
+    pub branch: f32,
+    pub status: f32,
+    pub upstream: f32,
+    pub last_commit: f32,
+    pub author: f32,
+}
+
+impl Default for ColumnWidths {
+    fn default() -> Self {
+        Self {
+            repository: 200.0,
+            kind: 110.0,
+            branch: 160.0,
+            status: 220.0,
+            upstream: 110.0,
+            last_commit: 300.0,
+            author: 140.0,
+        }
+    }
+}
+
+impl Workspace {
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty() && self.pinned.is_empty()
+    }
+
+    /// 同じフォルダ群を指しているか（探索設定の違いは問わない）
+    pub fn same_targets(&self, other: &Workspace) -> bool {
+        self.roots == other.roots && self.pinned == other.pinned
+    }
+}
+
+impl std::fmt::Display for Workspace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self
+            .roots
+            .iter()
+            .chain(self.pinned.iter())
+            .map(|x| {
+                x.file_name()
+                    .unwrap_or(x.as_os_str())
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+/// 設定ファイルの内容
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// 最近使ったワークスペース（先頭が最後に使ったもの）
+    pub recent_workspaces: Vec<Workspace>,
+}
+
+impl Config {
+    /// 設定ファイルを読み込む。無い場合は既定値、読めない・壊れている場合はエラー。
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = config_path() else {
+            return Ok(Self::default());
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => Ok(content),
+            // まだ保存していなければ既定の設定にする
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => Err(err.to_string()),
+        };
+        content
+            .and_then(|x| toml::from_str(&x).map_err(|err| err.to_string()))
+            .map_err(|err| format!("設定ファイルを読み込めません: {} ({})", path.display(), err))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = config_path().ok_or("設定ディレクトリが見つかりません")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let content = toml::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(&path, content).map_err(|err| err.to_string())
+    }
+
+    /// 最後に使ったワークスペース
+    pub fn last_workspace(&self) -> Option<&Workspace> {
+        self.recent_workspaces.first()
+    }
+
+    /// 開いた・復元した workspace を最近使ったものの先頭に置く
+    pub fn touch(&mut self, workspace: &Workspace) {
+        self.recent_workspaces
+            .retain(|x| !x.same_targets(workspace));
+        self.recent_workspaces.insert(0, workspace.clone());
+        self.recent_workspaces.truncate(RECENT_WORKSPACES_LIMIT);
+    }
+
+    /// 開いているワークスペース（先頭）を編集後の内容で置き換える。
+    /// フォルダの追加・削除や表示設定の変更のたびに履歴が増えないようにする。
+    pub fn update_current(&mut self, workspace: &Workspace) {
+        if !self.recent_workspaces.is_empty() {
+            self.recent_workspaces.remove(0);
+        }
+        self.touch(workspace);
+    }
+}
+
+/// $XDG_CONFIG_HOME/skjra/config.toml
+fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|x| x.join("skjra").join("config.toml"))
+}